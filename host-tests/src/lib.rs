@@ -0,0 +1,80 @@
+//! The firmware modules which can run without the hardware, built for the host. The peripherals
+//! they use are replaced by the crates in `stubs`. Their `#[cfg(test)]` modules are run by
+//! `cargo test` in this directory.
+
+// Only part of the firmware is built here, so items used by the rest of it look unused.
+#![allow(dead_code, unused_macros)]
+// With `std`, the float methods `FloatExt` provides are inherent, and some imports are only used by
+// the firmware, so they look unused.
+#![allow(unused_imports)]
+// The firmware's toolchain is older than `is_multiple_of`.
+#![allow(clippy::manual_is_multiple_of)]
+
+extern crate alloc;
+
+#[path = "../../src/app"]
+pub mod app {
+    pub mod types {
+        /// Stands in for the executor's store data, which needs the hardware.
+        pub struct Env;
+
+        /// Stands in for the executor, which needs the hardware. The constants are the same as the
+        /// firmware's.
+        pub mod wasm {
+            pub const WASM_MEMORY_LIMIT: usize = 1 << 20; // 1 MiB
+            pub const WASM_MEMORY_BUDGET: usize = 4 << 20; // 4 MiB
+            pub const WASM_FUEL_PER_TICK: u64 = 1 << 24;
+
+            #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+            pub struct Limits {
+                pub memory_size: usize,
+                pub fuel_per_tick: u64,
+            }
+
+            impl Limits {
+                pub fn new(memory_size: usize, fuel_per_tick: u64) -> Self {
+                    Self {
+                        memory_size,
+                        fuel_per_tick,
+                    }
+                }
+            }
+        }
+
+        pub mod error;
+        pub mod io;
+        pub mod manifest;
+        pub mod namespace;
+
+        pub use io::*;
+    }
+
+    pub mod syscall {
+        pub mod signature;
+    }
+}
+
+#[path = "../../src/allocator.rs"]
+pub mod allocator;
+
+#[path = "../../src/driver"]
+pub mod driver {
+    pub mod lcd;
+}
+
+#[path = "../../src/fs/mod.rs"]
+pub mod fs;
+
+#[path = "../../src/float.rs"]
+pub mod float;
+
+#[path = "../../src/macros.rs"]
+pub(crate) mod macros;
+
+#[path = "../../src/widget/mod.rs"]
+pub mod widget;
+
+/// The same as the firmware's, which is defined in `main.rs`.
+fn log_init(task: &str) {
+    log::debug!("{task} initialized");
+}
@@ -0,0 +1,10 @@
+//! The attribute macros `esp-hal` re-exports. They only place items in memory sections on the chip,
+//! so on the host they leave the item as it is.
+
+use proc_macro::TokenStream;
+
+/// Places a function or static in RAM on the chip. On the host, memory is all the same.
+#[proc_macro_attribute]
+pub fn ram(_args: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
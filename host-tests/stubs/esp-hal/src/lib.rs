@@ -0,0 +1,280 @@
+//! The parts of `esp-hal` used by the filesystem, the display driver and the heap, implemented in
+//! software so they can run on the host. The display's peripherals only need to exist, since the
+//! display task never runs in the tests.
+
+#![no_std]
+
+/// The blocking driver mode.
+pub struct Blocking;
+
+pub mod rng {
+    /// A xorshift generator in place of the hardware RNG. It's seeded so tests are repeatable.
+    pub struct Rng(u32);
+
+    impl Rng {
+        pub fn new(seed: u32) -> Self {
+            Self(seed.max(1))
+        }
+
+        pub fn random(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        pub fn read(&mut self, buffer: &mut [u8]) {
+            for chunk in buffer.chunks_mut(4) {
+                let bytes = self.random().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+    }
+}
+
+pub mod sha {
+    use core::{convert::Infallible, marker::PhantomData};
+    use nb::Result;
+    use sha2::Digest;
+
+    pub trait Sha<DM> {
+        fn is_busy(&self) -> bool;
+
+        fn write_data<'a>(&mut self, incoming: &'a [u8]) -> Result<&'a [u8], Infallible>;
+
+        fn process_buffer(&mut self);
+
+        fn finish(&mut self, output: &mut [u8]) -> Result<(), Infallible>;
+    }
+
+    const BLOCK_BYTES: usize = 64;
+
+    pub struct Sha256<DM> {
+        hasher: sha2::Sha256,
+        mode: PhantomData<DM>,
+    }
+
+    impl<DM> Sha256<DM> {
+        pub fn new() -> Self {
+            Self {
+                hasher: sha2::Sha256::new(),
+                mode: PhantomData,
+            }
+        }
+    }
+
+    impl<DM> Default for Sha256<DM> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<DM> Sha<DM> for Sha256<DM> {
+        fn is_busy(&self) -> bool {
+            false
+        }
+
+        // The peripheral takes a block at a time, so callers have to handle partial writes.
+        fn write_data<'a>(&mut self, incoming: &'a [u8]) -> Result<&'a [u8], Infallible> {
+            let (block, remaining) = incoming.split_at(incoming.len().min(BLOCK_BYTES));
+            self.hasher.update(block);
+            Ok(remaining)
+        }
+
+        fn process_buffer(&mut self) {}
+
+        fn finish(&mut self, output: &mut [u8]) -> Result<(), Infallible> {
+            output.copy_from_slice(&self.hasher.finalize_reset());
+            Ok(())
+        }
+    }
+}
+
+pub mod rom {
+    pub mod crc {
+        /// Matches the ROM function, which inverts `crc` before and after, so a `crc` of 0 gives
+        /// the standard CRC32.
+        pub fn crc32_le(crc: u32, buf: &[u8]) -> u32 {
+            let mut hasher = crc32fast::Hasher::new_with_initial(crc);
+            hasher.update(buf);
+            hasher.finalize()
+        }
+    }
+}
+
+pub mod macros {
+    pub use esp_hal_procmacros::ram;
+}
+
+pub mod clock {
+    pub struct Clocks;
+}
+
+pub mod peripherals {
+    pub struct SPI2;
+}
+
+pub mod gpio {
+    use core::marker::PhantomData;
+
+    pub struct GpioPin<const N: u8>;
+
+    pub enum Level {
+        Low,
+        High,
+    }
+
+    pub struct Output<'d, P> {
+        pin: PhantomData<&'d mut P>,
+    }
+
+    impl<'d, P> Output<'d, P> {
+        pub fn new_typed(_pin: P, _level: Level) -> Self {
+            Self { pin: PhantomData }
+        }
+
+        pub fn set_high(&mut self) {}
+
+        pub fn set_low(&mut self) {}
+    }
+}
+
+pub mod dma {
+    pub enum DmaPriority {
+        Priority0,
+    }
+
+    pub struct DmaDescriptor;
+
+    pub struct DmaChannel0;
+
+    impl DmaChannel0 {
+        pub fn configure_for_async(self, _burst_mode: bool, _priority: DmaPriority) -> Self {
+            self
+        }
+    }
+
+    pub struct Dma<'d> {
+        pub channel0: DmaChannel0,
+        lifetime: core::marker::PhantomData<&'d ()>,
+    }
+
+    #[derive(Debug)]
+    pub struct DmaBufError;
+
+    pub struct DmaTxBuf;
+
+    impl DmaTxBuf {
+        pub fn new(
+            _descriptors: &'static mut [DmaDescriptor],
+            _buffer: &'static mut [u8],
+        ) -> Result<Self, DmaBufError> {
+            Ok(Self)
+        }
+    }
+
+    pub struct DmaRxBuf;
+
+    impl DmaRxBuf {
+        pub fn new(
+            _descriptors: &'static mut [DmaDescriptor],
+            _buffer: &'static mut [u8],
+        ) -> Result<Self, DmaBufError> {
+            Ok(Self)
+        }
+    }
+
+    #[macro_export]
+    macro_rules! dma_buffers {
+        ($size:expr) => {{
+            fn leak<T>(value: T) -> &'static mut T {
+                ::std::boxed::Box::leak(::std::boxed::Box::new(value))
+            }
+
+            (
+                &mut leak([0u8; $size])[..],
+                &mut leak([$crate::dma::DmaDescriptor])[..],
+                &mut leak([0u8; $size])[..],
+                &mut leak([$crate::dma::DmaDescriptor])[..],
+            )
+        }};
+    }
+}
+
+pub mod spi {
+    pub enum SpiMode {
+        Mode0,
+    }
+
+    pub enum SpiBitOrder {
+        MsbFirst,
+        LSBFirst,
+    }
+
+    pub mod master {
+        use super::{SpiBitOrder, SpiMode};
+        use crate::dma::{DmaChannel0, DmaRxBuf, DmaTxBuf};
+        use crate::gpio::GpioPin;
+        use crate::peripherals::SPI2;
+        use core::convert::Infallible;
+        use embedded_hal_async::spi::{ErrorType, SpiBus};
+        use fugit::HertzU32;
+
+        /// Discards everything written to it.
+        pub struct Spi;
+
+        impl Spi {
+            pub fn new(_spi: SPI2, _frequency: HertzU32, _mode: SpiMode) -> Self {
+                Self
+            }
+
+            pub fn with_sck<const N: u8>(self, _sck: GpioPin<N>) -> Self {
+                self
+            }
+
+            pub fn with_mosi<const N: u8>(self, _mosi: GpioPin<N>) -> Self {
+                self
+            }
+
+            pub fn with_bit_order(self, _read: SpiBitOrder, _write: SpiBitOrder) -> Self {
+                self
+            }
+
+            pub fn with_dma(self, _channel: DmaChannel0) -> Self {
+                self
+            }
+
+            pub fn with_buffers(self, _rx: DmaRxBuf, _tx: DmaTxBuf) -> Self {
+                self
+            }
+        }
+
+        impl ErrorType for Spi {
+            type Error = Infallible;
+        }
+
+        impl SpiBus for Spi {
+            async fn read(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+                words.fill(0);
+                Ok(())
+            }
+
+            async fn write(&mut self, _words: &[u8]) -> Result<(), Infallible> {
+                Ok(())
+            }
+
+            async fn transfer(&mut self, read: &mut [u8], _write: &[u8]) -> Result<(), Infallible> {
+                read.fill(0);
+                Ok(())
+            }
+
+            async fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Infallible> {
+                Ok(())
+            }
+
+            async fn flush(&mut self) -> Result<(), Infallible> {
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! `esp-storage` backed by memory instead of the chip's flash, so the filesystem can run on the
+//! host. Writes behave like NOR flash: they can only clear bits until the sector is erased.
+
+use core::fmt;
+use embedded_storage::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+const FLASH_SIZE: usize = 8 * 1024 * 1024;
+const ERASED_BYTE: u8 = 0xff;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashStorageError {
+    IoError,
+    IoTimeout,
+    CantUnlock,
+    NotAligned,
+    OutOfBounds,
+    Other(i32),
+}
+
+impl NorFlashError for FlashStorageError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::NotAligned => NorFlashErrorKind::NotAligned,
+            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+pub struct FlashStorage {
+    bytes: Vec<u8>,
+}
+
+impl FlashStorage {
+    pub const WORD_SIZE: u32 = 4;
+    pub const SECTOR_SIZE: u32 = 4096;
+
+    pub fn new() -> Self {
+        Self {
+            bytes: vec![ERASED_BYTE; FLASH_SIZE],
+        }
+    }
+
+    fn range(
+        &self,
+        offset: u32,
+        len: usize,
+        align: u32,
+    ) -> Result<(usize, usize), FlashStorageError> {
+        if offset % align != 0 || len % align as usize != 0 {
+            return Err(FlashStorageError::NotAligned);
+        }
+
+        let start = offset as usize;
+        let end = start + len;
+
+        if end > self.bytes.len() {
+            return Err(FlashStorageError::OutOfBounds);
+        }
+
+        Ok((start, end))
+    }
+}
+
+impl Default for FlashStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The contents are too big to be worth printing.
+impl fmt::Debug for FlashStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlashStorage").finish_non_exhaustive()
+    }
+}
+
+impl ErrorType for FlashStorage {
+    type Error = FlashStorageError;
+}
+
+impl ReadNorFlash for FlashStorage {
+    const READ_SIZE: usize = Self::WORD_SIZE as usize;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let (start, end) = self.range(offset, bytes.len(), Self::WORD_SIZE)?;
+        bytes.copy_from_slice(&self.bytes[start..end]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl NorFlash for FlashStorage {
+    const WRITE_SIZE: usize = Self::WORD_SIZE as usize;
+    const ERASE_SIZE: usize = Self::SECTOR_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let len = to.checked_sub(from).ok_or(FlashStorageError::OutOfBounds)?;
+        let (start, end) = self.range(from, len as usize, Self::SECTOR_SIZE)?;
+        self.bytes[start..end].fill(ERASED_BYTE);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let (start, end) = self.range(offset, bytes.len(), Self::WORD_SIZE)?;
+
+        for (old, new) in self.bytes[start..end].iter_mut().zip(bytes) {
+            *old &= new;
+        }
+
+        Ok(())
+    }
+}
+
+impl MultiwriteNorFlash for FlashStorage {}
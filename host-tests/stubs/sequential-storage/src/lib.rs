@@ -0,0 +1,318 @@
+//! A map with the same interface as `sequential-storage`'s, stored as a log of items in flash.
+//!
+//! Every store or removal appends an item, and a fetch returns the last one with a matching key.
+//! Like the real crate, items never cross a page boundary and a page's first word is written as
+//! soon as anything is stored in it. Pages are only erased by `erase_all`, so storage otherwise
+//! fills up for good.
+
+use core::ops::Range;
+
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error<S> {
+    Storage { value: S },
+    FullStorage,
+    Corrupted {},
+    BufferTooBig,
+    BufferTooSmall(usize),
+    SerializationError(map::SerializationError),
+    ItemTooBig,
+}
+
+/// Erases all of `flash_range`, leaving an empty map.
+pub async fn erase_all<S: embedded_storage_async::nor_flash::NorFlash>(
+    flash: &mut S,
+    flash_range: Range<u32>,
+) -> Result<(), Error<S::Error>> {
+    flash
+        .erase(flash_range.start, flash_range.end)
+        .await
+        .map_err(|value| Error::Storage { value })
+}
+
+pub mod cache {
+    use core::marker::PhantomData;
+
+    /// Caches nothing, since the log is read from flash every time.
+    #[derive(Debug)]
+    pub struct KeyPointerCache<const PAGE_COUNT: usize, KEY, const KEYS: usize>(PhantomData<KEY>);
+
+    impl<const PAGE_COUNT: usize, KEY, const KEYS: usize> KeyPointerCache<PAGE_COUNT, KEY, KEYS> {
+        pub const fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    impl<const PAGE_COUNT: usize, KEY, const KEYS: usize> Default
+        for KeyPointerCache<PAGE_COUNT, KEY, KEYS>
+    {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+}
+
+pub mod map {
+    use super::{cache::KeyPointerCache, Error, Range};
+    use embedded_storage_async::nor_flash::{MultiwriteNorFlash, NorFlash};
+
+    // Items start with the length of their key and of their value, each as a little endian u32.
+    const HEADER_BYTES: usize = 8;
+    const WORD_BYTES: usize = 4;
+    const ERASED_WORD: u32 = u32::MAX;
+    // The value length of an item which removes its key.
+    const REMOVED: u32 = u32::MAX - 1;
+    const MAX_KEY_BYTES: usize = 64;
+    const PAGE_SIZE: u32 = 4096;
+    // Two words at each end of a page are kept for its state, like the real crate.
+    const PAGE_RESERVED_BYTES: u32 = 2 * WORD_BYTES as u32;
+    const OPEN_MARKER: u32 = 0;
+
+    #[non_exhaustive]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SerializationError {
+        BufferTooSmall,
+        InvalidData,
+        InvalidFormat,
+        Custom(i32),
+    }
+
+    pub trait Key: Eq + Clone + Sized {
+        fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, SerializationError>;
+
+        fn deserialize_from(buffer: &[u8]) -> Result<(Self, usize), SerializationError>;
+    }
+
+    impl<const N: usize> Key for [u8; N] {
+        fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, SerializationError> {
+            buffer
+                .get_mut(..N)
+                .ok_or(SerializationError::BufferTooSmall)?
+                .copy_from_slice(self);
+
+            Ok(N)
+        }
+
+        fn deserialize_from(buffer: &[u8]) -> Result<(Self, usize), SerializationError> {
+            let bytes = buffer.get(..N).ok_or(SerializationError::BufferTooSmall)?;
+            let mut key = [0; N];
+            key.copy_from_slice(bytes);
+
+            Ok((key, N))
+        }
+    }
+
+    pub trait Value<'a> {
+        fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, SerializationError>;
+
+        fn deserialize_from(buffer: &'a [u8]) -> Result<Self, SerializationError>
+        where
+            Self: Sized;
+    }
+
+    struct Item {
+        key: Vec<u8>,
+        value: Option<Vec<u8>>,
+    }
+
+    fn padded(len: usize) -> usize {
+        len.div_ceil(WORD_BYTES) * WORD_BYTES
+    }
+
+    fn serialize_key<K: Key, E>(key: &K) -> Result<Vec<u8>, Error<E>> {
+        let mut buf = [0; MAX_KEY_BYTES];
+        let len = key
+            .serialize_into(&mut buf)
+            .map_err(Error::SerializationError)?;
+
+        Ok(buf[..len].to_vec())
+    }
+
+    async fn read<S: NorFlash>(
+        flash: &mut S,
+        offset: u32,
+        len: usize,
+    ) -> Result<Vec<u8>, Error<S::Error>> {
+        let mut buf = vec![0; padded(len)];
+        flash
+            .read(offset, &mut buf)
+            .await
+            .map_err(|value| Error::Storage { value })?;
+        buf.truncate(len);
+
+        Ok(buf)
+    }
+
+    // Reads every item in the log, returning them with the offset an item would be appended at.
+    // That's `None` if the log hasn't started yet.
+    async fn read_log<S: NorFlash>(
+        flash: &mut S,
+        flash_range: Range<u32>,
+    ) -> Result<(Vec<Item>, Option<u32>), Error<S::Error>> {
+        let mut items = Vec::new();
+        let mut end = None;
+
+        for page in flash_range.clone().step_by(PAGE_SIZE as usize) {
+            let marker = read(flash, page, WORD_BYTES).await?;
+
+            if marker == ERASED_WORD.to_le_bytes() {
+                break;
+            }
+
+            let mut offset = page + PAGE_RESERVED_BYTES;
+
+            while offset + HEADER_BYTES as u32 <= page + PAGE_SIZE - PAGE_RESERVED_BYTES {
+                let header = read(flash, offset, HEADER_BYTES).await?;
+                let key_len = u32::from_le_bytes(header[..4].try_into().unwrap());
+                let value_len = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+                if key_len == ERASED_WORD {
+                    break;
+                }
+
+                let stored_value_len = if value_len == REMOVED { 0 } else { value_len };
+                let body_len = (key_len + stored_value_len) as usize;
+                let body = read(flash, offset + HEADER_BYTES as u32, body_len).await?;
+                let (key, value) = body.split_at(key_len as usize);
+
+                items.push(Item {
+                    key: key.to_vec(),
+                    value: (value_len != REMOVED).then(|| value.to_vec()),
+                });
+
+                offset += (HEADER_BYTES + padded(body_len)) as u32;
+            }
+
+            end = Some(offset);
+        }
+
+        Ok((items, end))
+    }
+
+    async fn append<S: NorFlash>(
+        flash: &mut S,
+        flash_range: Range<u32>,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) -> Result<(), Error<S::Error>> {
+        let mut item = Vec::new();
+        let value_len = value.map_or(REMOVED, |value| value.len() as u32);
+        item.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        item.extend_from_slice(&value_len.to_le_bytes());
+        item.extend_from_slice(key);
+        item.extend_from_slice(value.unwrap_or_default());
+        item.resize(padded(item.len()), 0);
+
+        let item_len = item.len() as u32;
+
+        if item_len > PAGE_SIZE - 2 * PAGE_RESERVED_BYTES {
+            return Err(Error::ItemTooBig);
+        }
+
+        let (_, end) = read_log(flash, flash_range.clone()).await?;
+
+        // items which don't fit in the rest of the page start the next one.
+        let offset = match end {
+            Some(offset) if offset % PAGE_SIZE + item_len <= PAGE_SIZE - PAGE_RESERVED_BYTES => {
+                offset
+            }
+            Some(offset) => offset.next_multiple_of(PAGE_SIZE) + PAGE_RESERVED_BYTES,
+            None => flash_range.start + PAGE_RESERVED_BYTES,
+        };
+
+        if offset + item_len > flash_range.end - PAGE_RESERVED_BYTES {
+            return Err(Error::FullStorage);
+        }
+
+        let page = offset - PAGE_RESERVED_BYTES;
+
+        if offset % PAGE_SIZE == PAGE_RESERVED_BYTES {
+            flash
+                .write(page, &OPEN_MARKER.to_le_bytes())
+                .await
+                .map_err(|value| Error::Storage { value })?;
+        }
+
+        flash
+            .write(offset, &item)
+            .await
+            .map_err(|value| Error::Storage { value })
+    }
+
+    pub async fn fetch_item<
+        'd,
+        K: Key,
+        V: Value<'d>,
+        S: NorFlash,
+        const PAGES: usize,
+        const KEYS: usize,
+    >(
+        flash: &mut S,
+        flash_range: Range<u32>,
+        _cache: &mut KeyPointerCache<PAGES, K, KEYS>,
+        data_buffer: &'d mut [u8],
+        search_key: &K,
+    ) -> Result<Option<V>, Error<S::Error>> {
+        let key = serialize_key(search_key)?;
+        let (items, _) = read_log(flash, flash_range).await?;
+
+        let Some(value) = items
+            .into_iter()
+            .rev()
+            .find(|item| item.key == key)
+            .and_then(|item| item.value)
+        else {
+            return Ok(None);
+        };
+
+        data_buffer
+            .get_mut(..value.len())
+            .ok_or(Error::BufferTooSmall(value.len()))?
+            .copy_from_slice(&value);
+
+        let data_buffer: &'d [u8] = data_buffer;
+
+        V::deserialize_from(&data_buffer[..value.len()])
+            .map(Some)
+            .map_err(Error::SerializationError)
+    }
+
+    pub async fn store_item<
+        'd,
+        K: Key,
+        V: Value<'d>,
+        S: NorFlash,
+        const PAGES: usize,
+        const KEYS: usize,
+    >(
+        flash: &mut S,
+        flash_range: Range<u32>,
+        _cache: &mut KeyPointerCache<PAGES, K, KEYS>,
+        data_buffer: &mut [u8],
+        key: &K,
+        item: &V,
+    ) -> Result<(), Error<S::Error>> {
+        let key = serialize_key(key)?;
+        let len = item
+            .serialize_into(data_buffer)
+            .map_err(Error::SerializationError)?;
+
+        append(flash, flash_range, &key, Some(&data_buffer[..len])).await
+    }
+
+    pub async fn remove_item<
+        K: Key,
+        S: MultiwriteNorFlash,
+        const PAGES: usize,
+        const KEYS: usize,
+    >(
+        flash: &mut S,
+        flash_range: Range<u32>,
+        _cache: &mut KeyPointerCache<PAGES, K, KEYS>,
+        _data_buffer: &mut [u8],
+        search_key: &K,
+    ) -> Result<(), Error<S::Error>> {
+        let key = serialize_key(search_key)?;
+        append(flash, flash_range, &key, None).await
+    }
+}
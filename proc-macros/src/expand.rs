@@ -1,8 +1,9 @@
 use proc_macro2::{Span, TokenStream};
 use proc_macro_crate::{crate_name, FoundCrate};
-use quote::{quote, quote_spanned};
+use quote::{format_ident, quote, quote_spanned};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{FnArg, Ident, ItemFn, Pat, ReturnType, Signature};
+use syn::{FnArg, Ident, ItemFn, LitStr, Pat, PatType, ReturnType, Signature, Token, Type};
 
 use crate::error::Errors;
 
@@ -12,7 +13,7 @@ macro_rules! to_compile_error {
     };
 }
 
-pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
+pub fn expand(names: Vec<LitStr>, f: ItemFn) -> Result<TokenStream, TokenStream> {
     check_signature(&f.sig)?;
 
     let xenon_crate =
@@ -45,8 +46,18 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
         "syscall function requires at least one argument (the caller)"
     ))?;
 
+    let caller_ident = match first_input {
+        FnArg::Typed(pat_type) => match &*pat_type.pat {
+            Pat::Ident(ident) => Some(&ident.ident),
+            _ => None,
+        },
+        FnArg::Receiver(_) => None,
+    };
+
     let mut args: Vec<TokenStream> = Vec::new();
     let mut cvt_stmts: Vec<TokenStream> = Vec::new();
+    let mut memory_stmts: Vec<TokenStream> = Vec::new();
+    let mut param_types: Vec<TokenStream> = Vec::new();
 
     for arg in inputs_iter {
         match arg {
@@ -66,6 +77,49 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
 
                 let ty = &*pat_type.ty;
 
+                if let Some(kind) = memory_arg_kind(pat_type)? {
+                    let Some(caller) = caller_ident else {
+                        return Err(to_compile_error!(
+                            first_input,
+                            "the caller must be bound to a name to use `#[wasm(ptr, len)]` parameters"
+                        ));
+                    };
+
+                    let ident = &ident.ident;
+                    let ptr = format_ident!("{}_ptr", ident);
+                    let len = format_ident!("{}_len", ident);
+
+                    for part in [&ptr, &len] {
+                        param_types.push(wasm_val_type(&xenon_crate, &quote!(usize)));
+
+                        args.push(quote_spanned! {
+                            ident.span() =>
+                            #part: <usize as #xenon_crate::app::convert::TryFromWasm>::WasmTy
+                        });
+
+                        cvt_stmts.push(quote_spanned! {
+                            ident.span() =>
+                            let #part = <usize as #xenon_crate::app::convert::TryFromWasm>::try_from_wasm(#part)
+                                .map_err(|e| #xenon_crate::app::types::Error::InvalidValue(e.0))?;
+                        });
+                    }
+
+                    let fetch = match kind {
+                        MemoryArg::Bytes => quote!(memory_slice),
+                        MemoryArg::Str => quote!(memory_str),
+                    };
+
+                    memory_stmts.push(quote_spanned! {
+                        ident.span() =>
+                        let #ident: #ty = #caller
+                            .data()
+                            .lock_data_blocking()
+                            .#fetch(&#caller, #ptr, #len)?;
+                    });
+
+                    continue;
+                }
+
                 let arg_tokens = quote_spanned! {
                     ident.span() =>
                     #ident: <#ty as #xenon_crate::app::convert::TryFromWasm>::WasmTy
@@ -79,6 +133,7 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
 
                 args.push(arg_tokens);
                 cvt_stmts.push(cvt_tokens);
+                param_types.push(wasm_val_type(&xenon_crate, &quote!(#ty)));
             }
         }
     }
@@ -88,6 +143,8 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
         ReturnType::Type(_, ty) => quote_spanned!(ty.span() => #ty),
     };
 
+    let registration = registration(&xenon_crate, &name, &names, &param_types, &return_type);
+
     let verify_return_type = quote_spanned! {
         return_type.span() =>
         const _: () = {
@@ -112,6 +169,8 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
     Ok(quote! {
         #verify_return_type
 
+        #registration
+
         #(
             #attrs
         )*
@@ -129,6 +188,10 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
                 #cvt_stmts
             )*
 
+            #(
+                #memory_stmts
+            )*
+
             {
                 #body
             }
@@ -136,6 +199,117 @@ pub fn expand(f: ItemFn) -> Result<TokenStream, TokenStream> {
     })
 }
 
+// Adds an entry to the syscall table for each name, which `link_syscalls` goes through when an app
+// is loaded. Each name's signature is also checked against `signatures.txt`.
+fn registration(
+    xenon_crate: &TokenStream,
+    f: &Ident,
+    names: &[LitStr],
+    param_types: &[TokenStream],
+    return_type: &TokenStream,
+) -> TokenStream {
+    if names.is_empty() {
+        return TokenStream::new();
+    }
+
+    let linkme_crate = match crate_name("linkme").expect("linkme to be in Cargo.toml") {
+        FoundCrate::Itself => quote!(crate),
+        FoundCrate::Name(name) => {
+            let ident = Ident::new(name.as_str(), Span::call_site());
+            quote!(#ident)
+        }
+    };
+
+    let entries = names.iter().enumerate().map(|(i, name)| {
+        let entry = format_ident!("__SYSCALL_{}_{}", f.to_string().to_uppercase(), i);
+
+        quote_spanned! {
+            name.span() =>
+            const _: () = #xenon_crate::app::syscall::signature::assert_signature(
+                #name,
+                &[#(#param_types),*],
+                <#return_type as #xenon_crate::app::convert::WasmResults>::RESULTS,
+            );
+
+            #[#linkme_crate::distributed_slice(#xenon_crate::app::syscall::SYSCALLS)]
+            #[linkme(crate = #linkme_crate)]
+            static #entry: #xenon_crate::app::syscall::SyscallEntry =
+                #xenon_crate::app::syscall::SyscallEntry::new(
+                    #name,
+                    &[#(#param_types),*],
+                    <#return_type as #xenon_crate::app::convert::WasmResults>::RESULTS,
+                    |linker| {
+                        linker.func_wrap(#xenon_crate::app::syscall::SYSCALL_NAMESPACE, #name, #f)?;
+                        Ok(())
+                    },
+                );
+        }
+    });
+
+    quote! {
+        const _: () = {
+            #(
+                #entries
+            )*
+        };
+    }
+}
+
+// The wasm type the syscall parameter `ty` is passed as.
+fn wasm_val_type(xenon_crate: &TokenStream, ty: &TokenStream) -> TokenStream {
+    quote! {
+        <<#ty as #xenon_crate::app::convert::TryFromWasm>::WasmTy
+            as #xenon_crate::app::convert::WasmValType>::VAL_TYPE
+    }
+}
+
+// Parameters which are read out of wasm memory, passed as a pointer and a length.
+enum MemoryArg {
+    Bytes,
+    Str,
+}
+
+// Returns the kind of memory parameter `pat_type` is if it has a `#[wasm(ptr, len)]` attribute.
+fn memory_arg_kind(pat_type: &PatType) -> Result<Option<MemoryArg>, TokenStream> {
+    let Some(attr) = pat_type
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("wasm"))
+    else {
+        return Ok(None);
+    };
+
+    let parts = attr
+        .parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)
+        .map_err(syn::Error::into_compile_error)?;
+
+    let is_ptr_len = parts.len() == 2 && parts[0] == "ptr" && parts[1] == "len";
+
+    if !is_ptr_len {
+        return Err(to_compile_error!(
+            attr,
+            "only `#[wasm(ptr, len)]` is supported for syscall parameters"
+        ));
+    }
+
+    let kind = match &*pat_type.ty {
+        Type::Reference(reference) if reference.mutability.is_none() => match &*reference.elem {
+            Type::Slice(slice) => match &*slice.elem {
+                Type::Path(path) if path.path.is_ident("u8") => Some(MemoryArg::Bytes),
+                _ => None,
+            },
+            Type::Path(path) if path.path.is_ident("str") => Some(MemoryArg::Str),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    kind.map(Some).ok_or(to_compile_error!(
+        &pat_type.ty,
+        "`#[wasm(ptr, len)]` parameters must be `&[u8]` or `&str`"
+    ))
+}
+
 fn check_signature(sig: &Signature) -> Result<(), TokenStream> {
     let mut errors = Errors::new();
 
@@ -2,13 +2,19 @@ mod error;
 mod expand;
 
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, ItemFn};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, ItemFn, LitStr, Token};
 
+/// Turns an `extern "wasm"` fn into a syscall which can be linked into apps.
+///
+/// Names passed to the attribute (`#[syscall("print", "eprint")]`) register the syscall under
+/// each of them, so it's linked automatically when an app is loaded.
 #[proc_macro_attribute]
-pub fn syscall(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn syscall(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let names = parse_macro_input!(attr with Punctuated::<LitStr, Token![,]>::parse_terminated);
     let f = parse_macro_input!(item as ItemFn);
 
-    match expand::expand(f) {
+    match expand::expand(names.into_iter().collect(), f) {
         Ok(tokens) => tokens.into(),
         Err(tokens) => tokens.into(),
     }
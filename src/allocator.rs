@@ -10,7 +10,8 @@ use static_cell::ConstStaticCell;
 
 const WIFI_HEAP_BYTES: usize = 1 << 17; // 128 KiB
 
-#[global_allocator]
+// Only global on the chip, so the host tests keep the system allocator.
+#[cfg_attr(target_os = "none", global_allocator)]
 pub static ALLOCATOR: Allocator = Allocator::new();
 
 #[used]
@@ -30,6 +31,29 @@ static WIFI_ALLOCATOR: Lazy<Allocator> = Lazy::new(|| {
     allocator
 });
 
+/// Returns how much of each heap is in use.
+pub fn alloc_stats() -> AllocStats {
+    AllocStats {
+        psram_used: ALLOCATOR.used(),
+        psram_free: ALLOCATOR.free(),
+        internal_used: WIFI_ALLOCATOR.used(),
+        internal_free: WIFI_ALLOCATOR.free(),
+        largest_free_block: ALLOCATOR.largest_free_block(),
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub struct AllocStats {
+    pub psram_used: usize,
+    pub psram_free: usize,
+    /// Bytes used in the internal RAM heap reserved for the wireless drivers.
+    pub internal_used: usize,
+    pub internal_free: usize,
+    /// The largest single allocation the PSRAM heap could make. When this is much smaller than
+    /// `psram_free`, the heap is fragmented.
+    pub largest_free_block: usize,
+}
+
 pub struct Allocator(TicketMutex<Heap>);
 
 impl Allocator {
@@ -54,6 +78,50 @@ impl Allocator {
     pub fn free(&self) -> usize {
         cs::with(|_| self.0.lock().free())
     }
+
+    /// Returns the size of the largest allocation that would currently succeed.
+    pub fn largest_free_block(&self) -> usize {
+        cs::with(|_| {
+            let mut heap = self.0.lock();
+
+            // The heap doesn't expose its free list, so this binary searches for the largest size
+            // which can be allocated. Each probe is freed straight away, and freeing merges the
+            // hole back together, so the heap ends up as it started.
+            let mut fits = 0;
+            let mut too_large = heap.free() + 1;
+
+            while too_large - fits > 1 {
+                let size = fits + (too_large - fits) / 2;
+                let layout = Layout::from_size_align(size, 1).expect("valid size");
+
+                match heap.allocate_first_fit(layout) {
+                    Ok(ptr) => {
+                        unsafe { heap.deallocate(ptr, layout) };
+                        fits = size;
+                    }
+                    Err(()) => too_large = size,
+                }
+            }
+
+            fits
+        })
+    }
+
+    // This is called after the heap lock has been released, since the logger may end up calling
+    // back into the allocator. Formatting the message itself doesn't allocate.
+    #[cold]
+    fn log_alloc_error(&self, layout: Layout) {
+        let (used, free) = cs::with(|_| {
+            let heap = self.0.lock();
+            (heap.used(), heap.free())
+        });
+
+        log::error!(
+            "failed to allocate {} bytes with alignment {} ({used} bytes used, {free} bytes free)",
+            layout.size(),
+            layout.align(),
+        );
+    }
 }
 
 impl Default for Allocator {
@@ -64,13 +132,15 @@ impl Default for Allocator {
 
 unsafe impl GlobalAlloc for Allocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        cs::with(|_| {
-            self.0
-                .lock()
-                .allocate_first_fit(layout)
-                .map(|nonnull| nonnull.as_ptr())
-                .unwrap_or(ptr::null_mut())
-        })
+        let allocation = cs::with(|_| self.0.lock().allocate_first_fit(layout));
+
+        match allocation {
+            Ok(nonnull) => nonnull.as_ptr(),
+            Err(()) => {
+                self.log_alloc_error(layout);
+                ptr::null_mut()
+            }
+        }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
@@ -96,3 +166,65 @@ pub extern "C" fn esp_wifi_free_internal_heap() -> usize {
 pub extern "C" fn esp_wifi_allocate_from_internal_ram(size: usize) -> *mut u8 {
     unsafe { WIFI_ALLOCATOR.alloc(Layout::from_size_align(size, 4).expect("valid size")) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use std::string::{String, ToString};
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    // Keeps the messages logged from this module, since other tests log too.
+    struct Capture(Mutex<Vec<(Level, String)>>);
+
+    impl Log for Capture {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            if record.target() == module_path!().trim_end_matches("::tests") {
+                let message = record.args().to_string();
+                self.0.lock().unwrap().push((record.level(), message));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static CAPTURE: Capture = Capture(Mutex::new(Vec::new()));
+
+    #[test]
+    fn failed_allocation_is_logged() {
+        #[repr(C, align(16))]
+        struct TinyHeap([MaybeUninit<u8>; 256]);
+
+        static HEAP: ConstStaticCell<TinyHeap> =
+            ConstStaticCell::new(TinyHeap([MaybeUninit::uninit(); 256]));
+
+        log::set_logger(&CAPTURE).unwrap();
+        log::set_max_level(LevelFilter::Error);
+
+        let allocator = Allocator::new();
+        let heap = HEAP.take();
+        unsafe { allocator.init(heap.0.as_mut_ptr().cast(), heap.0.len()) };
+
+        let fits = Layout::from_size_align(64, 8).unwrap();
+        let too_large = Layout::from_size_align(1024, 8).unwrap();
+
+        let ptr = unsafe { allocator.alloc(fits) };
+        assert!(!ptr.is_null());
+        assert!(CAPTURE.0.lock().unwrap().is_empty());
+
+        let (used, free) = (allocator.used(), allocator.free());
+        assert!(unsafe { allocator.alloc(too_large) }.is_null());
+
+        let expected = format!(
+            "failed to allocate 1024 bytes with alignment 8 ({used} bytes used, {free} bytes free)"
+        );
+        assert_eq!(*CAPTURE.0.lock().unwrap(), [(Level::Error, expected)]);
+
+        unsafe { allocator.dealloc(ptr, fits) };
+    }
+}
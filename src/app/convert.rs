@@ -1,7 +1,20 @@
+use crate::app::types::{Namespace, OpenMode};
+use crate::widget::bitmap::{PixelColor, Transform};
+use crate::widget::text::layout::WrapMode;
 use core::any::type_name;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::primitives::StrokeAlignment;
 use thiserror::Error;
+use wasmi::core::ValType;
 use wasmi::WasmTy;
 
+/// Conversion from a wasm value into a syscall parameter. Any type implementing this trait can be
+/// used as a parameter of a `#[syscall]` function, which converts the raw wasm value and returns
+/// [`Error::InvalidValue`](crate::app::types::Error::InvalidValue) if the conversion fails.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a supported parameter type for syscall functions",
+    label = "cannot use `{Self}` as a syscall parameter type"
+)]
 pub trait TryFromWasm: Sized {
     type WasmTy: WasmTy;
 
@@ -51,6 +64,155 @@ impl TryFromWasm for bool {
     }
 }
 
+impl TryFromWasm for BinaryColor {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            1 => Ok(BinaryColor::Off),
+            2 => Ok(BinaryColor::On),
+            _ => Err(InvalidValueError(type_name::<BinaryColor>())),
+        }
+    }
+}
+
+// Used for optional colors, such as the fill and stroke colors of primitives, where 0 means the
+// color isn't set.
+impl TryFromWasm for Option<BinaryColor> {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            0 => Ok(None),
+            _ => BinaryColor::try_from_wasm(value)
+                .map(Some)
+                .map_err(|_| InvalidValueError(type_name::<Self>())),
+        }
+    }
+}
+
+impl TryFromWasm for StrokeAlignment {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            0 => Ok(StrokeAlignment::Inside),
+            1 => Ok(StrokeAlignment::Center),
+            2 => Ok(StrokeAlignment::Outside),
+            _ => Err(InvalidValueError(type_name::<StrokeAlignment>())),
+        }
+    }
+}
+
+impl TryFromWasm for PixelColor {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            1 => Ok(PixelColor::Black),
+            2 => Ok(PixelColor::White),
+            3 => Ok(PixelColor::Transparent),
+            _ => Err(InvalidValueError(type_name::<PixelColor>())),
+        }
+    }
+}
+
+impl TryFromWasm for Transform {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            0 => Ok(Transform::FlipHorizontal),
+            1 => Ok(Transform::FlipVertical),
+            2 => Ok(Transform::Rotate90),
+            3 => Ok(Transform::Rotate180),
+            4 => Ok(Transform::Rotate270),
+            _ => Err(InvalidValueError(type_name::<Transform>())),
+        }
+    }
+}
+
+impl TryFromWasm for WrapMode {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            0 => Ok(WrapMode::Whitespace),
+            1 => Ok(WrapMode::Character),
+            2 => Ok(WrapMode::Both),
+            3 => Ok(WrapMode::Truncate),
+            _ => Err(InvalidValueError(type_name::<WrapMode>())),
+        }
+    }
+}
+
+impl TryFromWasm for OpenMode {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            0 => Ok(OpenMode::Open),
+            1 => Ok(OpenMode::Create),
+            2 => Ok(OpenMode::OpenOrCreate),
+            _ => Err(InvalidValueError(type_name::<OpenMode>())),
+        }
+    }
+}
+
+impl TryFromWasm for Namespace {
+    type WasmTy = u32;
+
+    fn try_from_wasm(value: Self::WasmTy) -> Result<Self, InvalidValueError> {
+        match value {
+            0 => Ok(Namespace::App),
+            1 => Ok(Namespace::Shared),
+            _ => Err(InvalidValueError(type_name::<Namespace>())),
+        }
+    }
+}
+
+/// The wasm type a value is passed as. Unlike [`WasmTy::ty`], it's known at compile time, which
+/// lets `#[syscall]` record each syscall's signature in the syscall table.
+pub trait WasmValType {
+    const VAL_TYPE: ValType;
+}
+
+/// The wasm types a syscall returns, which is empty for `()`.
+pub trait WasmResults {
+    const RESULTS: &'static [ValType];
+}
+
+macro_rules! wasm_val_type {
+    ($($ty:ty => $val_ty:ident),* $(,)?) => {
+        $(
+            impl WasmValType for $ty {
+                const VAL_TYPE: ValType = ValType::$val_ty;
+            }
+
+            impl WasmResults for $ty {
+                const RESULTS: &'static [ValType] = &[ValType::$val_ty];
+            }
+        )*
+    };
+}
+
+wasm_val_type! {
+    u32 => I32,
+    i32 => I32,
+    u64 => I64,
+    i64 => I64,
+    f32 => F32,
+    f64 => F64,
+}
+
+impl WasmResults for () {
+    const RESULTS: &'static [ValType] = &[];
+}
+
+impl<T: WasmResults> WasmResults for Result<T, wasmi::Error> {
+    const RESULTS: &'static [ValType] = T::RESULTS;
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Error)]
 #[error("invalid value for type {0}")]
 pub struct InvalidValueError(pub &'static str);
@@ -1,30 +1,52 @@
-use crate::app::types::Executor as WasmExecutor;
+use crate::app::types::{Error as AppError, Executor as WasmExecutor, Manifest};
+use crate::fs;
+use alloc::format;
+use alloc::vec::Vec;
 use crate::macros::make_static;
 use core::marker::PhantomData;
 use core::sync::atomic::{AtomicBool, Ordering};
 use embassy_executor::{task, SendSpawner};
-use embassy_time::{Instant, Timer};
+use embassy_time::Instant;
 use esp_hal::cpu_control::{AppCoreGuard, CpuControl, Stack};
-use esp_hal::interrupt::Priority;
 use esp_hal::peripherals::CPU_CTRL;
 use esp_hal::rng::Trng;
 use esp_hal::Cpu;
-use esp_hal_embassy::{Executor, InterruptExecutor};
+use esp_hal_embassy::Executor;
 use static_cell::StaticCell;
 
 const STACK_SIZE: usize = 32 * 1024;
-const APP_SWI: u8 = 0;
-const REACTOR_SWI: u8 = 1;
+const MANIFEST_EXTENSION: &str = ".manifest";
 
 static RUNNING: AtomicBool = AtomicBool::new(false);
 static STACK: StaticCell<Stack<STACK_SIZE>> = StaticCell::new();
-static WASM_MODULE: &[u8] = include_bytes!("../../assets/xenon-test-app.wasm");
 
 #[task]
-async fn start(rng: Trng<'static>, reactor_spawner: SendSpawner) {
+async fn start(name: &'static str, rng: Trng<'static>, reactor_spawner: SendSpawner) {
     let start = Instant::now();
-    // TODO: Load wasm module from "filesystem" and handle errors more gracefully.
-    let mut wasm_executor = match WasmExecutor::new(rng, reactor_spawner, WASM_MODULE) {
+
+    let module = match load_module(name).await {
+        Ok(module) => module,
+        Err(e) => {
+            log::error!("failed to load wasm module `{name}`: {e}");
+            return;
+        }
+    };
+
+    // apps are namespaced by their name without the extension.
+    let app_name = name.strip_suffix(".wasm").unwrap_or(name);
+
+    let manifest = match load_manifest(app_name).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            log::error!("failed to load manifest for wasm module `{name}`: {e}");
+            return;
+        }
+    };
+
+    let executor =
+        WasmExecutor::with_manifest(app_name, rng, reactor_spawner, &module, &manifest);
+
+    let mut wasm_executor = match executor {
         Ok(ex) => ex,
         Err(e) => {
             log::error!("failed to create wasm executor: {e}");
@@ -44,6 +66,30 @@ async fn start(rng: Trng<'static>, reactor_spawner: SendSpawner) {
     }
 }
 
+async fn load_module(name: &str) -> Result<Vec<u8>, fs::Error> {
+    // without a filesystem there's nowhere the module could be found.
+    let fs = fs::filesystem().ok_or(fs::Error::NotFound)?;
+    let mut file = fs.open_file(name).await?;
+
+    file.read_to_end().await
+}
+
+// Apps without a manifest are still run, with the fallback manifest.
+async fn load_manifest(app_name: &str) -> Result<Manifest, AppError> {
+    let file_name = format!("{app_name}{MANIFEST_EXTENSION}");
+
+    let bytes = match fs::filesystem() {
+        Some(fs) => match fs.open_file(&file_name).await {
+            Ok(mut file) => file.read_to_end().await.map_err(|_| AppError::InvalidManifest)?,
+            Err(fs::Error::NotFound) => return Ok(Manifest::fallback(app_name)),
+            Err(_) => return Err(AppError::InvalidManifest),
+        },
+        None => return Ok(Manifest::fallback(app_name)),
+    };
+
+    Manifest::from_bytes(&bytes)
+}
+
 #[clippy::has_significant_drop]
 pub struct AppCpu<'a> {
     control: CpuControl<'static>,
@@ -60,8 +106,10 @@ impl<'a> AppCpu<'a> {
         }
     }
 
+    /// Starts the application core, running the wasm app in the file `name`.
     pub fn start(
         &mut self,
+        name: &'static str,
         rng: Trng<'static>,
         reactor_spawner: SendSpawner,
     ) {
@@ -73,7 +121,7 @@ impl<'a> AppCpu<'a> {
             let guard = self
                 .control
                 .start_app_core(stack, move || {
-                    Self::cpu_main(rng, reactor_spawner)
+                    Self::cpu_main(name, rng, reactor_spawner)
                 })
                 .unwrap();
 
@@ -99,7 +147,11 @@ impl<'a> AppCpu<'a> {
         self.control.unpark_core(Cpu::AppCpu)
     }
 
+    // The app core runs a thread-mode executor, so no software interrupts or priorities need
+    // setting up. Tasks spawned by syscalls go to `spawner`, which belongs to the main core's
+    // executor.
     fn cpu_main(
+        name: &'static str,
         rng: Trng<'static>,
         spawner: SendSpawner,
     ) {
@@ -108,6 +160,6 @@ impl<'a> AppCpu<'a> {
             Executor::new()
         );
 
-        app_executor.run(move |app_spawner| app_spawner.must_spawn(start(rng, spawner)))
+        app_executor.run(move |app_spawner| app_spawner.must_spawn(start(name, rng, spawner)))
     }
 }
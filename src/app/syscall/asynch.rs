@@ -4,7 +4,7 @@ use crate::macros::task;
 use embassy_time::{Duration, Instant, Timer};
 use wasmi::{Caller, Func};
 
-#[syscall]
+#[syscall("wait")]
 pub extern "wasm" fn wait(caller: Caller<'_, Env>) -> Result<(), wasmi::Error> {
     let mut env_data = caller.data().lock_data_blocking();
 
@@ -19,7 +19,7 @@ pub extern "wasm" fn wait(caller: Caller<'_, Env>) -> Result<(), wasmi::Error> {
     }
 }
 
-#[syscall]
+#[syscall("poll")]
 pub extern "wasm" fn poll(caller: Caller<'_, Env>) -> Result<(), wasmi::Error> {
     let mut env_data = caller.data().lock_data_blocking();
 
@@ -30,3 +30,28 @@ pub extern "wasm" fn poll(caller: Caller<'_, Env>) -> Result<(), wasmi::Error> {
         Err(PollRequest::Poll.into())
     }
 }
+
+#[syscall("sleep")]
+pub extern "wasm" fn sleep(caller: Caller<'_, Env>, micros: u64) -> Result<(), wasmi::Error> {
+    let deadline = Instant::now()
+        .checked_add(Duration::from_micros(micros))
+        .unwrap_or(Instant::MAX);
+
+    // The executor takes the deadline when handling the `PollRequest::Wait`, and only resumes the
+    // app once it has passed.
+    caller
+        .data()
+        .lock_data_blocking()
+        .set_sleep_deadline(deadline);
+
+    Err(PollRequest::Wait.into())
+}
+
+#[syscall("present")]
+pub extern "wasm" fn present(caller: Caller<'_, Env>) -> Result<(), wasmi::Error> {
+    // Like `sleep`, the executor picks this up when handling the `PollRequest::Wait`, and resumes
+    // the app once everything drawn so far is on the display.
+    caller.data().lock_data_blocking().set_present();
+
+    Err(PollRequest::Wait.into())
+}
@@ -0,0 +1,466 @@
+// Syscalls are synchronous, but the filesystem is async. The filesystem only awaits while waiting
+// for its lock, since the flash operations underneath it are blocking, so these syscalls run the
+// filesystem futures to completion with `block_on`. This is the same tradeoff as
+// `Env::lock_data_blocking`: the app core spins until the main core releases the lock, which it
+// only holds for the duration of a flash operation.
+//
+// Reading and writing files can take much longer, so those run in the background instead. They
+// return `WOULD_BLOCK` while the operation is in progress, and the app can pass the file's handle
+// to `schedule_io` to be woken once it's done.
+//
+// Every syscall here returns a non-negative value on success and one of the negative codes from
+// `fs_error_code` on failure.
+
+use crate::app::types::{Env, Error, FileState, Namespace, OpenFile, OpenMode};
+use crate::fs::{self, File, Filesystem};
+use crate::macros::syscall;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::future::Future;
+use embassy_futures::block_on;
+use embedded_io_async::{Read, Seek, SeekFrom, Write};
+use wasmi::Caller;
+
+/// The id of the first file handle. Handles share ids with the other sources passed to
+/// `schedule_io`, so they start after [`INPUT_IO_ID`](super::input::INPUT_IO_ID).
+pub const FILE_IO_ID_START: i32 = 1;
+
+/// The most reads and writes which can be in progress at once, across every open file.
+const MAX_PENDING_FILE_OPS: usize = 4;
+
+/// The most bytes read by a single `fs_read`. Larger reads return fewer bytes, like a short read.
+const MAX_READ_LEN: usize = 4096;
+
+/// Returned when the filesystem hasn't been initialized.
+const NO_FILESYSTEM: i32 = -1;
+
+/// Returned when a read or write on the file is still in progress.
+pub const WOULD_BLOCK: i32 = -14;
+
+/// Returned when the handle doesn't refer to an open file.
+const INVALID_HANDLE: i32 = -15;
+
+pub(crate) fn fs_error_code(error: &fs::Error) -> i32 {
+    use fs::Error as FsError;
+
+    match error {
+        FsError::Corrupted => -2,
+        FsError::NotFound => -3,
+        FsError::AlreadyExists => -4,
+        FsError::Invalidname | FsError::FilenameTooLong => -5,
+        FsError::BufferTooSmall | FsError::ReadBufferTooSmall(_) => -6,
+        FsError::DataTooLarge | FsError::ValueTooLarge => -7,
+        FsError::Canceled => -8,
+        FsError::Full => -9,
+        FsError::InvalidFormat | FsError::IsNotMetadata | FsError::IsNotChunk => -10,
+        FsError::OutOfBounds => -11,
+        FsError::WriteZero => -12,
+        FsError::Postcard(_) | FsError::Flash(_) => -13,
+    }
+}
+
+// Reads a file name from wasm memory and puts it in `namespace` for the running app.
+pub(crate) fn read_name(
+    caller: &Caller<'_, Env>,
+    namespace: Namespace,
+    ptr: usize,
+    len: usize,
+) -> Result<String, wasmi::Error> {
+    let env = caller.data();
+    let name = env.lock_data_blocking().memory_str(caller, ptr, len)?;
+
+    Ok(env.namespaced(namespace, name))
+}
+
+// Runs `f` with the filesystem to completion, converting the result to a syscall return value.
+pub(crate) fn block_on_fs<F, T>(f: impl FnOnce(&'static Filesystem) -> F) -> Result<T, i32>
+where
+    F: Future<Output = Result<T, fs::Error>>,
+{
+    let fs = fs::filesystem().ok_or(NO_FILESYSTEM)?;
+
+    block_on(f(fs)).map_err(|e| fs_error_code(&e))
+}
+
+/// Returns 1 if the file exists and 0 if it doesn't.
+#[syscall("fs_exists")]
+pub extern "wasm" fn fs_exists(
+    caller: Caller<'_, Env>,
+    namespace: Namespace,
+    name_ptr: usize,
+    name_len: usize,
+) -> Result<i32, wasmi::Error> {
+    let name = read_name(&caller, namespace, name_ptr, name_len)?;
+
+    Ok(block_on_fs(|fs| fs.metadata_exists(&name)).map_or_else(|e| e, i32::from))
+}
+
+/// Returns the size of the file in bytes.
+#[syscall("fs_size")]
+pub extern "wasm" fn fs_size(
+    caller: Caller<'_, Env>,
+    namespace: Namespace,
+    name_ptr: usize,
+    name_len: usize,
+) -> Result<i64, wasmi::Error> {
+    let name = read_name(&caller, namespace, name_ptr, name_len)?;
+
+    Ok(block_on_fs(|fs| fs.file_size(&name)).map_or_else(i64::from, |size| size as i64))
+}
+
+/// Deletes the file, returning 0.
+#[syscall("fs_delete")]
+pub extern "wasm" fn fs_delete(
+    caller: Caller<'_, Env>,
+    namespace: Namespace,
+    name_ptr: usize,
+    name_len: usize,
+) -> Result<i32, wasmi::Error> {
+    let name = read_name(&caller, namespace, name_ptr, name_len)?;
+
+    Ok(block_on_fs(|fs| fs.delete_file(&name)).map_or_else(|e| e, |_| 0))
+}
+
+/// Writes the SHA-256 digest of the file's contents, 32 bytes, to `out_ptr`, returning 0. This
+/// reads the whole file, and the app is blocked until it's done.
+#[syscall("fs_hash")]
+pub extern "wasm" fn fs_hash(
+    mut caller: Caller<'_, Env>,
+    namespace: Namespace,
+    name_ptr: usize,
+    name_len: usize,
+    out_ptr: usize,
+) -> Result<i32, wasmi::Error> {
+    let name = read_name(&caller, namespace, name_ptr, name_len)?;
+    let digest = match block_on_fs(|fs| fs.hash_file(&name)) {
+        Ok(digest) => digest,
+        Err(code) => return Ok(code),
+    };
+
+    let memory = caller.data().lock_data_blocking().memory();
+    memory.write(&mut caller, out_ptr, &digest)?;
+
+    Ok(0)
+}
+
+enum FileOp {
+    Read(usize),
+    /// Writes the bytes after moving the cursor back by `rewind` bytes, to undo a read whose
+    /// bytes the app never took.
+    Write {
+        bytes: Vec<u8>,
+        rewind: usize,
+    },
+    Flush,
+}
+
+#[embassy_executor::task(pool_size = MAX_PENDING_FILE_OPS)]
+async fn run_file_op(file: Arc<OpenFile>, op: FileOp) {
+    let state = {
+        let mut inner = file.file().lock().await;
+
+        match op {
+            FileOp::Read(len) => read_file(&mut inner, len).await.map(FileState::Read),
+            FileOp::Write { bytes, rewind } => write_file(&mut inner, &bytes, rewind)
+                .await
+                .map(|_| FileState::Idle),
+            FileOp::Flush => inner.flush().await.map(|_| FileState::Flushed),
+        }
+    };
+
+    file.finish(state.unwrap_or_else(FileState::Failed));
+}
+
+async fn read_file(file: &mut File, len: usize) -> Result<Vec<u8>, fs::Error> {
+    let mut buf = vec![0; len];
+    let count = file.read(&mut buf).await?;
+    buf.truncate(count);
+
+    Ok(buf)
+}
+
+async fn write_file(file: &mut File, bytes: &[u8], rewind: usize) -> Result<(), fs::Error> {
+    if rewind > 0 {
+        file.seek(SeekFrom::Current(-(rewind as i64))).await?;
+    }
+
+    file.write_all(bytes).await
+}
+
+fn handle_index(handle: i32) -> Option<usize> {
+    usize::try_from(handle.checked_sub(FILE_IO_ID_START)?).ok()
+}
+
+/// Returns the open file with the given handle, if there is one.
+pub(crate) fn get_file(env: &Env, handle: i32) -> Option<Arc<OpenFile>> {
+    env.lock_data_blocking().get_file(handle_index(handle)?)
+}
+
+// Starts `op` in the background, returning false if every task is already in use. The file is
+// then put back in its `previous` state, so the app can try again once something has finished.
+fn spawn_file_op(env: &Env, file: Arc<OpenFile>, op: FileOp, previous: FileState) -> bool {
+    file.set_state(FileState::Busy);
+
+    if env.spawn(run_file_op(file.clone(), op)).is_err() {
+        file.set_state(previous);
+        return false;
+    }
+
+    true
+}
+
+/// Opens a file and returns its handle.
+#[syscall("fs_open")]
+pub extern "wasm" fn fs_open(
+    caller: Caller<'_, Env>,
+    namespace: Namespace,
+    name_ptr: usize,
+    name_len: usize,
+    mode: OpenMode,
+) -> Result<i32, wasmi::Error> {
+    let name = read_name(&caller, namespace, name_ptr, name_len)?;
+
+    let opened = block_on_fs(|fs| async move {
+        match mode {
+            OpenMode::Open => fs.open_file(&name).await,
+            OpenMode::Create => fs.create_file(&name).await,
+            OpenMode::OpenOrCreate => match fs.open_file(&name).await {
+                Err(fs::Error::NotFound) => fs.create_file(&name).await,
+                result => result,
+            },
+        }
+    });
+
+    let file = match opened {
+        Ok(file) => file,
+        Err(code) => return Ok(code),
+    };
+
+    let index = caller
+        .data()
+        .lock_data_blocking()
+        .open_file(OpenFile::new(file));
+
+    Ok(index as i32 + FILE_IO_ID_START)
+}
+
+/// Reads up to `len` bytes from the file into wasm memory, returning how many were read. 0 means
+/// the end of the file was reached.
+///
+/// The first call starts the read and returns `WOULD_BLOCK`, and a call after it's finished
+/// returns the bytes. If `len` is smaller than the number of bytes which were read, the rest are
+/// kept for the next call.
+#[syscall("fs_read")]
+pub extern "wasm" fn fs_read(
+    mut caller: Caller<'_, Env>,
+    handle: i32,
+    buf_ptr: usize,
+    len: usize,
+) -> Result<i64, wasmi::Error> {
+    let env = caller.data().clone();
+
+    let Some(file) = get_file(&env, handle) else {
+        return Ok(INVALID_HANDLE.into());
+    };
+
+    match file.take_state() {
+        FileState::Busy => Ok(WOULD_BLOCK.into()),
+        FileState::Failed(e) => Ok(fs_error_code(&e).into()),
+        FileState::Read(mut bytes) => {
+            let count = len.min(bytes.len());
+
+            let memory = env.lock_data_blocking().memory();
+            memory.write(&mut caller, buf_ptr, &bytes[..count])?;
+
+            if count < bytes.len() {
+                bytes.drain(..count);
+                file.set_state(FileState::Read(bytes));
+            }
+
+            Ok(count as i64)
+        }
+        FileState::Idle | FileState::Flushed if len == 0 => Ok(0),
+        state @ (FileState::Idle | FileState::Flushed) => {
+            spawn_file_op(&env, file, FileOp::Read(len.min(MAX_READ_LEN)), state);
+
+            Ok(WOULD_BLOCK.into())
+        }
+    }
+}
+
+/// Writes `len` bytes from wasm memory to the file, returning `len`. The write finishes in the
+/// background, and if it fails, the error is returned by the next call on the handle. The bytes
+/// aren't saved to flash until they fill a chunk or the file is flushed with `fs_flush`.
+///
+/// Returns `WOULD_BLOCK` without writing anything if too many reads and writes are in progress.
+#[syscall("fs_write")]
+pub extern "wasm" fn fs_write(
+    caller: Caller<'_, Env>,
+    handle: i32,
+    buf_ptr: usize,
+    len: usize,
+) -> Result<i64, wasmi::Error> {
+    let env = caller.data();
+
+    let Some(file) = get_file(env, handle) else {
+        return Ok(INVALID_HANDLE.into());
+    };
+
+    let state = file.take_state();
+
+    let rewind = match &state {
+        FileState::Busy => return Ok(WOULD_BLOCK.into()),
+        FileState::Failed(e) => return Ok(fs_error_code(e).into()),
+        FileState::Read(bytes) => bytes.len(),
+        FileState::Idle | FileState::Flushed => 0,
+    };
+
+    let bytes = match env.lock_data_blocking().memory_slice(&caller, buf_ptr, len) {
+        Ok(bytes) => bytes.to_vec(),
+        Err(e) => {
+            file.set_state(state);
+            return Err(e);
+        }
+    };
+
+    if !spawn_file_op(env, file, FileOp::Write { bytes, rewind }, state) {
+        return Ok(WOULD_BLOCK.into());
+    }
+
+    Ok(len as i64)
+}
+
+/// Moves the cursor of the file, returning the new position. `whence` is 0 to seek from the start,
+/// 1 to seek from the current position, and 2 to seek from the end.
+#[syscall("fs_seek")]
+pub extern "wasm" fn fs_seek(
+    caller: Caller<'_, Env>,
+    handle: i32,
+    whence: u32,
+    offset: i64,
+) -> Result<i64, wasmi::Error> {
+    let Some(file) = get_file(caller.data(), handle) else {
+        return Ok(INVALID_HANDLE.into());
+    };
+
+    let unread = match file.take_state() {
+        FileState::Busy => return Ok(WOULD_BLOCK.into()),
+        FileState::Failed(e) => return Ok(fs_error_code(&e).into()),
+        FileState::Read(bytes) => bytes.len() as i64,
+        FileState::Idle | FileState::Flushed => 0,
+    };
+
+    let pos = match whence {
+        0 => SeekFrom::Start(offset as u64),
+        // bytes which were read but not taken by the app haven't been read as far as it knows.
+        1 => SeekFrom::Current(offset - unread),
+        2 => SeekFrom::End(offset),
+        _ => return Err(Error::InvalidValue("whence").into()),
+    };
+
+    // Nothing else uses the file while it's idle, so the lock is free.
+    let result = block_on(async { file.file().lock().await.seek(pos).await });
+
+    Ok(result.map_or_else(|e| fs_error_code(&e).into(), |position| position as i64))
+}
+
+/// Shrinks the file to `len` bytes. Bytes which were read but not taken by the app are dropped.
+#[syscall("fs_truncate")]
+pub extern "wasm" fn fs_truncate(
+    caller: Caller<'_, Env>,
+    handle: i32,
+    len: u64,
+) -> Result<i32, wasmi::Error> {
+    let Some(file) = get_file(caller.data(), handle) else {
+        return Ok(INVALID_HANDLE);
+    };
+
+    let unread = match file.take_state() {
+        FileState::Busy => return Ok(WOULD_BLOCK),
+        FileState::Failed(e) => return Ok(fs_error_code(&e)),
+        FileState::Read(bytes) => bytes.len() as i64,
+        FileState::Idle | FileState::Flushed => 0,
+    };
+
+    let result = block_on(async {
+        let mut inner = file.file().lock().await;
+
+        // put the cursor back where the app expects it to be before it's clamped to the new end.
+        inner.seek(SeekFrom::Current(-unread)).await?;
+        inner.truncate(len).await
+    });
+
+    Ok(result.map_or_else(|e| fs_error_code(&e), |_| 0))
+}
+
+/// Saves everything written to the file to flash. Writes are buffered until a chunk fills up, so
+/// this is the only way to know the data is durable.
+///
+/// The first call starts the flush and returns `WOULD_BLOCK`, and a call after it's finished
+/// returns 0.
+#[syscall("fs_flush")]
+pub extern "wasm" fn fs_flush(caller: Caller<'_, Env>, handle: i32) -> Result<i32, wasmi::Error> {
+    let env = caller.data();
+
+    let Some(file) = get_file(env, handle) else {
+        return Ok(INVALID_HANDLE);
+    };
+
+    match file.take_state() {
+        FileState::Busy => Ok(WOULD_BLOCK),
+        FileState::Failed(e) => Ok(fs_error_code(&e)),
+        FileState::Flushed => Ok(0),
+        // reads flush the file before they start, and nothing has been written since.
+        FileState::Read(bytes) => {
+            file.set_state(FileState::Read(bytes));
+
+            Ok(0)
+        }
+        FileState::Idle => {
+            spawn_file_op(env, file, FileOp::Flush, FileState::Idle);
+
+            Ok(WOULD_BLOCK)
+        }
+    }
+}
+
+/// Closes the file. Anything which hasn't been flushed is saved in the background after any write
+/// still in progress, but errors from either are lost. Use `fs_flush` first to know the data was
+/// saved.
+///
+/// Returns `WOULD_BLOCK` and leaves the file open if too many reads and writes are in progress to
+/// start saving it.
+#[syscall("fs_close")]
+pub extern "wasm" fn fs_close(caller: Caller<'_, Env>, handle: i32) -> Result<i32, wasmi::Error> {
+    let env = caller.data();
+
+    let Some((index, file)) = handle_index(handle)
+        .and_then(|index| Some((index, env.lock_data_blocking().get_file(index)?)))
+    else {
+        return Ok(INVALID_HANDLE);
+    };
+
+    let state = file.take_state();
+
+    // the flush waits for the file's lock, so it runs after a write in progress. The file is only
+    // closed once the flush has started, so unsaved data isn't dropped when there's no free task.
+    if !matches!(state, FileState::Flushed | FileState::Read(_))
+        && env.spawn(run_file_op(file.clone(), FileOp::Flush)).is_err()
+    {
+        // a busy file was left alone by `take_state`, and the write in progress will finish it.
+        if !matches!(state, FileState::Busy) {
+            file.set_state(state);
+        }
+
+        return Ok(WOULD_BLOCK);
+    }
+
+    env.lock_data_blocking().close_file(index);
+
+    match state {
+        FileState::Failed(e) => Ok(fs_error_code(&e)),
+        _ => Ok(0),
+    }
+}
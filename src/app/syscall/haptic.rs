@@ -0,0 +1,19 @@
+use crate::app::types::Env;
+use crate::driver::haptic::{self, MAX_INTENSITY};
+use crate::macros::syscall;
+use embassy_time::Duration;
+use wasmi::Caller;
+
+/// Vibrates for `ms` milliseconds at `intensity`, from 0 to 100. Larger intensities are clamped.
+#[syscall("vibrate")]
+pub extern "wasm" fn vibrate(
+    _: Caller<'_, Env>,
+    ms: u32,
+    intensity: u32,
+) -> Result<(), wasmi::Error> {
+    let intensity = intensity.min(MAX_INTENSITY as u32) as u8;
+
+    haptic::vibrate(Duration::from_millis(ms as u64), intensity);
+
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use crate::app::types::Env;
+use crate::driver::input;
+use crate::macros::syscall;
+use wasmi::Caller;
+
+/// The id passed to `schedule_io` to be woken when there's a button event to poll.
+pub const INPUT_IO_ID: i32 = 0;
+
+#[syscall("poll_input")]
+pub extern "wasm" fn poll_input(
+    mut caller: Caller<'_, Env>,
+    out_ptr: usize,
+) -> Result<i32, wasmi::Error> {
+    let Some(event) = input::poll() else {
+        return Ok(0);
+    };
+
+    let memory = caller.data().lock_data_blocking().memory();
+    memory.write(&mut caller, out_ptr, &event.to_u32().to_le_bytes())?;
+
+    Ok(1)
+}
@@ -1,11 +1,14 @@
-use crate::app::types::{Env, Error, Registration, WakerFunc};
+use super::fs;
+use super::input::INPUT_IO_ID;
+use crate::app::types::{Env, Error, Interest, OpenFile, Registration, WakerFunc};
+use crate::driver::input;
 use crate::macros::task;
-use embassy_time::{Duration, Instant, Timer};
-use esp_println::dbg;
+use alloc::sync::Arc;
+use embassy_time::{Instant, Timer};
 use wasmi::Caller;
 use xenon_proc_macros::syscall;
 
-#[syscall]
+#[syscall("schedule_timer")]
 pub extern "wasm" fn schedule_timer(
     caller: Caller<'_, Env>,
     func_index: u32,
@@ -30,13 +33,13 @@ pub extern "wasm" fn schedule_timer(
             data: u32,
             deadline: Instant
         ) {
-            Timer::after_secs(1).await;
+            Timer::at(deadline).await;
             env.push_registration(Registration::new_timer(deadline, data, wake_func)).await;
         }
     })
 }
 
-#[syscall]
+#[syscall("schedule_io")]
 pub extern "wasm" fn schedule_io(
     caller: Caller<'_, Env>,
     wake_index: u32,
@@ -44,5 +47,52 @@ pub extern "wasm" fn schedule_io(
     readable: bool,
     writable: bool,
 ) -> Result<(), wasmi::Error> {
-    todo!()
+    let env = caller.data();
+    let env_data = env.lock_data_blocking();
+
+    let wake_func = env_data
+        .get_func(&caller, wake_index)
+        .func()
+        .ok_or(Error::NullFunction)?
+        .typed::<u32, ()>(&caller)?;
+
+    // looking up a file handle locks the data again.
+    drop(env_data);
+
+    let mut interest = Interest::empty();
+    interest.set(Interest::READ, readable);
+    interest.set(Interest::WRITE, writable);
+
+    match id {
+        // button events can only be read.
+        INPUT_IO_ID if interest == Interest::READ => env.spawn(task! {
+            (
+                env: Env = env.clone(),
+                wake_func: WakerFunc,
+                id: i32,
+                interest: Interest
+            ) {
+                input::ready().await;
+                env.push_registration(Registration::new_io(id, interest, id as u32, wake_func)).await;
+            }
+        }),
+        // file handles are ready once their last read or write has finished.
+        _ if !interest.is_empty() => {
+            let file = fs::get_file(env, id).ok_or(Error::InvalidId(id))?;
+
+            env.spawn(task! {
+                (
+                    env: Env = env.clone(),
+                    file: Arc<OpenFile>,
+                    wake_func: WakerFunc,
+                    id: i32,
+                    interest: Interest
+                ) {
+                    file.ready().await;
+                    env.push_registration(Registration::new_io(id, interest, id as u32, wake_func)).await;
+                }
+            })
+        }
+        _ => Err(Error::InvalidId(id).into()),
+    }
 }
@@ -1,18 +1,126 @@
-use crate::app::types::{Env, Error};
-use crate::driver::lcd::LCD_BUFFER;
+use crate::allocator;
+use crate::app::types::{DrawGuard, Env, Error, PollRequest};
+use crate::driver::lcd::{self, LCD_BUFFER, LCD_BUFFER_SIZE, LCD_X, LCD_Y};
 use crate::macros::{syscall, task};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Point, Size};
+use embedded_graphics::primitives::Rectangle;
 use wasmi::Caller;
 
-#[syscall]
+#[syscall("clear_buffer")]
 pub extern "wasm" fn clear_buffer(caller: Caller<'_, Env>) -> Result<(), wasmi::Error> {
     caller.data().spawn(task! {
-        () {
+        (_draw: DrawGuard = caller.data().begin_draw()) {
             LCD_BUFFER.lock().await.clear();
         }
     })
 }
 
-#[syscall]
+/// Clears a rectangle of the display to white.
+#[syscall("clear_region")]
+pub extern "wasm" fn clear_region(
+    caller: Caller<'_, Env>,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+) -> Result<(), wasmi::Error> {
+    let rect = Rectangle::new(Point::new(x, y), Size::new(w, h));
+
+    caller.data().spawn(task! {
+        (_draw: DrawGuard = caller.data().begin_draw(), rect: Rectangle) {
+            LCD_BUFFER.lock().await.fill_rect(&rect, BinaryColor::Off);
+        }
+    })
+}
+
+/// Turns double buffering on or off. While it's on, nothing drawn is shown until `present` is
+/// called.
+#[syscall("set_double_buffered")]
+pub extern "wasm" fn set_double_buffered(
+    caller: Caller<'_, Env>,
+    enabled: bool,
+) -> Result<(), wasmi::Error> {
+    caller.data().spawn(task! {
+        (_draw: DrawGuard = caller.data().begin_draw(), enabled: bool) {
+            lcd::set_double_buffered(enabled).await;
+        }
+    })
+}
+
+/// Sets the most times per second the display is refreshed, clamped to 1-60, and returns the rate
+/// that was set.
+#[syscall("set_refresh_rate")]
+pub extern "wasm" fn set_refresh_rate(_: Caller<'_, Env>, hz: u32) -> Result<u32, wasmi::Error> {
+    Ok(lcd::set_refresh_rate(hz))
+}
+
+/// Writes the width and height of the display, in pixels, to `w_ptr` and `h_ptr` as `u32`s.
+#[syscall("get_display_size")]
+pub extern "wasm" fn get_display_size(
+    mut caller: Caller<'_, Env>,
+    w_ptr: usize,
+    h_ptr: usize,
+) -> Result<(), wasmi::Error> {
+    let memory = caller.data().lock_data_blocking().memory();
+
+    memory.write(&mut caller, w_ptr, &(LCD_X as u32).to_le_bytes())?;
+    memory.write(&mut caller, h_ptr, &(LCD_Y as u32).to_le_bytes())?;
+
+    Ok(())
+}
+
+/// Copies the display buffer, [`LCD_BUFFER_SIZE`] bytes packed like [`LcdBuffer::as_bytes`], to
+/// `ptr`. Like `present`, this suspends the app until everything it drew before calling it is in
+/// the buffer.
+///
+/// [`LcdBuffer::as_bytes`]: crate::driver::lcd::LcdBuffer::as_bytes
+#[syscall("read_framebuffer")]
+pub extern "wasm" fn read_framebuffer(
+    caller: Caller<'_, Env>,
+    ptr: usize,
+) -> Result<(), wasmi::Error> {
+    let mut env_data = caller.data().lock_data_blocking();
+
+    // checked now, so a bad pointer traps here instead of after the app is suspended. Memory never
+    // shrinks, so the range is still valid when the executor copies the buffer.
+    env_data.memory_slice(&caller, ptr, LCD_BUFFER_SIZE)?;
+    env_data.set_framebuffer_read(ptr);
+
+    Err(PollRequest::Wait.into())
+}
+
+/// Writes the firmware's heap usage to `ptr` as five `u32`s: the bytes used and free in the PSRAM
+/// heap, the bytes used and free in the internal RAM heap, and the largest allocation the PSRAM
+/// heap could make. When the largest allocation is much smaller than the free bytes, the heap is
+/// fragmented.
+#[syscall("alloc_stats")]
+pub extern "wasm" fn alloc_stats(
+    mut caller: Caller<'_, Env>,
+    ptr: usize,
+) -> Result<(), wasmi::Error> {
+    let stats = allocator::alloc_stats();
+    let fields = [
+        stats.psram_used,
+        stats.psram_free,
+        stats.internal_used,
+        stats.internal_free,
+        stats.largest_free_block,
+    ];
+
+    let mut bytes = [0; 5 * size_of::<u32>()];
+
+    for (dest, field) in bytes.chunks_exact_mut(size_of::<u32>()).zip(fields) {
+        dest.copy_from_slice(&(field as u32).to_le_bytes());
+    }
+
+    let memory = caller.data().lock_data_blocking().memory();
+    memory.write(&mut caller, ptr, &bytes)?;
+
+    Ok(())
+}
+
+#[syscall("clone_binary_data")]
 pub extern "wasm" fn clone_binary_data(
     caller: Caller<'_, Env>,
     id: i32,
@@ -28,7 +136,7 @@ pub extern "wasm" fn clone_binary_data(
     Ok(index as i32)
 }
 
-#[syscall]
+#[syscall("drop_binary_data")]
 pub extern "wasm" fn drop_binary_data(
     caller: Caller<'_, Env>,
     id: i32,
@@ -1,8 +1,125 @@
+use crate::app::types::Env;
+use alloc::vec::Vec;
+use core::fmt;
+use linkme::distributed_slice;
+use wasmi::core::ValType;
+use wasmi::{FuncType, Linker};
+
 pub mod asynch;
+pub mod fs;
+pub mod haptic;
+pub mod input;
 pub mod misc;
 pub mod panic;
+pub mod power;
 pub mod rng;
+pub mod signature;
 pub mod stdio;
 pub mod time;
 pub mod widget;
 pub mod io;
+
+/// The module apps import syscalls from.
+pub const SYSCALL_NAMESPACE: &str = "__xenon_syscall";
+
+/// Every syscall registered with `#[syscall("name")]`.
+#[distributed_slice]
+pub static SYSCALLS: [SyscallEntry];
+
+/// Every registered syscall sorted by name, since the order of [`SYSCALLS`] depends on the order
+/// the linker places them in.
+pub fn syscalls() -> Vec<&'static SyscallEntry> {
+    let mut syscalls: Vec<_> = SYSCALLS.iter().collect();
+    syscalls.sort_unstable_by_key(|syscall| syscall.name);
+    syscalls
+}
+
+/// Logs the signature of every syscall, so app developers can see the ABI the firmware provides.
+pub fn log_syscalls() {
+    log::info!("{} syscalls in namespace `{SYSCALL_NAMESPACE}`:", SYSCALLS.len());
+
+    for syscall in syscalls() {
+        log::info!("    {syscall}");
+    }
+}
+
+/// A syscall, its signature, and how to add it to a [`Linker`].
+pub struct SyscallEntry {
+    name: &'static str,
+    params: &'static [ValType],
+    results: &'static [ValType],
+    link: fn(&mut Linker<Env>) -> Result<(), wasmi::Error>,
+}
+
+impl SyscallEntry {
+    pub const fn new(
+        name: &'static str,
+        params: &'static [ValType],
+        results: &'static [ValType],
+        link: fn(&mut Linker<Env>) -> Result<(), wasmi::Error>,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            results,
+            link,
+        }
+    }
+
+    /// Finds the syscall registered as `name`.
+    pub fn find(name: &str) -> Option<&'static SyscallEntry> {
+        SYSCALLS.iter().find(|syscall| syscall.name == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn params(&self) -> &'static [ValType] {
+        self.params
+    }
+
+    pub fn results(&self) -> &'static [ValType] {
+        self.results
+    }
+
+    /// The signature as a wasmi [`FuncType`], for comparing against a module's imports or
+    /// inspecting it at runtime.
+    pub fn func_type(&self) -> FuncType {
+        FuncType::new(self.params.iter().copied(), self.results.iter().copied())
+    }
+
+    /// Whether an import of type `ty` can be linked to this syscall.
+    pub fn matches(&self, ty: &FuncType) -> bool {
+        ty.params() == self.params && ty.results() == self.results
+    }
+
+    pub fn link(&self, linker: &mut Linker<Env>) -> Result<(), wasmi::Error> {
+        (self.link)(linker)
+    }
+}
+
+/// Formats the signature the same way it's written in the wat format, such as
+/// `print(i32, i32, i32) -> ()`, so it can be compared against the guest bindings. This is also the
+/// format of [`signature::SIGNATURES`].
+impl fmt::Display for SyscallEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_types(f: &mut fmt::Formatter<'_>, types: &[ValType]) -> fmt::Result {
+            for (i, ty) in types.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(", ")?;
+                }
+
+                f.write_str(signature::val_type_name(*ty))?;
+            }
+
+            Ok(())
+        }
+
+        write!(f, "{}(", self.name)?;
+        write_types(f, self.params)?;
+        f.write_str(") -> (")?;
+        write_types(f, self.results)?;
+        f.write_str(")")
+    }
+}
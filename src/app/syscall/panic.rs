@@ -1,28 +1,33 @@
 use crate::app::types::{Env, Error};
 use crate::macros::syscall;
+use alloc::format;
+use alloc::string::ToString;
 use wasmi::Caller;
 
-#[syscall]
+// Both syscalls stop the app with `Error::Panicked`, so the message ends up in the error the
+// executor returns instead of only in the log.
+
+#[syscall("panic")]
 pub extern "wasm" fn panic(
     caller: Caller<'_, Env>,
-    ptr: usize,
-    len: usize,
+    #[wasm(ptr, len)] message: &str,
 ) -> Result<(), wasmi::Error> {
-    let memory = caller.data().lock_data_blocking().memory();
-    let end = ptr + len;
-
-    let bytes = memory
-        .data(&caller)
-        .get(ptr..end)
-        .ok_or(Error::InvalidMemoryRange { start: ptr, end })?;
-
-    let message = core::str::from_utf8(bytes).map_err(|e| Error::InvalidUtf8 {
-        start: ptr,
-        len,
-        valid_up_to: e.valid_up_to(),
-    })?;
-
-    log::error!(target: "Wasm executor", "app panicked! message: {message}\n");
+    Err(Error::Panicked {
+        message: message.to_string(),
+    }
+    .into())
+}
 
-    Ok(())
+/// Like `panic`, but with the file and line the panic came from.
+#[syscall("panic_at")]
+pub extern "wasm" fn panic_at(
+    caller: Caller<'_, Env>,
+    #[wasm(ptr, len)] message: &str,
+    #[wasm(ptr, len)] file: &str,
+    line: u32,
+) -> Result<(), wasmi::Error> {
+    Err(Error::Panicked {
+        message: format!("{message} at {file}:{line}"),
+    }
+    .into())
 }
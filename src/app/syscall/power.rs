@@ -0,0 +1,21 @@
+use crate::app::types::Env;
+use crate::driver::battery;
+use crate::macros::syscall;
+use crate::power;
+use embassy_time::Duration;
+use wasmi::Caller;
+
+/// Returns the battery voltage in millivolts, or 0 if it can't be read.
+#[syscall("battery_millivolts")]
+pub extern "wasm" fn battery_millivolts(_: Caller<'_, Env>) -> Result<u32, wasmi::Error> {
+    Ok(battery::read_millivolts().map_or(0, u32::from))
+}
+
+/// Asks for the watch to go into light sleep for `millis` milliseconds, up to
+/// [`power::MAX_SLEEP_DURATION`]. Pressing a button wakes it up early. The sleep starts once the
+/// main core gets to it and the app's tick has ended, so this returns immediately.
+#[syscall("request_sleep")]
+pub extern "wasm" fn request_sleep(_: Caller<'_, Env>, millis: u32) -> Result<(), wasmi::Error> {
+    power::request_sleep(Duration::from_millis(millis as u64));
+    Ok(())
+}
@@ -1,35 +1,32 @@
-use crate::app::types::{Env, Error};
+use crate::app::types::Env;
 use crate::macros::syscall;
 use wasmi::Caller;
 
-#[syscall]
-pub extern "wasm" fn random_32(caller: Caller<'_, Env>) -> Result<u32, wasmi::Error> {
+#[syscall("random_u32")]
+pub extern "wasm" fn random_u32(caller: Caller<'_, Env>) -> Result<u32, wasmi::Error> {
     let mut env = caller.data().lock_data_blocking();
     Ok(env.random_32())
 }
 
-#[syscall]
-pub extern "wasm" fn random_64(caller: Caller<'_, Env>) -> Result<u64, wasmi::Error> {
+#[syscall("random_u64")]
+pub extern "wasm" fn random_u64(caller: Caller<'_, Env>) -> Result<u64, wasmi::Error> {
     let mut env = caller.data().lock_data_blocking();
     Ok(env.random_64())
 }
 
-#[syscall]
+#[syscall("random_bytes")]
 pub extern "wasm" fn random_bytes(
     mut caller: Caller<'_, Env>,
     ptr: usize,
     len: usize,
 ) -> Result<(), wasmi::Error> {
-    let memory = caller.data().lock_data_blocking().memory();
-    let end = ptr + len;
-    let (memory_data, store) = memory.data_and_store_mut(&mut caller);
-    let mut env = store.lock_data_blocking();
+    // `caller` needs to be borrowed mutably while the env data is locked, so the lock can't be
+    // taken through `caller.data()`.
+    let env = caller.data().clone();
+    let mut env_data = env.lock_data_blocking();
 
-    let bytes = memory_data
-        .get_mut(ptr..end)
-        .ok_or(Error::InvalidMemoryRange { start: ptr, end })?;
-
-    env.random_bytes(bytes);
+    let bytes = env_data.memory_slice_mut(&mut caller, ptr, len)?;
+    env_data.random_bytes(bytes);
 
     Ok(())
 }
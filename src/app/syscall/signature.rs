@@ -0,0 +1,244 @@
+// Syscall signatures are checked against `signatures.txt` while compiling, so changing one breaks
+// the build until the file is updated too. The SDK's bindings can be diffed against the same file,
+// so a mismatch is caught when building an app instead of when it's loaded.
+//
+// Each line of the file is a syscall's signature as it's formatted by `SyscallEntry`, sorted by
+// name. Everything here is `const fn` so `#[syscall]` can check its syscall in a `const` item.
+
+use wasmi::core::ValType;
+
+/// The signature of every syscall, one per line.
+pub const SIGNATURES: &str = include_str!("signatures.txt");
+
+/// How a syscall compares to its line in a signature table.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SignatureCheck {
+    Matches,
+    /// The syscall's line has different parameters or results.
+    Mismatched,
+    /// There's no line for the syscall.
+    Missing,
+}
+
+/// Fails to compile when it's evaluated in a `const` item if the syscall doesn't match its line
+/// in [`SIGNATURES`]. Used by `#[syscall]`.
+pub const fn assert_signature(name: &str, params: &[ValType], results: &[ValType]) {
+    match check_signature(SIGNATURES, name, params, results) {
+        SignatureCheck::Matches => {}
+        SignatureCheck::Mismatched => {
+            panic!("syscall signature doesn't match `src/app/syscall/signatures.txt`")
+        }
+        SignatureCheck::Missing => {
+            panic!("syscall is missing from `src/app/syscall/signatures.txt`")
+        }
+    }
+}
+
+/// Compares a syscall against its line in `table`, which has the same format as [`SIGNATURES`].
+pub const fn check_signature(
+    table: &str,
+    name: &str,
+    params: &[ValType],
+    results: &[ValType],
+) -> SignatureCheck {
+    let table = table.as_bytes();
+    let mut start = 0;
+
+    while start < table.len() {
+        let mut end = start;
+
+        while end < table.len() && table[end] != b'\n' {
+            end += 1;
+        }
+
+        if let Some(pos) = expect(table, start, end, name.as_bytes()) {
+            if let Some(pos) = expect(table, pos, end, b"(") {
+                let matches = match expect_types(table, pos, end, params) {
+                    Some(pos) => match expect(table, pos, end, b") -> (") {
+                        Some(pos) => match expect_types(table, pos, end, results) {
+                            Some(pos) => {
+                                matches!(expect(table, pos, end, b")"), Some(pos) if pos == end)
+                            }
+                            None => false,
+                        },
+                        None => false,
+                    },
+                    None => false,
+                };
+
+                return if matches {
+                    SignatureCheck::Matches
+                } else {
+                    SignatureCheck::Mismatched
+                };
+            }
+        }
+
+        start = end + 1;
+    }
+
+    SignatureCheck::Missing
+}
+
+/// The name of `ty` in the wat format.
+pub const fn val_type_name(ty: ValType) -> &'static str {
+    match ty {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+        ValType::FuncRef => "funcref",
+        ValType::ExternRef => "externref",
+    }
+}
+
+// Returns the position after `expected` if it's at `pos`, without going past `end`.
+const fn expect(table: &[u8], pos: usize, end: usize, expected: &[u8]) -> Option<usize> {
+    if end - pos < expected.len() {
+        return None;
+    }
+
+    let mut i = 0;
+
+    while i < expected.len() {
+        if table[pos + i] != expected[i] {
+            return None;
+        }
+
+        i += 1;
+    }
+
+    Some(pos + expected.len())
+}
+
+// Returns the position after `types` if they're at `pos`, separated by commas.
+const fn expect_types(
+    table: &[u8],
+    mut pos: usize,
+    end: usize,
+    types: &[ValType],
+) -> Option<usize> {
+    let mut i = 0;
+
+    while i < types.len() {
+        if i != 0 {
+            pos = match expect(table, pos, end, b", ") {
+                Some(pos) => pos,
+                None => return None,
+            };
+        }
+
+        pos = match expect(table, pos, end, val_type_name(types[i]).as_bytes()) {
+            Some(pos) => pos,
+            None => return None,
+        };
+
+        i += 1;
+    }
+
+    Some(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const TABLE: &str =
+        "draw_bitmap_region(i32, i32) -> ()\nfs_read(i32, i32, i32) -> (i64)\nwait() -> ()\n";
+
+    fn parse_types(types: &str) -> Vec<ValType> {
+        types
+            .split(", ")
+            .filter(|ty| !ty.is_empty())
+            .map(|ty| match ty {
+                "i32" => ValType::I32,
+                "i64" => ValType::I64,
+                "f32" => ValType::F32,
+                "f64" => ValType::F64,
+                _ => panic!("unknown type `{ty}`"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matching_signatures() {
+        use ValType::*;
+
+        let check = |name, params, results| check_signature(TABLE, name, params, results);
+
+        assert_eq!(
+            check("fs_read", &[I32, I32, I32], &[I64]),
+            SignatureCheck::Matches
+        );
+        assert_eq!(check("wait", &[], &[]), SignatureCheck::Matches);
+        assert_eq!(
+            check("draw_bitmap_region", &[I32, I32], &[]),
+            SignatureCheck::Matches
+        );
+    }
+
+    #[test]
+    fn mismatched_signatures() {
+        use ValType::*;
+
+        let check = |name, params, results| check_signature(TABLE, name, params, results);
+
+        assert_eq!(
+            check("fs_read", &[I32, I32], &[I64]),
+            SignatureCheck::Mismatched
+        );
+        assert_eq!(
+            check("fs_read", &[I32, I32, I32, I32], &[I64]),
+            SignatureCheck::Mismatched
+        );
+        assert_eq!(
+            check("fs_read", &[I32, I32, I64], &[I64]),
+            SignatureCheck::Mismatched
+        );
+        assert_eq!(
+            check("fs_read", &[I32, I32, I32], &[I32]),
+            SignatureCheck::Mismatched
+        );
+        assert_eq!(
+            check("fs_read", &[I32, I32, I32], &[]),
+            SignatureCheck::Mismatched
+        );
+        assert_eq!(check("wait", &[], &[I32]), SignatureCheck::Mismatched);
+    }
+
+    #[test]
+    fn missing_signatures() {
+        let check = |name| check_signature(TABLE, name, &[], &[]);
+
+        assert_eq!(check("draw_bitmap"), SignatureCheck::Missing);
+        assert_eq!(check("fs_rea"), SignatureCheck::Missing);
+        assert_eq!(check("wait_for"), SignatureCheck::Missing);
+    }
+
+    #[test]
+    fn signatures_file_is_sorted_and_well_formed() {
+        let mut names = Vec::new();
+
+        for line in SIGNATURES.lines() {
+            let (name, rest) = line.split_once('(').expect("line should have parameters");
+            let (params, results) = rest.split_once(") -> (").expect("line should have results");
+            let results = results.strip_suffix(')').expect("results should be closed");
+
+            let check = check_signature(
+                SIGNATURES,
+                name,
+                &parse_types(params),
+                &parse_types(results),
+            );
+
+            assert_eq!(check, SignatureCheck::Matches, "{line}");
+            names.push(name);
+        }
+
+        assert!(
+            names.windows(2).all(|pair| pair[0] < pair[1]),
+            "names should be sorted and unique"
+        );
+    }
+}
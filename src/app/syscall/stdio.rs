@@ -1,73 +1,100 @@
 use crate::app::types::{Env, Error};
+use crate::logger;
 use crate::macros::syscall;
 use esp_println::print;
-use log::Level as LogLevel;
+use log::{Level as LogLevel, LevelFilter};
 use wasmi::Caller;
 
+const LOG_LEVEL_OFF: u32 = 0;
 const LOG_LEVEL_ERROR: u32 = 1;
 const LOG_LEVEL_WARN: u32 = 2;
 const LOG_LEVEL_INFO: u32 = 3;
 const LOG_LEVEL_DEBUG: u32 = 4;
 const LOG_LEVEL_TRACE: u32 = 5;
 
-#[syscall]
+#[syscall("print", "eprint")]
 pub extern "wasm" fn print(
     caller: Caller<'_, Env>,
-    ptr: usize,
-    len: usize,
+    #[wasm(ptr, len)] string: &str,
     newline: bool,
 ) -> Result<(), wasmi::Error> {
-    let end = ptr + len;
     let new_line = if newline { "\n" } else { "" };
 
-    let memory = caller.data().lock_data_blocking().memory();
-    let range = memory
-        .data(&caller)
-        .get(ptr..end)
-        .ok_or(Error::InvalidMemoryRange { start: ptr, end })?;
-
-    let string = core::str::from_utf8(range).map_err(|e| Error::InvalidUtf8 {
-        start: ptr,
-        len,
-        valid_up_to: e.valid_up_to(),
-    })?;
-
     print!("{string}{new_line}");
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("log")]
 pub extern "wasm" fn log(
     caller: Caller<'_, Env>,
     level: u32,
-    ptr: usize,
-    len: usize,
+    #[wasm(ptr, len)] string: &str,
 ) -> Result<(), wasmi::Error> {
-    let end = ptr + len;
+    log::log!(log_level(level)?, "{string}");
 
-    let level = match level {
-        LOG_LEVEL_ERROR => LogLevel::Error,
-        LOG_LEVEL_WARN => LogLevel::Warn,
-        LOG_LEVEL_INFO => LogLevel::Info,
-        LOG_LEVEL_DEBUG => LogLevel::Debug,
-        LOG_LEVEL_TRACE => LogLevel::Trace,
-        unknown => return Err(Error::InvalidLogLevel(unknown).into()),
-    };
+    Ok(())
+}
 
-    let memory = caller.data().lock_data_blocking().memory();
-    let range = memory
-        .data(&caller)
-        .get(ptr..end)
-        .ok_or(Error::InvalidMemoryRange { start: ptr, end })?;
+/// Sets the most verbose level logged for targets starting with `target`, so a noisy part of the
+/// firmware can be quieted while debugging another. `level` is 0 to turn the targets off, or one of
+/// the levels `log` takes. Levels can't be raised above the firmware's own. Returns 0, or -1 if
+/// `target` is too long or too many targets have levels already.
+#[syscall("log_set_target_level")]
+pub extern "wasm" fn log_set_target_level(
+    caller: Caller<'_, Env>,
+    #[wasm(ptr, len)] target: &str,
+    level: u32,
+) -> Result<i32, wasmi::Error> {
+    let filter = match level {
+        LOG_LEVEL_OFF => LevelFilter::Off,
+        level => log_level(level)?.to_level_filter(),
+    };
 
-    let string = core::str::from_utf8(range).map_err(|e| Error::InvalidUtf8 {
-        start: ptr,
-        len,
-        valid_up_to: e.valid_up_to(),
-    })?;
+    Ok(if logger::set_target_level(target, filter) {
+        0
+    } else {
+        -1
+    })
+}
 
-    log::log!(level, "{string}");
+/// Removes the level `log_set_target_level` set for `target`.
+#[syscall("log_clear_target_level")]
+pub extern "wasm" fn log_clear_target_level(
+    caller: Caller<'_, Env>,
+    #[wasm(ptr, len)] target: &str,
+) -> Result<(), wasmi::Error> {
+    logger::clear_target_level(target);
 
     Ok(())
 }
+
+/// Copies as much of the firmware's recent log lines as fits in `len` bytes to `ptr`, oldest first
+/// and separated by `\n`, and returns the number of bytes copied. The history keeps lines logged
+/// before serial was connected, so it can show what happened while booting.
+#[syscall("read_log_history")]
+pub extern "wasm" fn read_log_history(
+    mut caller: Caller<'_, Env>,
+    ptr: usize,
+    len: usize,
+) -> Result<u32, wasmi::Error> {
+    // `caller` needs to be borrowed mutably while the env data is locked, so the lock can't be
+    // taken through `caller.data()`.
+    let env = caller.data().clone();
+    let env_data = env.lock_data_blocking();
+
+    let buf = env_data.memory_slice_mut(&mut caller, ptr, len)?;
+
+    Ok(logger::read_log_history(buf) as u32)
+}
+
+fn log_level(level: u32) -> Result<LogLevel, Error> {
+    match level {
+        LOG_LEVEL_ERROR => Ok(LogLevel::Error),
+        LOG_LEVEL_WARN => Ok(LogLevel::Warn),
+        LOG_LEVEL_INFO => Ok(LogLevel::Info),
+        LOG_LEVEL_DEBUG => Ok(LogLevel::Debug),
+        LOG_LEVEL_TRACE => Ok(LogLevel::Trace),
+        unknown => Err(Error::InvalidLogLevel(unknown)),
+    }
+}
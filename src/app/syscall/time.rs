@@ -1,9 +1,22 @@
 use crate::app::types::Env;
+use crate::driver::rtc;
 use crate::macros::syscall;
 use embassy_time::Instant;
 use wasmi::Caller;
 
-#[syscall]
+#[syscall("get_time")]
 pub extern "wasm" fn get_time(_: Caller<'_, Env>) -> Result<u64, wasmi::Error> {
     Ok(Instant::now().as_micros())
 }
+
+/// Returns the wall-clock time as seconds since the Unix epoch.
+#[syscall("get_unix_time")]
+pub extern "wasm" fn get_unix_time(_: Caller<'_, Env>) -> Result<u64, wasmi::Error> {
+    Ok(rtc::unix_time())
+}
+
+#[syscall("set_unix_time")]
+pub extern "wasm" fn set_unix_time(_: Caller<'_, Env>, secs: u64) -> Result<(), wasmi::Error> {
+    rtc::set_unix_time(secs);
+    Ok(())
+}
@@ -1,12 +1,14 @@
-use crate::app::types::{Env, Error};
+use crate::app::types::{DrawGuard, Env, Error};
 use crate::driver::lcd;
 use crate::macros::{syscall, task};
 use crate::widget::bitmap::{
-    self, BitmapError, BitmapRef, BitmapRefMut, CompressedBitmapRef, PixelColor,
+    self, Bitmap, BitmapError, BitmapRef, BitmapRefMut, CompressedBitmapRef, PixelColor, Transform,
 };
-use core::any::type_name;
-use embedded_graphics::image::Image;
-use embedded_graphics::prelude::Point;
+use crate::widget::dither::DitheredImage;
+use crate::widget::sprite::Sprite;
+use alloc::vec::Vec;
+use embedded_graphics::prelude::{Point, Size};
+use embedded_graphics::primitives::Rectangle;
 use wasmi::Caller;
 
 fn bitmap_error_to_wasm(err: BitmapError) -> (i32, u32, u32) {
@@ -16,6 +18,8 @@ fn bitmap_error_to_wasm(err: BitmapError) -> (i32, u32, u32) {
         BitmapError::InvalidDimensions { width, height } => (-3, width as u32, height as u32),
         BitmapError::LengthMismatch { expected, actual } => (-4, expected as u32, actual as u32),
         BitmapError::DecompressionFailed(_) => (-5, 0, 0),
+        BitmapError::InvalidRunLength => (-6, 0, 0),
+        BitmapError::UnknownFormat(tag) => (-7, tag.map_or(0, |tag| tag as u32 + 1), 0),
     }
 }
 
@@ -28,35 +32,23 @@ fn pixel_to_wasm(pixel: Option<PixelColor>) -> u32 {
     }
 }
 
-fn wasm_to_pixel(pixel: u32) -> Option<PixelColor> {
-    match pixel {
-        1 => Some(PixelColor::Black),
-        2 => Some(PixelColor::White),
-        3 => Some(PixelColor::Transparent),
-        _ => None,
-    }
-}
-
-#[syscall]
+#[syscall("load_compressed_bitmap")]
 pub extern "wasm" fn load_compressed_bitmap(
     caller: Caller<'_, Env>,
     ptr: usize,
     len: usize,
 ) -> Result<i32, wasmi::Error> {
-    let memory = caller.data().lock_data_blocking().memory();
-    let end = ptr + len;
-
-    let bytes = memory
-        .data(&caller)
-        .get(ptr..end)
-        .ok_or(Error::InvalidMemoryRange { start: ptr, end })?;
+    let bytes = caller
+        .data()
+        .lock_data_blocking()
+        .memory_slice(&caller, ptr, len)?;
 
     let idx = caller.data().lock_data_blocking().push_binary_data(bytes);
 
     Ok(idx as i32)
 }
 
-#[syscall]
+#[syscall("load_bitmap")]
 pub extern "wasm" fn load_bitmap(
     mut caller: Caller<'_, Env>,
     width: u8,
@@ -68,12 +60,11 @@ pub extern "wasm" fn load_bitmap(
     let memory = caller.data().lock_data_blocking().memory();
 
     let expected_len = bitmap::expected_data_len(width, height);
-    let end = ptr + expected_len;
 
-    let bytes = memory
-        .data(&caller)
-        .get(ptr..end)
-        .ok_or(Error::InvalidMemoryRange { start: ptr, end })?;
+    let bytes = caller
+        .data()
+        .lock_data_blocking()
+        .memory_slice(&caller, ptr, expected_len)?;
 
     let bitmap = match BitmapRef::new(width, height, bytes).map_err(bitmap_error_to_wasm) {
         Ok(b) => b,
@@ -93,7 +84,7 @@ pub extern "wasm" fn load_bitmap(
     Ok(idx as i32)
 }
 
-#[syscall]
+#[syscall("decompress_bitmap")]
 pub extern "wasm" fn decompress_bitmap(
     mut caller: Caller<'_, Env>,
     id: i32,
@@ -109,10 +100,9 @@ pub extern "wasm" fn decompress_bitmap(
         .map_err(|_| Error::InvalidId(id))
         .and_then(|index| env.get_binary_data_mut(index).ok_or(Error::InvalidId(id)))?;
 
-    let compressed = CompressedBitmapRef::new(width, height, data);
     let mut buf = bitmap::bitmap_buffer();
-    let decompressed = match compressed
-        .decompress_to_ref(&mut buf)
+    let decompressed = match CompressedBitmapRef::new(width, height, data)
+        .and_then(|compressed| compressed.decompress_to_ref(&mut buf))
         .map_err(bitmap_error_to_wasm)
     {
         Ok(bitmap) => bitmap,
@@ -132,7 +122,79 @@ pub extern "wasm" fn decompress_bitmap(
     Ok(0)
 }
 
-#[syscall]
+// Replaces the bitmap data with handle `id` with the result of `f`, or writes the error to wasm
+// memory if either the data isn't a valid bitmap or `f` fails. Returns 0 or the error code.
+fn replace_bitmap(
+    mut caller: Caller<'_, Env>,
+    id: i32,
+    width: u8,
+    height: u8,
+    e1_ptr: usize,
+    e2_ptr: usize,
+    f: impl FnOnce(BitmapRef<'_>) -> Result<Bitmap, BitmapError>,
+) -> Result<i32, wasmi::Error> {
+    let mut env = caller.data().lock_data_blocking();
+    let memory = env.memory();
+
+    let data = usize::try_from(id)
+        .map_err(|_| Error::InvalidId(id))
+        .and_then(|index| env.get_binary_data_mut(index).ok_or(Error::InvalidId(id)))?;
+
+    let replacement = match BitmapRef::new(width, height, data)
+        .and_then(f)
+        .map_err(bitmap_error_to_wasm)
+    {
+        Ok(bitmap) => bitmap,
+        Err((code, e1, e2)) => {
+            // explicitly end lifetime of `env` so `caller` can be borrowed mutably.
+            drop(env);
+            memory.write(&mut caller, e1_ptr, &e1.to_le_bytes())?;
+            memory.write(&mut caller, e2_ptr, &e2.to_le_bytes())?;
+
+            return Ok(code);
+        }
+    };
+
+    data.clear();
+    data.extend_from_slice(replacement.as_ref().data());
+
+    Ok(0)
+}
+
+#[syscall("scale_bitmap")]
+pub extern "wasm" fn scale_bitmap(
+    caller: Caller<'_, Env>,
+    id: i32,
+    width: u8,
+    height: u8,
+    new_width: u8,
+    new_height: u8,
+    e1_ptr: usize,
+    e2_ptr: usize,
+) -> Result<i32, wasmi::Error> {
+    replace_bitmap(caller, id, width, height, e1_ptr, e2_ptr, |bitmap| {
+        bitmap.scale(new_width, new_height)
+    })
+}
+
+#[syscall("transform_bitmap")]
+pub extern "wasm" fn transform_bitmap(
+    caller: Caller<'_, Env>,
+    id: i32,
+    width: u8,
+    height: u8,
+    transform: Transform,
+    e1_ptr: usize,
+    e2_ptr: usize,
+) -> Result<i32, wasmi::Error> {
+    replace_bitmap(caller, id, width, height, e1_ptr, e2_ptr, |bitmap| {
+        bitmap.transform(transform)
+    })
+}
+
+/// Decompresses the bitmap before drawing it, so corrupt data traps the app instead of failing in
+/// the drawing task.
+#[syscall("draw_compressed_bitmap")]
 pub extern "wasm" fn draw_compressed_bitmap(
     caller: Caller<'_, Env>,
     id: i32,
@@ -145,33 +207,24 @@ pub extern "wasm" fn draw_compressed_bitmap(
     let env_data = env.lock_data_blocking();
 
     let index = usize::try_from(id).map_err(|_| Error::InvalidId(id))?;
+    let data = env_data
+        .get_binary_data(index)
+        .ok_or(Error::InvalidId(id))?;
 
-    match env_data.get_binary_data(index) {
-        Some(_) => {
-            env.spawn(task! {
-                (
-                    env: Env = env.clone(),
-                    width: u8,
-                    height: u8,
-                    index: usize,
-                    position: Point = Point::new(x, y),
-                ) {
-                    let env_data = env.lock_data().await;
-                    let data = env_data.get_binary_data(index).unwrap();
-
-                    let bitmap = CompressedBitmapRef::new(width, height, data);
-
-                    lcd::draw(Image::new(&bitmap, position)).await;
-                }
-            })?;
-
-            Ok(())
+    let bitmap = CompressedBitmapRef::new(width, height, data)
+        .and_then(CompressedBitmapRef::decompress)
+        .map_err(|_| Error::InvalidValue("CompressedBitmap"))?;
+
+    drop(env_data);
+
+    env.spawn(task! {
+        (_draw: DrawGuard = env.begin_draw(), bitmap: Bitmap, position: Point = Point::new(x, y)) {
+            lcd::draw_bitmap(&bitmap.as_ref(), position).await;
         }
-        None => Err(Error::InvalidId(id).into()),
-    }
+    })
 }
 
-#[syscall]
+#[syscall("draw_bitmap")]
 pub extern "wasm" fn draw_bitmap(
     caller: Caller<'_, Env>,
     id: i32,
@@ -191,10 +244,61 @@ pub extern "wasm" fn draw_bitmap(
     if BitmapRef::new(width, height, data).is_ok() {
         env.spawn(task! {
             (
+                _draw: DrawGuard = env.begin_draw(),
+                env: Env = env.clone(),
+                width: u8,
+                height: u8,
+                index: usize,
+                position: Point = Point::new(x, y),
+            ) {
+                let env = env.lock_data().await;
+                let data = env.get_binary_data(index).unwrap();
+
+                let bitmap = BitmapRef::new_prechecked(width, height, data);
+
+                lcd::draw_bitmap(&bitmap, position).await;
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Draws the part of a `width * height` bitmap inside the rectangle at `src_x, src_y` with size
+/// `src_width * src_height`, with the top left corner of the rectangle at `x, y`.
+#[syscall("draw_bitmap_region")]
+pub extern "wasm" fn draw_bitmap_region(
+    caller: Caller<'_, Env>,
+    id: i32,
+    width: u8,
+    height: u8,
+    src_x: u8,
+    src_y: u8,
+    src_width: u8,
+    src_height: u8,
+    x: i32,
+    y: i32,
+) -> Result<(), wasmi::Error> {
+    let env = caller.data();
+    let env_data = env.lock_data_blocking();
+
+    let index = usize::try_from(id).map_err(|_| Error::InvalidId(id))?;
+    let data = env_data
+        .get_binary_data(index)
+        .ok_or(Error::InvalidId(id))?;
+
+    if BitmapRef::new(width, height, data).is_ok() {
+        env.spawn(task! {
+            (
+                _draw: DrawGuard = env.begin_draw(),
                 env: Env = env.clone(),
                 width: u8,
                 height: u8,
                 index: usize,
+                source: Rectangle = Rectangle::new(
+                    Point::new(src_x as i32, src_y as i32),
+                    Size::new(src_width as u32, src_height as u32),
+                ),
                 position: Point = Point::new(x, y),
             ) {
                 let env = env.lock_data().await;
@@ -202,7 +306,7 @@ pub extern "wasm" fn draw_bitmap(
 
                 let bitmap = BitmapRef::new_prechecked(width, height, data);
 
-                lcd::draw(Image::new(&bitmap, position)).await;
+                lcd::draw(Sprite::new(bitmap, source, position)).await;
             }
         })?;
     }
@@ -210,7 +314,42 @@ pub extern "wasm" fn draw_bitmap(
     Ok(())
 }
 
-#[syscall]
+/// Draws `width * height` bytes of 8-bit grayscale pixels from wasm memory, dithered to black and
+/// white.
+#[syscall("draw_grayscale_bitmap")]
+pub extern "wasm" fn draw_grayscale_bitmap(
+    caller: Caller<'_, Env>,
+    ptr: usize,
+    width: u8,
+    height: u8,
+    x: i32,
+    y: i32,
+) -> Result<(), wasmi::Error> {
+    let env = caller.data();
+    let len = width as usize * height as usize;
+
+    let data = env
+        .lock_data_blocking()
+        .memory_slice(&caller, ptr, len)?
+        .to_vec();
+
+    env.spawn(task! {
+        (
+            _draw: DrawGuard = env.begin_draw(),
+            data: Vec<u8>,
+            width: u8,
+            height: u8,
+            position: Point = Point::new(x, y),
+        ) {
+            // the length was checked when the data was copied out of wasm memory.
+            if let Some(image) = DitheredImage::new(position, width as u32, height as u32, &data) {
+                lcd::draw(image).await;
+            }
+        }
+    })
+}
+
+#[syscall("get_bitmap_pixel")]
 pub extern "wasm" fn get_bitmap_pixel(
     caller: Caller<'_, Env>,
     id: i32,
@@ -230,7 +369,7 @@ pub extern "wasm" fn get_bitmap_pixel(
     }
 }
 
-#[syscall]
+#[syscall("set_bitmap_pixel")]
 pub extern "wasm" fn set_bitmap_pixel(
     caller: Caller<'_, Env>,
     id: i32,
@@ -238,7 +377,7 @@ pub extern "wasm" fn set_bitmap_pixel(
     height: u8,
     x: u8,
     y: u8,
-    pixel_color: u32,
+    pixel_color: PixelColor,
 ) -> Result<(), wasmi::Error> {
     let mut env = caller.data().lock_data_blocking();
 
@@ -246,10 +385,7 @@ pub extern "wasm" fn set_bitmap_pixel(
     let data = env.get_binary_data_mut(index).ok_or(Error::InvalidId(id))?;
 
     if let Ok(mut bitmap) = BitmapRefMut::new(width, height, data) {
-        let pixel =
-            wasm_to_pixel(pixel_color).ok_or(Error::InvalidValue(type_name::<PixelColor>()))?;
-
-        bitmap.set_pixel(x, y, pixel);
+        bitmap.set_pixel(x, y, pixel_color);
     }
 
     Ok(())
@@ -1,5 +1,7 @@
 mod bitmap;
 mod primitive;
+mod text;
 
 pub use bitmap::*;
 pub use primitive::*;
+pub use text::*;
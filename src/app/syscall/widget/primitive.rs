@@ -1,22 +1,27 @@
-use crate::app::types::{Env, Error};
-use crate::driver::lcd;
+use crate::app::convert::TryFromWasm;
+use crate::app::types::{DrawGuard, Env, Error};
+use crate::driver::lcd::{self, LCD_BUFFER};
 use crate::macros::{syscall, task};
-use core::any::type_name;
+use crate::widget::{normalize_winding, Widget};
+use alloc::vec::Vec;
 use core::mem::size_of;
 use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics::prelude::{Angle, Point, Size};
+use embedded_graphics::prelude::{Angle, DrawTarget, Point, Size};
 use embedded_graphics::primitives::{
-    Arc, Circle, CornerRadii, CornerRadiiBuilder, Ellipse, Line, PrimitiveStyle,
+    Arc, Circle, CornerRadii, CornerRadiiBuilder, Ellipse, Line, Polyline, PrimitiveStyle,
     PrimitiveStyleBuilder, Rectangle, RoundedRectangle, Sector, StrokeAlignment, Styled, Triangle,
 };
 use wasmi::Caller;
 
 macro_rules! draw {
-    ($t:ty, $shape:expr, $style:expr) => {{
+    ($env:expr, $t:ty, $shape:expr, $style:expr) => {{
         let styled = Styled::new($shape, $style);
 
         task! {
-            (styled: Styled<$t, PrimitiveStyle<BinaryColor>>) {
+            (
+                _draw: DrawGuard = $env.begin_draw(),
+                styled: Styled<$t, PrimitiveStyle<BinaryColor>>,
+            ) {
                 lcd::draw(styled).await;
             }
         }
@@ -24,24 +29,24 @@ macro_rules! draw {
 }
 
 fn style(
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
-) -> Result<PrimitiveStyle<BinaryColor>, Error> {
+    stroke_alignment: StrokeAlignment,
+) -> PrimitiveStyle<BinaryColor> {
     let mut style_builder = PrimitiveStyleBuilder::new()
         .stroke_width(stroke_width)
-        .stroke_alignment(wasm_to_stroke_align(stroke_alignment)?);
+        .stroke_alignment(stroke_alignment);
 
-    if let Some(color) = wasm_to_color(fill_color)? {
+    if let Some(color) = fill_color {
         style_builder = style_builder.fill_color(color);
     }
 
-    if let Some(color) = wasm_to_color(stroke_color)? {
+    if let Some(color) = stroke_color {
         style_builder = style_builder.stroke_color(color);
     }
 
-    Ok(style_builder.build())
+    style_builder.build()
 }
 
 #[inline]
@@ -71,25 +76,37 @@ fn corner_radii<'a>(iter: &mut impl Iterator<Item = &'a [u8]>) -> CornerRadii {
         .build()
 }
 
-fn wasm_to_color(v: u32) -> Result<Option<BinaryColor>, Error> {
-    match v {
-        0 => Ok(None),
-        1 => Ok(Some(BinaryColor::Off)),
-        2 => Ok(Some(BinaryColor::On)),
-        _ => Err(Error::InvalidValue(type_name::<Option<BinaryColor>>())),
-    }
-}
+// Reads `count` points, each stored as an (x, y) pair of i32s, from wasm memory at `ptr`.
+fn read_points(caller: &Caller<'_, Env>, ptr: usize, count: u32) -> Result<Vec<Point>, Error> {
+    const POINT_SIZE: usize = size_of::<i32>() * 2;
 
-fn wasm_to_stroke_align(v: u32) -> Result<StrokeAlignment, Error> {
-    match v {
-        0 => Ok(StrokeAlignment::Inside),
-        1 => Ok(StrokeAlignment::Center),
-        2 => Ok(StrokeAlignment::Outside),
-        _ => Err(Error::InvalidValue(type_name::<StrokeAlignment>())),
-    }
+    let len = (count as usize)
+        .checked_mul(POINT_SIZE)
+        .ok_or(Error::InvalidMemoryRange {
+            start: ptr,
+            end: usize::MAX,
+        })?;
+
+    let bytes = caller
+        .data()
+        .lock_data_blocking()
+        .memory_slice(caller, ptr, len)?;
+
+    let points = bytes
+        .chunks_exact(POINT_SIZE)
+        .map(|point| {
+            let (x, y) = point.split_at(size_of::<i32>());
+            let x = i32::from_le_bytes(x.try_into().unwrap());
+            let y = i32::from_le_bytes(y.try_into().unwrap());
+
+            Point::new(x, y)
+        })
+        .collect();
+
+    Ok(points)
 }
 
-#[syscall]
+#[syscall("draw_arc")]
 pub extern "wasm" fn draw_arc(
     caller: Caller<'_, Env>,
     top_left_x: i32,
@@ -97,10 +114,10 @@ pub extern "wasm" fn draw_arc(
     diameter: u32,
     angle_start: f32,
     angle_sweep: f32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let top_left = Point::new(top_left_x, top_left_y);
 
@@ -111,107 +128,213 @@ pub extern "wasm" fn draw_arc(
         Angle::from_radians(angle_sweep),
     );
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller.data().spawn(draw!(Arc, arc, style))?;
+    caller.data().spawn(draw!(caller.data(), Arc, arc, style))?;
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("draw_circle")]
 pub extern "wasm" fn draw_circle(
     caller: Caller<'_, Env>,
     top_left_x: i32,
     top_left_y: i32,
     diameter: u32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let top_left = Point::new(top_left_x, top_left_y);
 
     let circle = Circle::new(top_left, diameter);
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller.data().spawn(draw!(Circle, circle, style))?;
+    caller
+        .data()
+        .spawn(draw!(caller.data(), Circle, circle, style))?;
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("draw_ellipse")]
 pub extern "wasm" fn draw_ellipse(
     caller: Caller<'_, Env>,
     top_left_x: i32,
     top_left_y: i32,
     width: u32,
     height: u32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let top_left = Point::new(top_left_x, top_left_y);
     let size = Size::new(width, height);
 
     let ellipse = Ellipse::new(top_left, size);
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller.data().spawn(draw!(Ellipse, ellipse, style))?;
+    caller
+        .data()
+        .spawn(draw!(caller.data(), Ellipse, ellipse, style))?;
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("draw_line")]
 pub extern "wasm" fn draw_line(
     caller: Caller<'_, Env>,
     start_x: i32,
     start_y: i32,
     end_x: i32,
     end_y: i32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let start = Point::new(start_x, start_y);
     let end = Point::new(end_x, end_y);
     let line = Line { start, end };
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller.data().spawn(draw!(Line, line, style))?;
+    caller
+        .data()
+        .spawn(draw!(caller.data(), Line, line, style))?;
 
     Ok(())
 }
 
-#[syscall]
+/// Draws a closed polygon through the points at `points_ptr`. The fill is drawn as a fan of
+/// triangles from the first point, so it's only correct for convex polygons.
+#[syscall("draw_polygon")]
+pub extern "wasm" fn draw_polygon(
+    caller: Caller<'_, Env>,
+    points_ptr: usize,
+    count: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
+    stroke_width: u32,
+) -> Result<(), wasmi::Error> {
+    let mut points = read_points(&caller, points_ptr, count)?;
+
+    let fill = fill_color.map(PrimitiveStyle::with_fill);
+    let stroke = stroke_color.map(|color| PrimitiveStyle::with_stroke(color, stroke_width));
+
+    // close the outline by returning to the first point.
+    if let Some(&first) = points.first() {
+        points.push(first);
+    }
+
+    caller.data().spawn(task! {
+        (
+            _draw: DrawGuard = caller.data().begin_draw(),
+            points: Vec<Point>,
+            fill: Option<PrimitiveStyle<BinaryColor>>,
+            stroke: Option<PrimitiveStyle<BinaryColor>>,
+        ) {
+            let mut buffer = LCD_BUFFER.lock().await;
+
+            // the fan doesn't need the closing point.
+            let corners = &points[..points.len().saturating_sub(1)];
+
+            if let (Some(fill), [first, rest @ ..]) = (fill, corners) {
+                for pair in rest.windows(2) {
+                    let triangle = normalize_winding(Triangle::new(*first, pair[0], pair[1]));
+                    Styled::new(triangle, fill).render(&mut buffer);
+                }
+            }
+
+            if let Some(stroke) = stroke {
+                Styled::new(Polyline::new(&points), stroke).render(&mut buffer);
+            }
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Draws connected lines through the points at `points_ptr`.
+#[syscall("draw_polyline")]
+pub extern "wasm" fn draw_polyline(
+    caller: Caller<'_, Env>,
+    points_ptr: usize,
+    count: u32,
+    stroke_color: BinaryColor,
+    stroke_width: u32,
+) -> Result<(), wasmi::Error> {
+    let points = read_points(&caller, points_ptr, count)?;
+
+    let style = PrimitiveStyle::with_stroke(stroke_color, stroke_width);
+
+    caller.data().spawn(task! {
+        (
+            _draw: DrawGuard = caller.data().begin_draw(),
+            points: Vec<Point>,
+            style: PrimitiveStyle<BinaryColor>,
+        ) {
+            lcd::draw(Styled::new(Polyline::new(&points), style)).await;
+        }
+    })?;
+
+    Ok(())
+}
+
+#[syscall("draw_rectangle")]
 pub extern "wasm" fn draw_rectangle(
     caller: Caller<'_, Env>,
     top_left_x: i32,
     top_left_y: i32,
     width: u32,
     height: u32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let top_left = Point::new(top_left_x, top_left_y);
     let size = Size::new(width, height);
 
     let rectangle = Rectangle::new(top_left, size);
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
+
+    caller
+        .data()
+        .spawn(draw!(caller.data(), Rectangle, rectangle, style))?;
+
+    Ok(())
+}
 
-    caller.data().spawn(draw!(Rectangle, rectangle, style))?;
+#[syscall("draw_rect_xor")]
+pub extern "wasm" fn draw_rect_xor(
+    caller: Caller<'_, Env>,
+    top_left_x: i32,
+    top_left_y: i32,
+    width: u32,
+    height: u32,
+) -> Result<(), wasmi::Error> {
+    let top_left = Point::new(top_left_x, top_left_y);
+    let size = Size::new(width, height);
+
+    let rectangle = Rectangle::new(top_left, size);
+
+    caller.data().spawn(task! {
+        (_draw: DrawGuard = caller.data().begin_draw(), rectangle: Rectangle) {
+            let mut buffer = LCD_BUFFER.lock().await;
+            let _ = buffer.xor().fill_solid(&rectangle, BinaryColor::On);
+        }
+    })?;
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("draw_rounded_rectangle")]
 pub extern "wasm" fn draw_rounded_rectangle(
     caller: Caller<'_, Env>,
     top_left_x: i32,
@@ -219,27 +342,21 @@ pub extern "wasm" fn draw_rounded_rectangle(
     width: u32,
     height: u32,
     corners_ptr: usize,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     // this isn't for calculating bits, it just looks like it because 4 (width, height) pairs
     // means 8 u32s.
     #[allow(clippy::manual_bits)]
     const CORNER_ELEMS: usize = size_of::<u32>() * 8;
 
-    let memory = caller.data().lock_data_blocking().memory();
-
-    let corners_end = corners_ptr + CORNER_ELEMS;
     let corners =
-        memory
-            .data(&caller)
-            .get(corners_ptr..corners_end)
-            .ok_or(Error::InvalidMemoryRange {
-                start: corners_ptr,
-                end: corners_end,
-            })?;
+        caller
+            .data()
+            .lock_data_blocking()
+            .memory_slice(&caller, corners_ptr, CORNER_ELEMS)?;
 
     let mut corner_iter = corners.chunks_exact(size_of::<u32>());
 
@@ -249,16 +366,19 @@ pub extern "wasm" fn draw_rounded_rectangle(
 
     let rounded_rectangle = RoundedRectangle::new(Rectangle::new(top_left, size), corners);
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller
-        .data()
-        .spawn(draw!(RoundedRectangle, rounded_rectangle, style))?;
+    caller.data().spawn(draw!(
+        caller.data(),
+        RoundedRectangle,
+        rounded_rectangle,
+        style
+    ))?;
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("draw_sector")]
 pub extern "wasm" fn draw_sector(
     caller: Caller<'_, Env>,
     top_left_x: i32,
@@ -266,10 +386,10 @@ pub extern "wasm" fn draw_sector(
     diameter: u32,
     angle_start: f32,
     angle_sweep: f32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let top_left = Point::new(top_left_x, top_left_y);
 
@@ -280,14 +400,16 @@ pub extern "wasm" fn draw_sector(
         Angle::from_radians(angle_sweep),
     );
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller.data().spawn(draw!(Sector, sector, style))?;
+    caller
+        .data()
+        .spawn(draw!(caller.data(), Sector, sector, style))?;
 
     Ok(())
 }
 
-#[syscall]
+#[syscall("draw_triangle")]
 pub extern "wasm" fn draw_triangle(
     caller: Caller<'_, Env>,
     x0: i32,
@@ -296,20 +418,91 @@ pub extern "wasm" fn draw_triangle(
     y1: i32,
     x2: i32,
     y2: i32,
-    fill_color: u32,
-    stroke_color: u32,
+    fill_color: Option<BinaryColor>,
+    stroke_color: Option<BinaryColor>,
     stroke_width: u32,
-    stroke_alignment: u32,
+    stroke_alignment: StrokeAlignment,
 ) -> Result<(), wasmi::Error> {
     let v0 = Point::new(x0, y0);
     let v1 = Point::new(x1, y1);
     let v2 = Point::new(x2, y2);
 
-    let triangle = Triangle::new(v0, v1, v2);
+    let triangle = normalize_winding(Triangle::new(v0, v1, v2));
 
-    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment)?;
+    let style = style(fill_color, stroke_color, stroke_width, stroke_alignment);
 
-    caller.data().spawn(draw!(Triangle, triangle, style))?;
+    caller
+        .data()
+        .spawn(draw!(caller.data(), Triangle, triangle, style))?;
+
+    Ok(())
+}
+
+#[syscall("set_screen_pixel")]
+pub extern "wasm" fn set_screen_pixel(
+    caller: Caller<'_, Env>,
+    x: i32,
+    y: i32,
+    color: BinaryColor,
+) -> Result<(), wasmi::Error> {
+    caller.data().spawn(task! {
+        (_draw: DrawGuard = caller.data().begin_draw(), x: i32, y: i32, color: BinaryColor) {
+            LCD_BUFFER.lock().await.set_pixel(x, y, color);
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Sets `count` pixels read from wasm memory at `ptr`. Each pixel is stored like a `#[repr(C)]`
+/// struct of an `i16` x, an `i16` y and a `u8` color, followed by a byte of padding.
+#[syscall("set_screen_pixels")]
+pub extern "wasm" fn set_screen_pixels(
+    caller: Caller<'_, Env>,
+    ptr: usize,
+    count: u32,
+) -> Result<(), wasmi::Error> {
+    const PIXEL_SIZE: usize = 6;
+
+    let len = (count as usize)
+        .checked_mul(PIXEL_SIZE)
+        .ok_or(Error::InvalidMemoryRange {
+            start: ptr,
+            end: usize::MAX,
+        })?;
+
+    let bytes = caller
+        .data()
+        .lock_data_blocking()
+        .memory_slice(&caller, ptr, len)?;
+
+    let pixels = bytes
+        .chunks_exact(PIXEL_SIZE)
+        .map(|pixel| {
+            let &[xa, xb, ya, yb, color, _] = pixel else {
+                unreachable!();
+            };
+
+            let color =
+                BinaryColor::try_from_wasm(color as u32).map_err(|e| Error::InvalidValue(e.0))?;
+
+            Ok((
+                i16::from_le_bytes([xa, xb]),
+                i16::from_le_bytes([ya, yb]),
+                color,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    caller.data().spawn(task! {
+        (_draw: DrawGuard = caller.data().begin_draw(), pixels: Vec<(i16, i16, BinaryColor)>) {
+            let mut buffer = LCD_BUFFER.lock().await;
+
+            for (x, y, color) in pixels {
+                buffer.set_pixel(x, y, color);
+            }
+        }
+    })?;
 
     Ok(())
 }
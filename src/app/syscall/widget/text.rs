@@ -0,0 +1,88 @@
+use crate::app::types::{DrawGuard, Env, Error};
+use crate::driver::lcd;
+use crate::macros::{syscall, task};
+use crate::widget::text::font;
+use crate::widget::text::layout::{Config, Layout, WrapMode};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, Point};
+use wasmi::Caller;
+
+// `Layout` wraps when a glyph would cross `Config::max_width` as an x coordinate, so a width
+// relative to the start of the text is converted to that coordinate. A width of 0 disables
+// wrapping.
+fn layout_config(x: i32, max_width: u32, wrap_mode: WrapMode) -> Config {
+    let max_width = match max_width {
+        0 => None,
+        width => Some(x.saturating_add_unsigned(width)),
+    };
+
+    Config {
+        max_width,
+        max_height: None,
+        wrap_mode,
+        ..Config::default()
+    }
+}
+
+fn read_str<'a>(caller: &'a Caller<'_, Env>, ptr: usize, len: usize) -> Result<&'a str, Error> {
+    caller
+        .data()
+        .lock_data_blocking()
+        .memory_str(caller, ptr, len)
+}
+
+#[syscall("draw_text")]
+pub extern "wasm" fn draw_text(
+    caller: Caller<'_, Env>,
+    ptr: usize,
+    len: usize,
+    x: i32,
+    y: i32,
+    color: BinaryColor,
+    max_width: u32,
+    wrap_mode: WrapMode,
+) -> Result<(), wasmi::Error> {
+    let font = font::system_font().ok_or(Error::NoSystemFont)?;
+
+    let text = read_str(&caller, ptr, len)?;
+
+    let mut layout = Layout::new(
+        Point::new(x, y),
+        font,
+        layout_config(x, max_width, wrap_mode),
+    );
+    layout.with_text(text, color);
+
+    caller.data().spawn(task! {
+        (_draw: DrawGuard = caller.data().begin_draw(), layout: Layout<'static>) {
+            lcd::draw(&layout).await;
+        }
+    })
+}
+
+#[syscall("measure_text")]
+pub extern "wasm" fn measure_text(
+    mut caller: Caller<'_, Env>,
+    ptr: usize,
+    len: usize,
+    max_width: u32,
+    wrap_mode: WrapMode,
+    width_ptr: usize,
+    height_ptr: usize,
+) -> Result<(), wasmi::Error> {
+    let font = font::system_font().ok_or(Error::NoSystemFont)?;
+    let text = read_str(&caller, ptr, len)?;
+
+    // the color doesn't change the glyph metrics, so it doesn't matter which is used here.
+    let mut layout = Layout::new(Point::zero(), font, layout_config(0, max_width, wrap_mode));
+    layout.with_text(text, BinaryColor::On);
+
+    let width = layout.bounding_box().size.width;
+    let height = layout.lines_height();
+
+    let memory = caller.data().lock_data_blocking().memory();
+    memory.write(&mut caller, width_ptr, &width.to_le_bytes())?;
+    memory.write(&mut caller, height_ptr, &height.to_le_bytes())?;
+
+    Ok(())
+}
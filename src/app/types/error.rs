@@ -3,10 +3,10 @@ use core::result;
 use thiserror::Error;
 use wasmi::core::HostError;
 
-pub type Result<T> = result::Result<T, wasmi::Error>;
+pub type Result<T, E = wasmi::Error> = result::Result<T, E>;
 
 #[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Error)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Error)]
 pub enum Error {
     #[error("wasm module did not export linear memory with the name `memory`")]
     NoMemory,
@@ -35,8 +35,25 @@ pub enum Error {
     TooManyTasks,
     #[error("undefined behavior: mismatched critical section release")]
     MismatchedCriticalSection,
-    #[error("wasm module panicked")]
-    Panicked,
+    #[error("wasm module panicked: {message}")]
+    Panicked { message: String },
+    #[error("wasm module needed more fuel than it gets for a tick")]
+    OutOfFuel,
+    #[error("no system font is available for rendering text")]
+    NoSystemFont,
+    #[error("app manifest is invalid")]
+    InvalidManifest,
+    #[error("app manifest doesn't allow the `{0}` syscall")]
+    MissingCapability(&'static str),
+    #[error("app imports unknown syscall `{0}`")]
+    UnknownSyscall(String),
+    #[error("app imports the `{name}` syscall with the wrong signature, expected `{expected}`")]
+    SyscallSignature {
+        name: &'static str,
+        expected: String,
+    },
+    #[error("wasm module did not export `{0}` as a function with no parameters or results")]
+    NoEntryPoint(&'static str),
 }
 
 impl From<Error> for wasmi::Error {
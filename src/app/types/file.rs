@@ -0,0 +1,138 @@
+use crate::fs::{self, File};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex;
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_sync::signal::Signal;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum OpenMode {
+    /// Opens an existing file, failing if it doesn't exist.
+    #[default]
+    Open,
+
+    /// Creates a new file, failing if it already exists.
+    Create,
+
+    /// Opens the file if it exists, and creates it otherwise.
+    OpenOrCreate,
+}
+
+/// The result of the last background operation on a file, which is handed to the app by the next
+/// syscall on its handle.
+#[derive(Debug, Default)]
+pub enum FileState {
+    #[default]
+    Idle,
+    Busy,
+    /// Bytes which have been read from the file, but not copied to the app yet.
+    Read(Vec<u8>),
+    /// Everything written to the file has been saved to flash.
+    Flushed,
+    Failed(fs::Error),
+}
+
+/// A file opened by an app. Reads and writes run in a task on the main core, since they can't
+/// finish without flash access, and leave their result in the file's state for the app to pick up
+/// once it's woken.
+pub struct OpenFile {
+    file: Mutex<CsRawMutex, File>,
+    state: BlockingMutex<CsRawMutex, RefCell<FileState>>,
+    ready: Signal<CsRawMutex, ()>,
+}
+
+impl OpenFile {
+    pub fn new(file: File) -> Self {
+        Self {
+            file: Mutex::new(file),
+            state: BlockingMutex::new(RefCell::new(FileState::Idle)),
+            ready: Signal::new(),
+        }
+    }
+
+    pub fn file(&self) -> &Mutex<CsRawMutex, File> {
+        &self.file
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.state
+            .lock(|state| matches!(*state.borrow(), FileState::Busy))
+    }
+
+    /// Takes the state, leaving the file idle. A busy file stays busy.
+    pub fn take_state(&self) -> FileState {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+
+            match *state {
+                FileState::Busy => FileState::Busy,
+                _ => mem::take(&mut *state),
+            }
+        })
+    }
+
+    pub fn set_state(&self, new: FileState) {
+        self.state.lock(|state| *state.borrow_mut() = new);
+    }
+
+    /// Stores the result of a background operation and wakes whoever is waiting for it.
+    pub fn finish(&self, new: FileState) {
+        self.set_state(new);
+        self.ready.signal(());
+    }
+
+    /// Waits until the file isn't busy.
+    pub async fn ready(&self) {
+        while self.is_busy() {
+            self.ready.wait().await;
+        }
+    }
+}
+
+/// The files opened by an app, indexed by handle. Handles are reused once they're closed.
+#[derive(Default)]
+pub struct FileTable {
+    free_indices: Vec<usize>,
+    files: Vec<Option<Arc<OpenFile>>>,
+}
+
+impl FileTable {
+    pub const fn new() -> Self {
+        Self {
+            free_indices: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<Arc<OpenFile>> {
+        self.files.get(index).and_then(Option::clone)
+    }
+
+    pub fn insert(&mut self, file: OpenFile) -> usize {
+        let file = Some(Arc::new(file));
+
+        match self.free_indices.pop() {
+            Some(index) => {
+                self.files[index] = file;
+                index
+            }
+            None => {
+                self.files.push(file);
+                self.files.len() - 1
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Arc<OpenFile>> {
+        let file = self.files.get_mut(index)?.take();
+
+        if file.is_some() {
+            self.free_indices.push(index);
+        }
+
+        file
+    }
+}
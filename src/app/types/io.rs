@@ -1,10 +1,11 @@
 use alloc::{collections::vec_deque::VecDeque, sync::Arc};
 use bitflags::bitflags;
+use core::mem;
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex, mutex::Mutex, signal::Signal,
 };
 use embassy_time::Instant;
-use wasmi::{Store, StoreContextMut, TypedFunc};
+use wasmi::{StoreContextMut, TypedFunc, TypedResumableCall};
 
 use super::Env;
 
@@ -21,13 +22,31 @@ impl RegistrationQueue {
         }))
     }
 
+    /// Pushes a registration onto the queue. Timer registrations are kept at the front of the
+    /// queue ordered by deadline, so the earliest deadline is always woken first. Other
+    /// registrations are woken in the order they were pushed.
     pub async fn push(&self, registration: Registration) {
-        self.0.queue.lock().await.push_back(registration);
+        let mut queue = self.0.queue.lock().await;
+
+        match registration.kind {
+            RegistrationKind::Timer(deadline) => {
+                let index = queue.partition_point(|queued| match queued.kind {
+                    RegistrationKind::Timer(queued_deadline) => queued_deadline <= deadline,
+                    _ => false,
+                });
+
+                queue.insert(index, registration);
+            }
+            _ => queue.push_back(registration),
+        }
+
+        drop(queue);
         self.0.barrier.signal(());
     }
 
-    pub async fn try_pop(&self) -> Option<Registration> {
-        self.0.queue.lock().await.pop_front()
+    /// Removes every registration currently in the queue, in the order they should be woken.
+    pub async fn drain(&self) -> VecDeque<Registration> {
+        mem::take(&mut *self.0.queue.lock().await)
     }
 
     pub async fn wait(&self) {
@@ -49,8 +68,11 @@ pub struct Registration {
 }
 
 impl Registration {
-    pub fn wake<'a>(&self, ctx: impl Into<StoreContextMut<'a, Env>>) -> Result<(), wasmi::Error> {
-        self.wake.call(ctx.into(), self.data)
+    pub fn wake<'a>(
+        &self,
+        ctx: impl Into<StoreContextMut<'a, Env>>,
+    ) -> Result<TypedResumableCall<()>, wasmi::Error> {
+        self.wake.call_resumable(ctx.into(), self.data)
     }
 
     pub fn new_timer(deadline: Instant, data: u32, wake: WakerFunc) -> Self {
@@ -79,3 +101,100 @@ bitflags! {
         const WRITE = 0x2;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use embassy_futures::{block_on, poll_once};
+    use wasmi::{Engine, Func, Store};
+
+    fn waker() -> WakerFunc {
+        let mut store = Store::new(&Engine::default(), ());
+        let func = Func::wrap(&mut store, |_: u32| {});
+
+        func.typed(&store).unwrap()
+    }
+
+    fn timer(micros: u64, data: u32) -> Registration {
+        Registration::new_timer(Instant::from_micros(micros), data, waker())
+    }
+
+    fn drained_data(queue: &RegistrationQueue) -> Vec<u32> {
+        block_on(queue.drain())
+            .iter()
+            .map(|registration| registration.data)
+            .collect()
+    }
+
+    #[test]
+    fn timers_drain_in_deadline_order() {
+        let queue = RegistrationQueue::new();
+
+        block_on(async {
+            queue.push(timer(300, 3)).await;
+            queue.push(timer(100, 1)).await;
+            queue.push(timer(200, 2)).await;
+        });
+
+        assert_eq!(drained_data(&queue), [1, 2, 3]);
+    }
+
+    #[test]
+    fn timers_drain_before_io() {
+        let queue = RegistrationQueue::new();
+
+        block_on(async {
+            queue
+                .push(Registration::new_io(7, Interest::READ, 4, waker()))
+                .await;
+            queue.push(timer(200, 2)).await;
+            queue
+                .push(Registration::new_io(8, Interest::WRITE, 5, waker()))
+                .await;
+            queue.push(timer(100, 1)).await;
+            queue.push(timer(200, 3)).await;
+        });
+
+        assert_eq!(drained_data(&queue), [1, 2, 3, 4, 5]);
+        assert!(drained_data(&queue).is_empty());
+    }
+
+    #[test]
+    fn ready_registrations_wake_in_one_batch() {
+        let woken = Arc::new(AtomicU32::new(0));
+        let mut store = Store::new(&Engine::default(), Env);
+        let count = woken.clone();
+        let wake = Func::wrap(&mut store, move |_: u32| {
+            count.fetch_add(1, Ordering::Relaxed);
+        })
+        .typed(&store)
+        .unwrap();
+
+        let queue = RegistrationQueue::new();
+
+        block_on(async {
+            for id in 0..3 {
+                queue
+                    .push(Registration::new_io(id, Interest::READ, id as u32, wake))
+                    .await;
+            }
+
+            // the three pushes only notify the executor once.
+            queue.wait().await;
+        });
+
+        assert!(poll_once(queue.wait()).is_pending());
+
+        let batch = block_on(queue.drain());
+
+        for registration in &batch {
+            registration.wake(&mut store).unwrap();
+        }
+
+        assert_eq!(batch.len(), 3);
+        assert_eq!(woken.load(Ordering::Relaxed), 3);
+        assert!(drained_data(&queue).is_empty());
+    }
+}
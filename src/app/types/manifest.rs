@@ -0,0 +1,151 @@
+use super::error::Error;
+use super::wasm::{Limits, WASM_FUEL_PER_TICK, WASM_MEMORY_BUDGET, WASM_MEMORY_LIMIT};
+use alloc::string::String;
+use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
+
+bitflags! {
+    /// The syscalls an app is allowed to import, beyond the ones every app can use (drawing,
+    /// timers, logging and so on).
+    #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+    pub struct Capabilities: u32 {
+        const FILESYSTEM = 0x1;
+        const INPUT = 0x2;
+        const POWER = 0x4;
+        const HAPTIC = 0x8;
+        const SET_TIME = 0x10;
+        /// Changing how the firmware logs, and reading its diagnostics.
+        const DIAGNOSTICS = 0x20;
+    }
+}
+
+// Syscalls which aren't listed here don't need any capabilities.
+const SYSCALL_CAPABILITIES: &[(&str, Capabilities)] = &[
+    ("fs_exists", Capabilities::FILESYSTEM),
+    ("fs_size", Capabilities::FILESYSTEM),
+    ("fs_delete", Capabilities::FILESYSTEM),
+    ("fs_open", Capabilities::FILESYSTEM),
+    ("fs_read", Capabilities::FILESYSTEM),
+    ("fs_write", Capabilities::FILESYSTEM),
+    ("fs_seek", Capabilities::FILESYSTEM),
+    ("fs_truncate", Capabilities::FILESYSTEM),
+    ("fs_flush", Capabilities::FILESYSTEM),
+    ("fs_hash", Capabilities::FILESYSTEM),
+    ("fs_close", Capabilities::FILESYSTEM),
+    ("poll_input", Capabilities::INPUT),
+    ("battery_millivolts", Capabilities::POWER),
+    ("request_sleep", Capabilities::POWER),
+    ("vibrate", Capabilities::HAPTIC),
+    ("set_unix_time", Capabilities::SET_TIME),
+    ("log_set_target_level", Capabilities::DIAGNOSTICS),
+    ("log_clear_target_level", Capabilities::DIAGNOSTICS),
+    ("read_log_history", Capabilities::DIAGNOSTICS),
+    ("alloc_stats", Capabilities::DIAGNOSTICS),
+];
+
+/// Describes an app to the firmware. It's stored as postcard next to the app's `.wasm` file, with
+/// the extension replaced by `.manifest`.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The name shown to the user, which can differ from the file name.
+    pub name: String,
+    pub version: [u16; 3],
+    /// The most linear memory the app can use, in bytes. Limits above [`WASM_MEMORY_BUDGET`] are
+    /// lowered to it.
+    pub memory_limit: u32,
+    /// Must be nonzero, since an app without any fuel can't run at all.
+    pub fuel_per_tick: u64,
+    capabilities: u32,
+}
+
+impl Manifest {
+    pub fn new(name: impl Into<String>, version: [u16; 3], capabilities: Capabilities) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            memory_limit: WASM_MEMORY_LIMIT as u32,
+            fuel_per_tick: WASM_FUEL_PER_TICK,
+            capabilities: capabilities.bits(),
+        }
+    }
+
+    /// The manifest used for apps which don't come with one. They get the default limits and no
+    /// capabilities, so only the syscalls every app can use.
+    pub fn fallback(name: impl Into<String>) -> Self {
+        Self::new(name, [0, 0, 0], Capabilities::empty())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let manifest: Self = postcard::from_bytes(bytes).map_err(|_| Error::InvalidManifest)?;
+
+        if manifest.fuel_per_tick == 0 {
+            return Err(Error::InvalidManifest);
+        }
+
+        Ok(manifest)
+    }
+
+    /// Unknown bits are ignored, so manifests written for newer firmware still load.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::from_bits_truncate(self.capabilities)
+    }
+
+    pub fn limits(&self) -> Limits {
+        let memory_size = (self.memory_limit as usize).min(WASM_MEMORY_BUDGET);
+
+        Limits::new(memory_size, self.fuel_per_tick)
+    }
+
+    /// Returns [`Error::MissingCapability`] if the app isn't allowed to use the syscall `name`.
+    pub fn check_syscall(&self, name: &str) -> Result<(), Error> {
+        let required = SYSCALL_CAPABILITIES
+            .iter()
+            .find(|&&(syscall, _)| syscall == name);
+
+        match required {
+            Some(&(syscall, capability)) if !self.capabilities().contains(capability) => {
+                Err(Error::MissingCapability(syscall))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(manifest: &Manifest) -> Result<Manifest, Error> {
+        Manifest::from_bytes(&postcard::to_allocvec(manifest).unwrap())
+    }
+
+    #[test]
+    fn fallback_has_no_capabilities() {
+        let manifest = Manifest::fallback("app");
+
+        assert_eq!(manifest.capabilities(), Capabilities::empty());
+        assert_eq!(
+            manifest.check_syscall("fs_open"),
+            Err(Error::MissingCapability("fs_open"))
+        );
+        assert_eq!(manifest.check_syscall("draw_rectangle"), Ok(()));
+    }
+
+    #[test]
+    fn zero_fuel_is_rejected() {
+        let mut manifest = Manifest::new("app", [1, 0, 0], Capabilities::FILESYSTEM);
+        assert_eq!(round_trip(&manifest), Ok(manifest.clone()));
+
+        manifest.fuel_per_tick = 0;
+        assert_eq!(round_trip(&manifest), Err(Error::InvalidManifest));
+    }
+
+    #[test]
+    fn memory_limit_is_clamped_to_the_budget() {
+        let mut manifest = Manifest::new("app", [1, 0, 0], Capabilities::empty());
+        assert_eq!(manifest.limits().memory_size, WASM_MEMORY_LIMIT);
+
+        manifest.memory_limit = u32::MAX;
+        assert_eq!(manifest.limits().memory_size, WASM_MEMORY_BUDGET);
+    }
+}
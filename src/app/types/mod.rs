@@ -1,7 +1,13 @@
 pub mod error;
+pub mod file;
 pub mod io;
+pub mod manifest;
+pub mod namespace;
 pub mod wasm;
 
 pub use error::*;
+pub use file::*;
 pub use io::*;
+pub use manifest::*;
+pub use namespace::*;
 pub use wasm::*;
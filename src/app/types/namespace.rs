@@ -0,0 +1,50 @@
+use alloc::format;
+use alloc::string::String;
+
+const APP_NAMESPACE_PREFIX: &str = "app.";
+const SHARED_NAMESPACE: &str = "shared";
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum Namespace {
+    /// Storage private to the running app.
+    #[default]
+    App,
+
+    /// Storage shared between every app.
+    Shared,
+}
+
+impl Namespace {
+    /// Returns the storage key for `name` when opened by the app called `app_name`.
+    pub fn key(self, app_name: &str, name: &str) -> String {
+        match self {
+            Namespace::App => format!("{APP_NAMESPACE_PREFIX}{app_name}/{name}"),
+            Namespace::Shared => format!("{SHARED_NAMESPACE}/{name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apps_get_distinct_keys_for_the_same_name() {
+        let first = Namespace::App.key("clock", "settings");
+        let second = Namespace::App.key("notes", "settings");
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn shared_keys_are_the_same_for_every_app() {
+        assert_eq!(
+            Namespace::Shared.key("clock", "settings"),
+            Namespace::Shared.key("notes", "settings"),
+        );
+        assert_ne!(
+            Namespace::Shared.key("clock", "settings"),
+            Namespace::App.key("clock", "settings"),
+        );
+    }
+}
@@ -1,109 +1,166 @@
+use crate::app::syscall::{SyscallEntry, SYSCALLS, SYSCALL_NAMESPACE};
+use crate::driver::lcd;
+use crate::power;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use core::ops::RangeBounds;
-use core::sync::atomic::{self, Ordering};
+use core::ops::{Range, RangeBounds};
+use core::pin::pin;
+use core::sync::atomic::{self, AtomicUsize, Ordering};
 use critical_section as cs;
 use embassy_executor::{SendSpawner, SpawnToken};
+use embassy_futures::select::{select, Either};
+use embassy_futures::yield_now;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex;
 use embassy_sync::mutex::{Mutex, MutexGuard};
-use embassy_time::Timer;
+use embassy_sync::signal::Signal;
+use embassy_time::{Instant, Timer};
 use esp_hal::rng::Trng;
 use esp_hal::Cpu;
-use wasmi::core::ValType;
+use wasmi::core::{TrapCode, ValType};
 use wasmi::{
-    Config, Engine, Extern, FuncRef, Instance, Linker, Memory, Module, Store, StoreContext,
-    StoreContextMut, StoreLimits, StoreLimitsBuilder, Table, TypedResumableCall,
+    Config, Engine, Extern, FuncRef, Linker, Memory, Module, Store, StoreContext, StoreContextMut,
+    StoreLimits, StoreLimitsBuilder, Table, TypedFunc, TypedResumableCall,
+    TypedResumableCallOutOfFuel, WasmResults,
 };
 
 use super::error::{Error, Result};
-use super::{PollRequest, Registration, RegistrationQueue, WakerFunc};
-
-macro_rules! link_syscalls {
-    (
-        $(($f:path, $name:literal)),* $(,)? ; $linker:expr
-    ) => {
-        $(
-            {
-                let link_result = $linker.func_wrap(SYSCALL_NAMESPACE, $name, $f);
-
-                if let Err(e) = link_result {
-                    return Err(wasmi::Error::from(e))
-                }
-            }
-        )*
-    }
-}
+use super::{
+    FileTable, Manifest, Namespace, OpenFile, PollRequest, Registration, RegistrationQueue,
+    WakerFunc,
+};
 
-const SYSCALL_NAMESPACE: &str = "__xenon_syscall";
 const ENTRY_POINT: &str = "__xenon_start";
 const MEMORY_NAME: &str = "memory";
 const FUNCTION_TABLE_NAME: &str = "__indirect_function_table";
-const WASM_MEMORY_LIMIT: usize = 1 << 20; // 1 MiB
+pub const WASM_MEMORY_LIMIT: usize = 1 << 20; // 1 MiB
+/// The most linear memory a manifest can ask for, which leaves the rest of the 8 MiB PSRAM heap to
+/// the firmware.
+pub const WASM_MEMORY_BUDGET: usize = 4 << 20; // 4 MiB
+pub const WASM_FUEL_PER_TICK: u64 = 1 << 24;
+
+// Running out of fuel during a resumable call only preempts the app, but the start function isn't
+// called resumably, so an app whose start function uses up its first tick's fuel is stopped.
+fn map_fuel_error(error: wasmi::Error) -> wasmi::Error {
+    match error.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => Error::OutOfFuel.into(),
+        _ => error,
+    }
+}
+
+// Syscalls only fail with `Error` or `PollRequest`, so a trap's host error is always one of them.
+fn host_error(error: &wasmi::Error) -> wasmi::Error {
+    match error.downcast_ref::<PollRequest>() {
+        Some(&request) => request.into(),
+        None => error.downcast_ref::<Error>().unwrap().clone().into(),
+    }
+}
 
 fn link_syscalls(linker: &mut Linker<Env>) -> Result<()> {
-    use crate::app::syscall::*;
-
-    link_syscalls![
-        (stdio::print, "print"),
-        (stdio::print, "eprint"),
-        (stdio::log, "log"),
-        (time::get_time, "get_time"),
-        (widget::draw_arc, "draw_arc"),
-        (widget::draw_circle, "draw_circle"),
-        (widget::draw_ellipse, "draw_ellipse"),
-        (widget::draw_line, "draw_line"),
-        (widget::draw_rectangle, "draw_rectangle"),
-        (widget::draw_rounded_rectangle, "draw_rounded_rectangle"),
-        (widget::draw_sector, "draw_sector"),
-        (widget::draw_triangle, "draw_triangle"),
-        (widget::load_compressed_bitmap, "load_compressed_bitmap"),
-        (widget::load_bitmap, "load_bitmap"),
-        (widget::decompress_bitmap, "decompress_bitmap"),
-        (widget::draw_compressed_bitmap, "draw_compressed_bitmap"),
-        (widget::draw_bitmap, "draw_bitmap"),
-        (widget::get_bitmap_pixel, "get_bitmap_pixel"),
-        (widget::set_bitmap_pixel, "set_bitmap_pixel"),
-        (misc::clear_buffer, "clear_buffer"),
-        (misc::clone_binary_data, "clone_binary_data"),
-        (misc::drop_binary_data, "drop_binary_data"),
-        (asynch::wait, "wait"),
-        (asynch::poll, "poll"),
-        (io::schedule_timer, "schedule_timer"),
-        (io::schedule_io, "schedule_io"),
-        (panic::panic, "panic");
-        linker
-    ];
+    for syscall in SYSCALLS {
+        syscall.link(linker)?;
+    }
 
     Ok(())
 }
 
 pub struct Executor {
-    instance: Instance,
+    entry: TypedFunc<(), ()>,
     store: Store<Env>,
+    fuel_per_tick: u64,
 }
 
 impl Executor {
-    pub fn new(rng: Trng<'static>, spawner: SendSpawner, module: &[u8]) -> Result<Self> {
+    /// Creates an executor with the default [`Limits`].
+    pub fn new(
+        name: &str,
+        rng: Trng<'static>,
+        spawner: SendSpawner,
+        module: &[u8],
+    ) -> Result<Self> {
+        Self::with_limits(name, rng, spawner, module, Limits::default())
+    }
+
+    pub fn with_limits(
+        name: &str,
+        rng: Trng<'static>,
+        spawner: SendSpawner,
+        module: &[u8],
+        limits: Limits,
+    ) -> Result<Self> {
+        Self::create(name, rng, spawner, module, limits, None)
+    }
+
+    /// Creates an executor with the limits from `manifest`, returning
+    /// [`Error::MissingCapability`] if the module imports a syscall the manifest doesn't allow.
+    pub fn with_manifest(
+        name: &str,
+        rng: Trng<'static>,
+        spawner: SendSpawner,
+        module: &[u8],
+        manifest: &Manifest,
+    ) -> Result<Self> {
+        Self::create(
+            name,
+            rng,
+            spawner,
+            module,
+            manifest.limits(),
+            Some(manifest),
+        )
+    }
+
+    fn create(
+        name: &str,
+        rng: Trng<'static>,
+        spawner: SendSpawner,
+        module: &[u8],
+        limits: Limits,
+        manifest: Option<&Manifest>,
+    ) -> Result<Self> {
         let mut config = Config::default();
         config.wasm_multi_value(false);
+        config.consume_fuel(true);
 
         let engine = Engine::new(&config);
         let module = Module::new(&engine, module)?;
 
-        let limits = Limits {
-            store: StoreLimitsBuilder::new()
-                .memories(1)
-                .memory_size(WASM_MEMORY_LIMIT)
-                .build(),
-        };
+        // checking imports up front rejects the app before any of its code runs, and gives a
+        // clearer error than the linker does when a syscall doesn't exist or has changed.
+        for import in module.imports() {
+            if import.module() != SYSCALL_NAMESPACE {
+                continue;
+            }
 
-        let mut store = Store::new(&engine, Env::new(rng, spawner, limits));
+            let syscall = SyscallEntry::find(import.name())
+                .ok_or_else(|| Error::UnknownSyscall(import.name().into()))?;
+
+            if !import.ty().func().is_some_and(|ty| syscall.matches(ty)) {
+                return Err(Error::SyscallSignature {
+                    name: syscall.name(),
+                    expected: syscall.to_string(),
+                }
+                .into());
+            }
+
+            if let Some(manifest) = manifest {
+                manifest.check_syscall(import.name())?;
+            }
+        }
+
+        let fuel_per_tick = limits.fuel_per_tick;
+
+        let mut store = Store::new(&engine, Env::new(name, rng, spawner, limits));
         store.limiter(|env| &mut env.limits.store);
+        store.set_fuel(fuel_per_tick)?;
 
         let mut linker = Linker::new(&engine);
         link_syscalls(&mut linker)?;
 
-        let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+        let instance = linker
+            .instantiate(&mut store, &module)?
+            .start(&mut store)
+            .map_err(map_fuel_error)?;
 
         let memory = instance
             .get_memory(&store, MEMORY_NAME)
@@ -114,78 +171,262 @@ impl Executor {
             .and_then(Extern::into_table)
             .ok_or(Error::NoFunctionTable)?;
 
+        // checked here rather than in `run`, so an app without the SDK's startup code fails to
+        // load with a clear error instead of wasmi's.
+        let entry = instance
+            .get_typed_func::<(), ()>(&store, ENTRY_POINT)
+            .map_err(|_| Error::NoEntryPoint(ENTRY_POINT))?;
+
         {
             let mut env_data = store.data().lock_data_blocking();
             env_data.set_memory(memory);
             env_data.set_funcs(&store, function_table)?;
         }
 
-        Ok(Self { instance, store })
+        Ok(Self {
+            entry,
+            store,
+            fuel_per_tick,
+        })
     }
 
     pub async fn run(&mut self) -> Result<()> {
         let env = self.store.data().clone();
 
-        let entry = self
-            .instance
-            .get_typed_func::<(), ()>(&self.store, ENTRY_POINT)?;
+        self.store.set_fuel(self.fuel_per_tick)?;
+
+        // the chip can only go to sleep while no app code is running.
+        let awake = power::stay_awake().await;
+        let mut entry_handle = self.entry.call_resumable(&mut self.store, ())?;
+        drop(awake);
 
-        let mut entry_handle = entry.call_resumable(&mut self.store, ())?;
+        loop {
+            let resumable = match entry_handle {
+                TypedResumableCall::Finished(()) => return Ok(()),
+                TypedResumableCall::OutOfFuel(out_of_fuel) => {
+                    entry_handle = self.refuel(out_of_fuel).await?;
+                    continue;
+                }
+                TypedResumableCall::HostTrap(resumable) => resumable,
+            };
 
-        while let TypedResumableCall::Resumable(resumable) = entry_handle {
             let Some(&request) = resumable.host_error().downcast_ref::<PollRequest>() else {
-                // Since wasmi guarantees that resumable.host_error() will never be a Wasm trap, and
-                // the only other error type returned by host calls is `Error`, the downcast should
-                // unconditionally return Some(_).
-                let &host_error = resumable.host_error().downcast_ref::<Error>().unwrap();
-                return Err(host_error.into());
+                return Err(host_error(resumable.host_error()));
             };
 
             match request {
                 PollRequest::Wait => {
                     log::trace!(target: "Wasm executor", "waiting for a task to wake up");
-                    self.poll_wakers(&env).await?;
+
+                    let (deadline, present, framebuffer_read) = {
+                        let mut env_data = env.lock_data().await;
+                        (
+                            env_data.take_sleep_deadline(),
+                            env_data.take_present(),
+                            env_data.take_framebuffer_read(),
+                        )
+                    };
+
+                    match (framebuffer_read, deadline) {
+                        (Some(ptr), _) => self.read_framebuffer(&env, ptr).await?,
+                        _ if present => self.wait_for_frame(&env).await?,
+                        (None, Some(deadline)) => self.sleep_until(&env, deadline).await?,
+                        (None, None) => self.poll_wakers(&env).await?,
+                    }
                 }
                 PollRequest::Poll => {
                     self.poll_wakers(&env).await?;
                 }
             }
 
+            // Every suspension is a tick boundary, so other tasks on this core get a chance to
+            // run and the app gets a fresh fuel budget.
+            yield_now().await;
+            self.store.set_fuel(self.fuel_per_tick)?;
+
+            let awake = power::stay_awake().await;
             entry_handle = resumable.resume(&mut self.store, &[])?;
+            drop(awake);
         }
+    }
+
+    // Preempts an app that used up its fuel for this tick, letting other tasks on this core run
+    // before the call is resumed with a fresh budget.
+    async fn refuel<R: WasmResults>(
+        &mut self,
+        out_of_fuel: TypedResumableCallOutOfFuel<R>,
+    ) -> Result<TypedResumableCall<R>> {
+        // a single instruction that costs more than a whole tick's budget could never finish.
+        if out_of_fuel.required_fuel() > self.fuel_per_tick {
+            return Err(Error::OutOfFuel.into());
+        }
+
+        log::trace!(target: "Wasm executor", "app ran out of fuel, preempting it");
+
+        yield_now().await;
+        self.store.set_fuel(self.fuel_per_tick)?;
+
+        let awake = power::stay_awake().await;
+        let call = out_of_fuel.resume(&mut self.store)?;
+        drop(awake);
+
+        Ok(call)
+    }
+
+    // Keeps waking tasks as their registrations come in until `deadline` passes, so the tasks of a
+    // sleeping app can still make progress.
+    async fn sleep_until(&mut self, env: &Env, deadline: Instant) -> Result<()> {
+        log::trace!(target: "Wasm executor", "sleeping until {deadline:?}");
+
+        loop {
+            self.poll_wakers(env).await?;
+
+            if let Either::First(_) = select(Timer::at(deadline), env.registrations.wait()).await {
+                break;
+            }
+        }
+
+        self.poll_wakers(env).await
+    }
+
+    // Like `sleep_until`, but waits for the display to show a frame copied after the app called
+    // `present`. Drawing syscalls only spawn tasks, so those are finished first, and with double
+    // buffering on the app's frame is then swapped to the front.
+    async fn wait_for_frame(&mut self, env: &Env) -> Result<()> {
+        log::trace!(target: "Wasm executor", "waiting for the next frame");
+
+        env.wait_for_draws().await;
+
+        if lcd::is_double_buffered() {
+            lcd::swap_buffers().await;
+        }
+
+        let mut refreshed = pin!(lcd::wait_for_refresh());
+
+        loop {
+            self.poll_wakers(env).await?;
+
+            if let Either::First(_) = select(refreshed.as_mut(), env.registrations.wait()).await {
+                break;
+            }
+        }
+
+        self.poll_wakers(env).await
+    }
+
+    // Copies the display buffer to `ptr` in the app's memory for `read_framebuffer`, once the
+    // drawing tasks the app spawned before calling it have finished.
+    async fn read_framebuffer(&mut self, env: &Env, ptr: usize) -> Result<()> {
+        env.wait_for_draws().await;
+
+        let bytes = lcd::read_buffer().await;
+        let memory = env.lock_data().await.memory();
+
+        memory
+            .write(&mut self.store, ptr, &bytes)
+            .map_err(|_| Error::InvalidMemoryRange {
+                start: ptr,
+                end: ptr + bytes.len(),
+            })?;
 
         Ok(())
     }
 
+    // Wakes every registration that's ready when this is called as a single batch, so the app is
+    // only resumed once for all of them. Registrations pushed while the batch is being woken are
+    // left for the next call.
     async fn poll_wakers(&mut self, env: &Env) -> Result<()> {
         log::trace!(target: "Wasm executor", "polling wakers");
-        while let Some(registration) = env.registrations.try_pop().await {
-            registration.wake(&mut self.store)?;
+        let batch = env.registrations.drain().await;
+
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        // wakers run app code too, and a long sleep can wake many batches before the next tick
+        // refuels the store, so each batch gets a budget of its own.
+        self.store.set_fuel(self.fuel_per_tick)?;
+
+        for registration in batch {
+            // `refuel` takes the guard itself, so it's only held while the waker is first called.
+            let awake = power::stay_awake().await;
+            let mut call = registration.wake(&mut self.store)?;
+            drop(awake);
+
+            loop {
+                call = match call {
+                    TypedResumableCall::Finished(()) => break,
+                    TypedResumableCall::OutOfFuel(out_of_fuel) => self.refuel(out_of_fuel).await?,
+                    // wakers can't suspend, so a syscall that tries to is an error like any other.
+                    TypedResumableCall::HostTrap(trap) => {
+                        return Err(host_error(trap.host_error()))
+                    }
+                };
+            }
+
             log::trace!(target: "Wasm executor", "woke up task at wasm address {:#x}", registration.data)
         }
 
+        env.lock_data().await.set_notified(true);
+
         Ok(())
     }
 }
 
+// Counts the drawing tasks an app has spawned which haven't finished yet.
+#[derive(Default)]
+struct PendingDraws {
+    count: AtomicUsize,
+    finished: Signal<CsRawMutex, ()>,
+}
+
+/// Held by a drawing task until it finishes, so `present` can wait for everything the app drew
+/// before it. Created with [`Env::begin_draw`].
+pub struct DrawGuard {
+    pending: Arc<PendingDraws>,
+}
+
+impl Drop for DrawGuard {
+    fn drop(&mut self) {
+        if self.pending.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.pending.finished.signal(());
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Env {
+    app_name: Arc<str>,
     data: Arc<Mutex<CsRawMutex, EnvData>>,
+    draws: Arc<PendingDraws>,
     registrations: RegistrationQueue,
     spawner: SendSpawner,
     limits: Limits,
 }
 
 impl Env {
-    pub fn new(rng: Trng<'static>, spawner: SendSpawner, limits: Limits) -> Self {
+    pub fn new(name: &str, rng: Trng<'static>, spawner: SendSpawner, limits: Limits) -> Self {
         Self {
+            app_name: Arc::from(name),
             data: Arc::new(Mutex::new(EnvData::new(rng))),
+            draws: Arc::new(PendingDraws::default()),
             registrations: RegistrationQueue::new(),
             spawner,
             limits,
         }
     }
 
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    /// Returns the storage key for `name` in the given namespace. Names in [`Namespace::App`] are
+    /// private to the running app, so different apps can use the same name without colliding,
+    /// while names in [`Namespace::Shared`] are the same for every app.
+    pub fn namespaced(&self, namespace: Namespace, name: &str) -> String {
+        namespace.key(&self.app_name, name)
+    }
+
     pub fn lock_data_blocking(&self) -> MutexGuard<'_, CsRawMutex, EnvData> {
         loop {
             match self.data.try_lock() {
@@ -203,6 +444,23 @@ impl Env {
         self.registrations.push(registration).await;
     }
 
+    /// Marks a drawing task as started. The task has to hold the returned guard until it's done
+    /// drawing. If spawning the task fails, the guard is dropped with it.
+    pub fn begin_draw(&self) -> DrawGuard {
+        self.draws.count.fetch_add(1, Ordering::AcqRel);
+
+        DrawGuard {
+            pending: self.draws.clone(),
+        }
+    }
+
+    /// Waits until every drawing task the app has spawned so far has finished.
+    pub async fn wait_for_draws(&self) {
+        while self.draws.count.load(Ordering::Acquire) != 0 {
+            self.draws.finished.wait().await;
+        }
+    }
+
     pub fn spawn<S: Send>(&self, token: SpawnToken<S>) -> Result<()> {
         self.spawner
             .spawn(token)
@@ -213,9 +471,13 @@ impl Env {
 pub struct EnvData {
     rng: Trng<'static>,
     binary_data: BinaryData,
+    files: FileTable,
     funcs: Option<Table>,
     memory: Option<Memory>,
     notified: bool,
+    sleep_deadline: Option<Instant>,
+    present: bool,
+    framebuffer_read: Option<usize>,
 }
 
 impl EnvData {
@@ -223,9 +485,13 @@ impl EnvData {
         Self {
             rng,
             binary_data: BinaryData::new(),
+            files: FileTable::new(),
             funcs: None,
             memory: None,
             notified: false,
+            sleep_deadline: None,
+            present: false,
+            framebuffer_read: None,
         }
     }
 
@@ -255,7 +521,7 @@ impl EnvData {
         // .unwrap() should never panic unless self.funcs is set through other means.
         self.funcs
             .expect("env function table was not set")
-            .get(ctx.into(), index)
+            .get(ctx.into(), u64::from(index))
             .map(|val| *val.funcref().unwrap())
             .unwrap_or_else(FuncRef::null)
     }
@@ -284,6 +550,60 @@ impl EnvData {
         self.memory().data_mut(ctx).get_mut((start, end))
     }
 
+    /// Returns the `len` bytes of wasm memory starting at `ptr`, or `Error::InvalidMemoryRange` if
+    /// any part of that range is out of bounds.
+    pub fn memory_slice<'a>(
+        &self,
+        ctx: impl Into<StoreContext<'a, Env>>,
+        ptr: usize,
+        len: usize,
+    ) -> Result<&'a [u8], Error> {
+        let range = memory_slice_range(ptr, len)?;
+
+        self.memory()
+            .data(ctx.into())
+            .get(range.clone())
+            .ok_or(Error::InvalidMemoryRange {
+                start: range.start,
+                end: range.end,
+            })
+    }
+
+    /// Returns the `len` bytes of wasm memory starting at `ptr` as a string, or
+    /// `Error::InvalidUtf8` if they aren't valid UTF-8.
+    pub fn memory_str<'a>(
+        &self,
+        ctx: impl Into<StoreContext<'a, Env>>,
+        ptr: usize,
+        len: usize,
+    ) -> Result<&'a str, Error> {
+        let bytes = self.memory_slice(ctx, ptr, len)?;
+
+        core::str::from_utf8(bytes).map_err(|e| Error::InvalidUtf8 {
+            start: ptr,
+            len,
+            valid_up_to: e.valid_up_to(),
+        })
+    }
+
+    /// Mutable version of [`EnvData::memory_slice`].
+    pub fn memory_slice_mut<'a>(
+        &self,
+        ctx: impl Into<StoreContextMut<'a, Env>>,
+        ptr: usize,
+        len: usize,
+    ) -> Result<&'a mut [u8], Error> {
+        let range = memory_slice_range(ptr, len)?;
+
+        self.memory()
+            .data_mut(ctx.into())
+            .get_mut(range.clone())
+            .ok_or(Error::InvalidMemoryRange {
+                start: range.start,
+                end: range.end,
+            })
+    }
+
     pub fn push_binary_data(&mut self, data: impl AsRef<[u8]>) -> usize {
         self.binary_data.push(data)
     }
@@ -300,6 +620,18 @@ impl EnvData {
         self.binary_data.get_mut(index)
     }
 
+    pub fn open_file(&mut self, file: OpenFile) -> usize {
+        self.files.insert(file)
+    }
+
+    pub fn get_file(&self, index: usize) -> Option<Arc<OpenFile>> {
+        self.files.get(index)
+    }
+
+    pub fn close_file(&mut self, index: usize) -> Option<Arc<OpenFile>> {
+        self.files.remove(index)
+    }
+
     pub fn random_32(&mut self) -> u32 {
         self.rng.random()
     }
@@ -318,11 +650,49 @@ impl EnvData {
         self.notified = notified;
     }
 
+    pub fn set_sleep_deadline(&mut self, deadline: Instant) {
+        self.sleep_deadline = Some(deadline);
+    }
+
+    pub fn take_sleep_deadline(&mut self) -> Option<Instant> {
+        self.sleep_deadline.take()
+    }
+
+    /// Makes the executor wait for the next frame to be displayed before resuming the app.
+    pub fn set_present(&mut self) {
+        self.present = true;
+    }
+
+    pub fn take_present(&mut self) -> bool {
+        core::mem::take(&mut self.present)
+    }
+
+    /// Makes the executor copy the display buffer to `ptr` in wasm memory before resuming the app.
+    pub fn set_framebuffer_read(&mut self, ptr: usize) {
+        self.framebuffer_read = Some(ptr);
+    }
+
+    pub fn take_framebuffer_read(&mut self) -> Option<usize> {
+        self.framebuffer_read.take()
+    }
+
     pub fn random_bytes(&mut self, bytes: &mut [u8]) {
         self.rng.read(bytes)
     }
 }
 
+/// Returns the range of wasm memory covering `len` bytes from `ptr`, or
+/// `Error::InvalidMemoryRange` if the end would overflow.
+pub fn memory_slice_range(ptr: usize, len: usize) -> Result<Range<usize>, Error> {
+    match ptr.checked_add(len) {
+        Some(end) => Ok(ptr..end),
+        None => Err(Error::InvalidMemoryRange {
+            start: ptr,
+            end: usize::MAX,
+        }),
+    }
+}
+
 // TODO: implement some sort of "generation" system (as is commonly used in ECSs) to have an extra
 // check against accidentally freeing data twice if something goes wrong in wasm-land (e.g. a
 // double-free bug in the wasm binary).
@@ -397,7 +767,29 @@ impl BinaryData {
 }
 
 #[non_exhaustive]
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Limits {
     pub store: StoreLimits,
+    /// The amount of fuel an app can consume between yields to the executor before it's preempted.
+    pub fuel_per_tick: u64,
+}
+
+impl Limits {
+    /// Limits an app to `memory_size` bytes of linear memory and `fuel_per_tick` fuel between
+    /// yields.
+    pub fn new(memory_size: usize, fuel_per_tick: u64) -> Self {
+        Self {
+            store: StoreLimitsBuilder::new()
+                .memories(1)
+                .memory_size(memory_size)
+                .build(),
+            fuel_per_tick,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new(WASM_MEMORY_LIMIT, WASM_FUEL_PER_TICK)
+    }
 }
@@ -0,0 +1,58 @@
+// The TRNG takes ownership of ADC1 (see `Trng::new` in `main`), since it samples ADC noise as an
+// entropy source. The battery is read through ADC2 instead, on GPIO11 (ADC2 channel 0), so the two
+// never contend for the same peripheral. ADC2 is also used by the radio for Wi-Fi, but Xenon only
+// uses BLE, which doesn't need it.
+
+use crate::log_init;
+use core::cell::RefCell;
+use critical_section::{self as cs, Mutex};
+use esp_hal::analog::adc::{Adc, AdcCalCurve, AdcConfig, AdcPin, Attenuation};
+use esp_hal::gpio::GpioPin;
+use esp_hal::peripherals::ADC2;
+
+/// The ratio of the battery voltage to the voltage at the sense pin, as (numerator, denominator).
+/// The battery's voltage is above what the ADC can measure, so it's read through a resistor
+/// divider. The default is for two equal resistors.
+pub const DEFAULT_DIVIDER: (u32, u32) = (2, 1);
+
+static BATTERY: Mutex<RefCell<Option<Battery>>> = Mutex::new(RefCell::new(None));
+
+type SensePin = AdcPin<GpioPin<11>, ADC2, AdcCalCurve<ADC2>>;
+
+struct Battery {
+    adc: Adc<'static, ADC2>,
+    pin: SensePin,
+    divider: (u32, u32),
+}
+
+pub fn init(adc: ADC2, pin: GpioPin<11>, divider: (u32, u32)) {
+    let mut config = AdcConfig::new();
+    // with calibration, readings from the pin are in millivolts instead of raw values.
+    let pin = config.enable_pin_with_cal::<_, AdcCalCurve<ADC2>>(pin, Attenuation::Attenuation11dB);
+    let adc = Adc::new(adc, config);
+
+    cs::with(|cs| BATTERY.replace(cs, Some(Battery { adc, pin, divider })));
+
+    log_init("battery");
+}
+
+/// Reads the battery voltage in millivolts, or `None` if [`init`] hasn't been called.
+pub fn read_millivolts() -> Option<u16> {
+    cs::with(|cs| {
+        let mut battery = BATTERY.borrow_ref_mut(cs);
+        let Battery { adc, pin, divider } = battery.as_mut()?;
+
+        let sense_mv = loop {
+            // the conversion only takes a few microseconds, so it's fine to spin here.
+            match adc.read_oneshot(pin) {
+                Ok(mv) => break mv as u32,
+                Err(_) => core::hint::spin_loop(),
+            }
+        };
+
+        let (numerator, denominator) = *divider;
+        let mv = sense_mv * numerator / denominator;
+
+        Some(mv.min(u16::MAX as u32) as u16)
+    })
+}
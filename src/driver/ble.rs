@@ -0,0 +1,225 @@
+use crate::driver::{battery, rtc};
+use crate::log_init;
+use bt_hci::controller::ExternalController;
+use embassy_executor::task;
+use embassy_futures::join::join3;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_time::{Duration, Timer};
+use esp_hal::peripherals::BT;
+use esp_wifi::ble::controller::asynch::BleConnector;
+use esp_wifi::EspWifiInitialization;
+use trouble_host::advertise::{
+    AdStructure, Advertisement, BR_EDR_NOT_SUPPORTED, LE_GENERAL_DISCOVERABLE,
+};
+use trouble_host::attribute::{AttributeTable, CharacteristicProp, Service, Uuid};
+use trouble_host::connection::Connection;
+use trouble_host::gatt::{GattEvent, GattServer};
+use trouble_host::{Address, BleHostError, Controller, PacketQos, Peripheral, Resources};
+
+const DEVICE_NAME: &[u8] = b"Xenon";
+const BLE_ADDRESS: [u8; 6] = [0xff, 0x58, 0x65, 0x6e, 0x6f, 0x6e];
+const MAX_ATTRIBUTES: usize = 16;
+const L2CAP_MTU: usize = 27;
+const CONNECTIONS_MAX: usize = 1;
+const L2CAP_CHANNELS_MAX: usize = 2;
+const UPDATE_INTERVAL: Duration = Duration::from_secs(30);
+
+// Battery voltages treated as empty and full when reporting the battery level. These are typical
+// for a single lithium polymer cell.
+const BATTERY_EMPTY_MV: u32 = 3300;
+const BATTERY_FULL_MV: u32 = 4200;
+
+const GAP_SERVICE: u16 = 0x1800;
+const GATT_SERVICE: u16 = 0x1801;
+const CURRENT_TIME_SERVICE: u16 = 0x1805;
+const BATTERY_SERVICE: u16 = 0x180f;
+const DEVICE_NAME_CHARACTERISTIC: u16 = 0x2a00;
+const APPEARANCE_CHARACTERISTIC: u16 = 0x2a01;
+const BATTERY_LEVEL_CHARACTERISTIC: u16 = 0x2a19;
+const CURRENT_TIME_CHARACTERISTIC: u16 = 0x2a2b;
+// "Watch: Smartwatch" from the Bluetooth assigned numbers.
+const APPEARANCE: [u8; 2] = [0xc2, 0x00];
+
+type BleController = ExternalController<BleConnector<'static>, 20>;
+
+/// Runs the BLE host with a GATT server exposing the battery level and the current time.
+#[task]
+pub async fn start(init: &'static EspWifiInitialization, bt: BT) -> ! {
+    let connector = BleConnector::new(init, bt);
+    let controller: BleController = ExternalController::new(connector);
+
+    let mut resources: Resources<BleController, CONNECTIONS_MAX, L2CAP_CHANNELS_MAX, L2CAP_MTU> =
+        Resources::new(PacketQos::None);
+
+    let (stack, mut peripheral, _, runner) = trouble_host::new(controller, &mut resources)
+        .set_random_address(Address::random(BLE_ADDRESS))
+        .build();
+
+    let mut battery_level = [battery_percent()];
+    let mut current_time = current_time();
+
+    let mut table: AttributeTable<'_, NoopRawMutex, MAX_ATTRIBUTES> = AttributeTable::new();
+
+    let mut gap = table.add_service(Service::new(GAP_SERVICE));
+    let _ = gap.add_characteristic_ro(DEVICE_NAME_CHARACTERISTIC, DEVICE_NAME);
+    let _ = gap.add_characteristic_ro(APPEARANCE_CHARACTERISTIC, &APPEARANCE[..]);
+    gap.build();
+
+    table.add_service(Service::new(GATT_SERVICE));
+
+    let battery_handle = table
+        .add_service(Service::new(BATTERY_SERVICE))
+        .add_characteristic(
+            BATTERY_LEVEL_CHARACTERISTIC,
+            &[CharacteristicProp::Read, CharacteristicProp::Notify],
+            &mut battery_level,
+        )
+        .build();
+
+    let time_handle = table
+        .add_service(Service::new(CURRENT_TIME_SERVICE))
+        .add_characteristic(
+            CURRENT_TIME_CHARACTERISTIC,
+            &[CharacteristicProp::Read, CharacteristicProp::Notify],
+            &mut current_time,
+        )
+        .build();
+
+    let server =
+        GattServer::<BleController, NoopRawMutex, MAX_ATTRIBUTES, L2CAP_MTU>::new(stack, &table);
+
+    log_init("GATT server");
+
+    let host = async {
+        if let Err(e) = runner.run().await {
+            log::error!("BLE host stopped: {e:?}");
+        }
+    };
+
+    let gatt = async {
+        loop {
+            match server.next().await {
+                Ok(GattEvent::Write { handle, .. }) => {
+                    log::debug!("BLE characteristic {handle:?} written");
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("GATT error: {e:?}"),
+            }
+        }
+    };
+
+    let advertise = async {
+        loop {
+            let connection = match advertise(&mut peripheral).await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    log::warn!("BLE advertising failed: {e:?}");
+                    Timer::after(UPDATE_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            log::info!("BLE connection from {:?}", connection.peer_address());
+
+            // Keep the values fresh for whoever is connected, then go back to advertising once
+            // they disconnect.
+            while connection.is_connected() {
+                Timer::after(UPDATE_INTERVAL).await;
+
+                let level = [battery_percent()];
+                let time = current_time();
+
+                let _ = server.notify(battery_handle, &level).await;
+                let _ = server.notify(time_handle, &time).await;
+            }
+        }
+    };
+
+    join3(host, gatt, advertise).await;
+
+    unreachable!("BLE tasks never finish")
+}
+
+async fn advertise<'p, C: Controller>(
+    peripheral: &mut Peripheral<'p, C>,
+) -> Result<Connection<'p>, BleHostError<C::Error>> {
+    let mut adv_data = [0; 31];
+
+    AdStructure::encode_slice(
+        &[
+            AdStructure::Flags(LE_GENERAL_DISCOVERABLE | BR_EDR_NOT_SUPPORTED),
+            AdStructure::ServiceUuids16(&[Uuid::Uuid16(BATTERY_SERVICE.to_le_bytes())]),
+            AdStructure::CompleteLocalName(DEVICE_NAME),
+        ],
+        &mut adv_data[..],
+    )?;
+
+    let advertiser = peripheral
+        .advertise(
+            &Default::default(),
+            Advertisement::ConnectableScannableUndirected {
+                adv_data: &adv_data[..],
+                scan_data: &[],
+            },
+        )
+        .await?;
+
+    advertiser.accept().await
+}
+
+// The battery level as a percentage, estimated linearly from the voltage.
+fn battery_percent() -> u8 {
+    let Some(mv) = battery::read_millivolts() else {
+        return 0;
+    };
+
+    let mv = (mv as u32).clamp(BATTERY_EMPTY_MV, BATTERY_FULL_MV);
+
+    ((mv - BATTERY_EMPTY_MV) * 100 / (BATTERY_FULL_MV - BATTERY_EMPTY_MV)) as u8
+}
+
+// The current time in the format of the Current Time characteristic: the year (little endian),
+// month, day, hours, minutes, seconds, day of the week (Monday is 1), fractions of a second in
+// 1/256ths, and the reason for the last adjustment.
+fn current_time() -> [u8; 10] {
+    let micros = rtc::unix_time_micros();
+    let secs = micros / 1_000_000;
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    let (year, month, day) = civil_from_days(days);
+    // the Unix epoch was a Thursday.
+    let weekday = (days + 3).rem_euclid(7) + 1;
+    let fraction = (micros % 1_000_000) * 256 / 1_000_000;
+
+    let year = (year as u16).to_le_bytes();
+
+    [
+        year[0],
+        year[1],
+        month as u8,
+        day as u8,
+        (secs_of_day / 3600) as u8,
+        (secs_of_day / 60 % 60) as u8,
+        (secs_of_day % 60) as u8,
+        weekday as u8,
+        fraction as u8,
+        0,
+    ]
+}
+
+// Converts days since the Unix epoch to a (year, month, day) date in the proleptic Gregorian
+// calendar. This is Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    (year, month, day)
+}
@@ -0,0 +1,103 @@
+use crate::log_init;
+use embassy_executor::task;
+use embassy_futures::select::{select, Either};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+use esp_hal::gpio::{GpioPin, Level, Output};
+use esp_hal::ledc::channel::{self, ChannelIFace};
+use esp_hal::ledc::timer::{self, TimerIFace};
+use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};
+use esp_hal::peripherals::LEDC;
+use fugit::RateExtU32;
+
+pub(crate) const HAPTIC_PWM_FREQ: u32 = 20_000;
+pub(crate) const MAX_INTENSITY: u8 = 100;
+
+// Only the latest request is kept, so a new vibration replaces one that's still running.
+static VIBRATION: Signal<CsRawMutex, Vibration> = Signal::new();
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+struct Vibration {
+    duration: Duration,
+    intensity: u8,
+}
+
+/// Runs the vibration motor for `duration` at `intensity`, from 0 (off) to [`MAX_INTENSITY`]. This
+/// returns immediately, and replaces any vibration which is still running. Without PWM, any
+/// intensity above 0 runs the motor at full power.
+pub fn vibrate(duration: Duration, intensity: u8) {
+    VIBRATION.signal(Vibration {
+        duration,
+        intensity: intensity.min(MAX_INTENSITY),
+    });
+}
+
+enum Motor<'d> {
+    Pwm(channel::Channel<'d, LowSpeed, GpioPin<5>>),
+    OnOff(Output<'d, GpioPin<5>>),
+}
+
+impl Motor<'_> {
+    fn set_intensity(&mut self, intensity: u8) {
+        match self {
+            Self::Pwm(channel) => {
+                if let Err(e) = channel.set_duty(intensity) {
+                    log::warn!("failed to set vibration intensity: {e:?}");
+                }
+            }
+            Self::OnOff(output) => output.set_level(Level::from(intensity > 0)),
+        }
+    }
+}
+
+#[task]
+pub async fn start(ledc: LEDC, pin: GpioPin<5>) -> ! {
+    let mut ledc = Ledc::new(ledc);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    let mut pwm_timer = ledc.get_timer::<LowSpeed>(timer::Number::Timer0);
+
+    let timer_config = timer::config::Config {
+        duty: timer::config::Duty::Duty8Bit,
+        clock_source: timer::LSClockSource::APBClk,
+        frequency: HAPTIC_PWM_FREQ.Hz(),
+    };
+
+    // the channel can't fail to configure with a valid timer, so only the timer needs checking.
+    let mut motor = match pwm_timer.configure(timer_config) {
+        Ok(_) => {
+            let mut channel = ledc.get_channel(channel::Number::Channel0, pin);
+
+            channel
+                .configure(channel::config::Config {
+                    timer: &pwm_timer,
+                    duty_pct: 0,
+                    pin_config: channel::config::PinConfig::PushPull,
+                })
+                .expect("failed to configure haptic PWM channel");
+
+            Motor::Pwm(channel)
+        }
+        Err(e) => {
+            log::warn!("haptic PWM unavailable ({e:?}), vibration intensity will be ignored");
+            Motor::OnOff(Output::new(pin, Level::Low))
+        }
+    };
+
+    log_init("haptic");
+
+    let mut next = VIBRATION.wait().await;
+
+    loop {
+        motor.set_intensity(next.intensity);
+
+        next = match select(Timer::after(next.duration), VIBRATION.wait()).await {
+            Either::First(_) => {
+                motor.set_intensity(0);
+                VIBRATION.wait().await
+            }
+            Either::Second(replacement) => replacement,
+        };
+    }
+}
@@ -0,0 +1,146 @@
+use crate::log_init;
+use core::future::poll_fn;
+use embassy_executor::task;
+use embassy_futures::select::{select, Either};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+use esp_hal::gpio::{AnyPin, Input, Pull, WakeEvent};
+
+pub(crate) const BUTTON_COUNT: usize = 4;
+pub(crate) const INPUT_QUEUE_SIZE: usize = 16;
+const DEBOUNCE_TIME: Duration = Duration::from_millis(20);
+const HOLD_TIME: Duration = Duration::from_millis(500);
+
+/// Button events which haven't been handled yet. If apps don't keep up, new events are dropped
+/// until there's space in the queue again.
+pub static INPUT_EVENTS: Channel<CsRawMutex, ButtonEvent, INPUT_QUEUE_SIZE> = Channel::new();
+
+// Signalled once by `prepare_sleep` and again by `finish_sleep`, for each button's task.
+static SLEEP_SIGNALS: [Signal<CsRawMutex, ()>; BUTTON_COUNT] =
+    [const { Signal::new() }; BUTTON_COUNT];
+
+// Sent by each button's task once its pin can wake the chip.
+static WAKEUP_ARMED: Channel<CsRawMutex, (), BUTTON_COUNT> = Channel::new();
+
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Button {
+    Back,
+    Up,
+    Select,
+    Down,
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum ButtonEventKind {
+    Press,
+    Release,
+    /// The button has been held down for a while. This is sent once per press, before the
+    /// release.
+    Hold,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct ButtonEvent {
+    pub button: Button,
+    pub kind: ButtonEventKind,
+}
+
+impl ButtonEvent {
+    /// Encodes the event for passing to apps. The low byte is the button and the next byte is the
+    /// kind of event.
+    pub fn to_u32(self) -> u32 {
+        self.button as u32 | (self.kind as u32) << 8
+    }
+}
+
+/// Removes the next button event from the queue, if there is one.
+pub fn poll() -> Option<ButtonEvent> {
+    INPUT_EVENTS.try_receive().ok()
+}
+
+/// Waits until there's a button event in the queue, without removing it.
+pub async fn ready() {
+    poll_fn(|cx| INPUT_EVENTS.poll_ready_to_receive(cx)).await
+}
+
+/// Arms every button to wake the chip from light sleep, waiting until they all are. A button which
+/// is being held is armed once it's released. [`finish_sleep`] has to be called after waking up.
+pub async fn prepare_sleep() {
+    for signal in &SLEEP_SIGNALS {
+        signal.signal(());
+    }
+
+    for _ in 0..BUTTON_COUNT {
+        WAKEUP_ARMED.receive().await;
+    }
+}
+
+/// Lets the buttons go back to sending events after [`prepare_sleep`].
+pub fn finish_sleep() {
+    for signal in &SLEEP_SIGNALS {
+        signal.signal(());
+    }
+}
+
+fn push(button: Button, kind: ButtonEventKind) {
+    if INPUT_EVENTS.try_send(ButtonEvent { button, kind }).is_err() {
+        log::warn!("input queue full, dropping {kind:?} event for {button:?}");
+    }
+}
+
+/// Watches a single button. The buttons pull their pins low when pressed.
+#[task(pool_size = BUTTON_COUNT)]
+pub async fn start(button: Button, pin: AnyPin) -> ! {
+    let mut input = Input::new(pin, Pull::Up);
+
+    log_init("button");
+
+    loop {
+        wait_for_press(&mut input, button).await;
+
+        // the contacts bounce for a few milliseconds, so the level is only trusted once it's
+        // settled.
+        Timer::after(DEBOUNCE_TIME).await;
+
+        if input.is_high() {
+            continue;
+        }
+
+        push(button, ButtonEventKind::Press);
+
+        if let Either::First(_) = select(Timer::after(HOLD_TIME), input.wait_for_high()).await {
+            push(button, ButtonEventKind::Hold);
+            input.wait_for_high().await;
+        }
+
+        Timer::after(DEBOUNCE_TIME).await;
+        push(button, ButtonEventKind::Release);
+    }
+}
+
+// Waits for the button's pin to go low. While the chip is asleep, the pin is armed to wake it
+// instead.
+async fn wait_for_press(input: &mut Input<'_>, button: Button) {
+    let sleep = &SLEEP_SIGNALS[button as usize];
+
+    loop {
+        if let Either::First(_) = select(input.wait_for_falling_edge(), sleep.wait()).await {
+            return;
+        }
+
+        input.wakeup_enable(true, WakeEvent::LowLevel);
+        WAKEUP_ARMED.send(()).await;
+
+        sleep.wait().await;
+        input.wakeup_enable(false, WakeEvent::LowLevel);
+
+        // a button which woke the chip is still held down, so there won't be an edge for it.
+        if input.is_low() {
+            return;
+        }
+    }
+}
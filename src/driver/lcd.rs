@@ -13,10 +13,12 @@
 
 use crate::log_init;
 use crate::macros::singleton;
+use crate::widget::bitmap::{BitmapRef, PixelColor};
 use crate::widget::Widget;
 use bitflags::bitflags;
 use core::convert::Infallible;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use embassy_executor::task;
 use embassy_futures::yield_now;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex;
@@ -24,7 +26,10 @@ use embassy_sync::mutex::Mutex;
 use embassy_sync::signal::Signal;
 use embassy_time::{Duration, Instant, Timer};
 use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics::prelude::{DrawTarget, OriginDimensions, Size};
+use embedded_graphics::prelude::{
+    Dimensions, DrawTarget, OriginDimensions, Point, PointsIter, Size,
+};
+use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::Pixel;
 use embedded_hal_async::spi::SpiBus;
 use esp_hal::clock::Clocks;
@@ -41,12 +46,24 @@ pub(crate) const LCD_Y: u8 = 168;
 pub(crate) const LCD_BUFFER_SIZE: usize = (LCD_X as usize * LCD_Y as usize) / 8;
 pub(crate) const LCD_DMA_BUFFER_SIZE: usize = SPI_BUFFER_SIZE * LCD_Y as usize + 2;
 pub(crate) const LCD_SPI_FREQ: u32 = 2_000_000;
-pub(crate) const LCD_REFRESH_TIME: Duration = Duration::from_hz(60);
+pub(crate) const LCD_REFRESH_RATES: RangeInclusive<u32> = 1..=60;
 const BYTES_PER_LINE: usize = LCD_X as usize / 8;
 const SPI_BUFFER_SIZE: usize = BYTES_PER_LINE + 2;
 
 static LCD_INITIALIZED: AtomicBool = AtomicBool::new(false);
+static LCD_DISPLAY_ON: AtomicBool = AtomicBool::new(true);
+static LCD_BLANKED: Signal<CsRawMutex, ()> = Signal::new();
+static LCD_REFRESH_RATE: AtomicU32 = AtomicU32::new(*LCD_REFRESH_RATES.end());
+// The number of frames the display task has copied out of `LCD_BUFFER`, and the number of the last
+// one it finished drawing.
+static LCD_FRAMES_STARTED: AtomicU32 = AtomicU32::new(0);
+static LCD_FRAME_DONE: Signal<CsRawMutex, u32> = Signal::new();
+static LCD_DOUBLE_BUFFERED: AtomicBool = AtomicBool::new(false);
+/// The buffer everything is drawn into. With double buffering on, this is the back buffer, and it's
+/// only shown once [`swap_buffers`] is called.
 pub static LCD_BUFFER: Mutex<CsRawMutex, LcdBuffer> = Mutex::new(LcdBuffer::new());
+// The buffer the display task reads from while double buffering is on.
+static LCD_FRONT_BUFFER: Mutex<CsRawMutex, LcdBuffer> = Mutex::new(LcdBuffer::new());
 
 macro_rules! data {
     ($($val:expr),* $(,)?) => {
@@ -59,11 +76,98 @@ pub async fn draw<W: Widget>(widget: W) {
     widget.render(&mut buffer);
 }
 
+/// Draws `bitmap` with [`LcdBuffer::blit_bitmap`], which is faster than drawing it as a widget.
+pub async fn draw_bitmap(bitmap: &BitmapRef<'_>, position: Point) {
+    let mut buffer = LCD_BUFFER.lock().await;
+    buffer.blit_bitmap(bitmap, position.x, position.y);
+}
+
 pub async fn clear() {
     let mut buffer = LCD_BUFFER.lock().await;
     buffer.clear()
 }
 
+/// Returns the maximum number of times per second the display is refreshed.
+pub fn refresh_rate() -> u32 {
+    LCD_REFRESH_RATE.load(Ordering::Relaxed)
+}
+
+/// Sets the maximum number of times per second the display is refreshed, clamped to
+/// [`LCD_REFRESH_RATES`]. Returns the rate that was actually set. The display task picks up the new
+/// rate at the start of its next frame.
+pub fn set_refresh_rate(hz: u32) -> u32 {
+    let hz = hz.clamp(*LCD_REFRESH_RATES.start(), *LCD_REFRESH_RATES.end());
+    LCD_REFRESH_RATE.store(hz, Ordering::Relaxed);
+    hz
+}
+
+/// Waits until a frame copied from [`LCD_BUFFER`] after this is called has been drawn, so
+/// everything drawn before calling it is on the display. Only one task should wait at a time.
+pub async fn wait_for_refresh() {
+    let started = LCD_FRAMES_STARTED.load(Ordering::Acquire);
+
+    while LCD_FRAME_DONE.wait().await <= started {}
+}
+
+pub fn is_double_buffered() -> bool {
+    LCD_DOUBLE_BUFFERED.load(Ordering::Relaxed)
+}
+
+/// Turns double buffering on or off. While it's on, the display only shows what was in
+/// [`LCD_BUFFER`] the last time [`swap_buffers`] was called, so it never shows a half drawn frame.
+/// Turning it on swaps the buffers once, so the display keeps showing the current contents, and
+/// turning it off redraws all of [`LCD_BUFFER`], since the display could be showing an older frame.
+pub async fn set_double_buffered(enabled: bool) {
+    let mut back = LCD_BUFFER.lock().await;
+
+    if enabled {
+        let mut front = LCD_FRONT_BUFFER.lock().await;
+
+        *front = *back;
+        front.mark_all_changed();
+    } else {
+        back.mark_all_changed();
+    }
+
+    LCD_DOUBLE_BUFFERED.store(enabled, Ordering::Relaxed);
+}
+
+/// Copies [`LCD_BUFFER`] to the front buffer, to be shown on the next refresh. [`LCD_BUFFER`]
+/// keeps its contents, so drawing can carry on from the last frame.
+///
+/// Drawing tasks which haven't run yet aren't part of the swapped frame, so callers should wait
+/// for them first (the app executor uses `Env::wait_for_draws`), or the front buffer can end up
+/// with half a frame.
+pub async fn swap_buffers() {
+    // like `set_double_buffered`, this locks the back buffer first, so the two can't deadlock.
+    let mut back = LCD_BUFFER.lock().await;
+    let mut front = LCD_FRONT_BUFFER.lock().await;
+
+    front.take_changes(&mut back);
+}
+
+/// Blanks the display, waiting until the display task has cleared it. [`LCD_BUFFER`] can still be
+/// drawn to while the display is off, and everything in it is shown again by [`display_on`].
+pub async fn display_off() {
+    LCD_BLANKED.reset();
+    LCD_DISPLAY_ON.store(false, Ordering::Relaxed);
+    LCD_BLANKED.wait().await;
+}
+
+/// Turns the display back on after [`display_off`], redrawing the whole buffer.
+pub fn display_on() {
+    LCD_DISPLAY_ON.store(true, Ordering::Relaxed);
+}
+
+pub fn is_display_on() -> bool {
+    LCD_DISPLAY_ON.load(Ordering::Relaxed)
+}
+
+/// Returns a copy of the contents of [`LCD_BUFFER`], in the format of [`LcdBuffer::as_bytes`].
+pub async fn read_buffer() -> [u8; LCD_BUFFER_SIZE] {
+    *LCD_BUFFER.lock().await.as_bytes()
+}
+
 #[task]
 pub async fn start(
     spi: SPI2,
@@ -90,6 +194,7 @@ pub async fn start(
         .with_buffers(rx, tx);
 
     let mut lcd = Lcd::new(spi, cs);
+    let mut blanked = false;
 
     log_init("display");
 
@@ -100,13 +205,45 @@ pub async fn start(
         yield_now().await;
 
         let render_start = Instant::now();
+        let refresh_time = Duration::from_hz(refresh_rate() as u64);
+
+        if !is_display_on() {
+            // The buffer isn't marked as refreshed while the display is off, so nothing drawn in
+            // the meantime is lost.
+            if !blanked {
+                lcd.clear().await;
+                blanked = true;
+            }
+
+            LCD_BLANKED.signal(());
+            // nothing can be shown while the display is off, so waiting for a frame is pointless.
+            LCD_FRAME_DONE.signal(LCD_FRAMES_STARTED.fetch_add(1, Ordering::AcqRel) + 1);
+            Timer::after(refresh_time).await;
+            continue;
+        }
 
-        {
+        let frame = {
             // Copying to a local buffer prevents holding the mutex lock for ~14ms while the display
             // is being drawn to.
-            let mut buffer = LCD_BUFFER.lock().await;
+            let source = if is_double_buffered() {
+                &LCD_FRONT_BUFFER
+            } else {
+                &LCD_BUFFER
+            };
+
+            let mut buffer = source.lock().await;
             local_buffer = *buffer;
             buffer.refreshed();
+
+            // counted before the lock is released, so `wait_for_refresh` can't be called between
+            // the copy and the increment and mistake this frame for one copied after it.
+            LCD_FRAMES_STARTED.fetch_add(1, Ordering::AcqRel) + 1
+        };
+
+        if blanked {
+            // The panel was cleared, so every line needs to be sent again.
+            local_buffer.mark_all_changed();
+            blanked = false;
         }
 
         if local_buffer.needs_clear() {
@@ -115,13 +252,15 @@ pub async fn start(
             lcd.refresh(&mut local_buffer).await;
         }
 
+        LCD_FRAME_DONE.signal(frame);
+
         let elapsed = render_start.elapsed();
 
-        if elapsed < LCD_REFRESH_TIME {
+        if elapsed < refresh_time {
             // Limit the framerate to the maximum allowed.
             // FIXME: This timer always seems to stop before the 16.66.. ms is up,
             // maybe something to do with embassy's default tick frequency?
-            Timer::after(LCD_REFRESH_TIME - elapsed).await;
+            Timer::after(refresh_time - elapsed).await;
         }
     }
 }
@@ -251,6 +390,49 @@ impl LcdBuffer {
         }
     }
 
+    /// Creates a buffer with `bitmap` drawn at the top left corner. Transparent pixels, and the
+    /// parts of the display the bitmap doesn't cover, are left white.
+    pub fn from_bitmap(bitmap: &BitmapRef<'_>) -> Self {
+        let mut buffer = Self::new();
+        buffer.blit_bitmap(bitmap, 0, 0);
+        buffer
+    }
+
+    /// Draws `bitmap` with its top left corner at `x, y`, skipping transparent pixels. This writes
+    /// to the buffer directly, so it's much faster than drawing the bitmap through
+    /// embedded-graphics, which goes through every pixel of the bitmap one at a time and bounds
+    /// checks each of them.
+    pub fn blit_bitmap(&mut self, bitmap: &BitmapRef<'_>, x: i32, y: i32) {
+        const LCD_WHITE_LUT: [u8; 8] = [1, 2, 4, 8, 16, 32, 64, 128];
+
+        let origin = Point::new(x, y);
+        let area = Rectangle::new(origin, bitmap.size()).intersection(&self.bounding_box());
+
+        let Some(bottom_right) = area.bottom_right() else {
+            return;
+        };
+
+        // the intersection is within the display, so every coordinate fits in a u8, and the
+        // coordinates relative to the bitmap are within it.
+        for screen_y in area.top_left.y..=bottom_right.y {
+            let bitmap_y = (screen_y - y) as u8;
+
+            for screen_x in area.top_left.x..=bottom_right.x {
+                let bitmap_x = (screen_x - x) as u8;
+                let (index, bit) = Self::get_index_and_bit(screen_x as u8, screen_y as u8);
+
+                match bitmap.get_pixel(bitmap_x, bitmap_y) {
+                    Some(PixelColor::Black) => self.buf[index] &= !LCD_WHITE_LUT[bit],
+                    Some(PixelColor::White) => self.buf[index] |= LCD_WHITE_LUT[bit],
+                    Some(PixelColor::Transparent) | None => {}
+                }
+            }
+        }
+
+        self.min_changed = self.min_changed.min(area.top_left.y as u8);
+        self.max_changed = self.max_changed.max(bottom_right.y as u8 + 1);
+    }
+
     pub fn fill(&mut self, byte: u8) {
         self.buf.fill(byte);
         self.min_changed = 0;
@@ -264,6 +446,16 @@ impl LcdBuffer {
         self.clear = true;
     }
 
+    /// Sets every pixel in `rect` to `color`. The parts of `rect` outside the display are ignored.
+    pub fn fill_rect(&mut self, rect: &Rectangle, color: BinaryColor) {
+        let rect = rect.intersection(&self.bounding_box());
+
+        for Point { x, y } in rect.points() {
+            // the intersection is within the display, so both coordinates fit in a u8.
+            self.set_pixel_internal(x as u8, y as u8, color);
+        }
+    }
+
     pub fn set_pixel<T>(&mut self, x: T, y: T, color: BinaryColor)
     where
         T: TryInto<u8>,
@@ -295,10 +487,42 @@ impl LcdBuffer {
         }
     }
 
+    /// XORs a pixel with `color`. Drawing [`BinaryColor::On`] inverts the pixel, and drawing
+    /// [`BinaryColor::Off`] leaves it unchanged, so drawing the same pixels twice restores the
+    /// original contents of the buffer.
+    pub fn set_pixel_xor<T>(&mut self, x: T, y: T, color: BinaryColor)
+    where
+        T: TryInto<u8>,
+    {
+        let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) else {
+            return;
+        };
+
+        if color.is_on() && x < LCD_X && y < LCD_Y {
+            let (index, bit) = Self::get_index_and_bit(x, y);
+
+            self.buf[index] ^= 1 << bit;
+
+            self.min_changed = self.min_changed.min(y);
+            self.max_changed = self.max_changed.max(y.saturating_add(1)).min(LCD_Y);
+        }
+    }
+
+    /// Returns a draw target which XORs everything drawn to it with the contents of this buffer.
+    pub fn xor(&mut self) -> XorTarget<'_> {
+        XorTarget(self)
+    }
+
     pub fn copy_from_buffer(&mut self, other: &Self) {
         *self = *other
     }
 
+    /// Returns the raw contents of the buffer. Each line is [`LCD_X`] / 8 bytes long, with pixels
+    /// packed LSB first. A set bit is a white pixel and a cleared bit is a black pixel.
+    pub fn as_bytes(&self) -> &[u8; LCD_BUFFER_SIZE] {
+        &self.buf
+    }
+
     pub fn get_line(&self, n: usize) -> &[u8] {
         let index = n * BYTES_PER_LINE;
 
@@ -311,6 +535,23 @@ impl LcdBuffer {
         &mut self.buf[index..index + BYTES_PER_LINE]
     }
 
+    /// Marks every line as changed, so the next refresh redraws the whole display.
+    pub fn mark_all_changed(&mut self) {
+        self.min_changed = 0;
+        self.max_changed = LCD_Y;
+    }
+
+    /// Copies the contents of `other` into this buffer, adding the lines changed in `other` to the
+    /// ones already waiting to be refreshed in this one. `other` is marked as refreshed.
+    pub fn take_changes(&mut self, other: &mut Self) {
+        self.buf = other.buf;
+        self.min_changed = self.min_changed.min(other.min_changed);
+        self.max_changed = self.max_changed.max(other.max_changed);
+        self.clear |= other.clear;
+
+        other.refreshed();
+    }
+
     pub fn refreshed(&mut self) {
         self.min_changed = LCD_Y;
         self.max_changed = 0;
@@ -362,3 +603,122 @@ impl DrawTarget for LcdBuffer {
         Ok::<_, Infallible>(())
     }
 }
+
+/// A draw target which XORs pixels into an [`LcdBuffer`] instead of setting them. See
+/// [`LcdBuffer::set_pixel_xor`].
+pub struct XorTarget<'a>(&'a mut LcdBuffer);
+
+impl OriginDimensions for XorTarget<'_> {
+    fn size(&self) -> Size {
+        self.0.size()
+    }
+}
+
+impl DrawTarget for XorTarget<'_> {
+    type Color = BinaryColor;
+
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels.into_iter() {
+            self.0.set_pixel_xor(point.x, point.y, color);
+        }
+
+        Ok::<_, Infallible>(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+    use embedded_graphics::prelude::{Drawable, Primitive};
+    use embedded_graphics::primitives::{Circle, PrimitiveStyle};
+    use std::sync::{Mutex as StdMutex, MutexGuard as StdMutexGuard};
+
+    // The display state is global, so tests using it can't run at the same time.
+    static DISPLAY: StdMutex<()> = StdMutex::new(());
+
+    fn lock_display() -> StdMutexGuard<'static, ()> {
+        DISPLAY
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    #[test]
+    fn read_buffer_returns_packed_pixels() {
+        let _display = lock_display();
+
+        block_on(async {
+            clear().await;
+            draw(
+                Rectangle::new(Point::new(8, 1), Size::new(10, 2))
+                    .into_styled(PrimitiveStyle::with_fill(BinaryColor::On)),
+            )
+            .await;
+
+            // x = 8..16 is the whole second byte of each line, and x = 16..18 is the low two bits
+            // of the third. Every other pixel is white.
+            let mut expected = [0xff; LCD_BUFFER_SIZE];
+
+            for y in 1..3 {
+                expected[y * BYTES_PER_LINE + 1] = 0x00;
+                expected[y * BYTES_PER_LINE + 2] = 0b1111_1100;
+            }
+
+            assert_eq!(read_buffer().await, expected);
+        });
+    }
+
+    #[test]
+    fn turning_off_double_buffering_redraws_the_back_buffer() {
+        let _display = lock_display();
+
+        block_on(async {
+            set_double_buffered(true).await;
+            // the display task has sent everything drawn so far.
+            LCD_BUFFER.lock().await.refreshed();
+
+            set_double_buffered(false).await;
+
+            let back = LCD_BUFFER.lock().await;
+            assert!(back.needs_refresh());
+            assert_eq!((back.min_changed, back.max_changed), (0, LCD_Y));
+        });
+    }
+
+    #[test]
+    fn refresh_rate_is_clamped() {
+        let _display = lock_display();
+
+        assert_eq!(set_refresh_rate(0), 1);
+        assert_eq!(refresh_rate(), 1);
+        assert_eq!(set_refresh_rate(30), 30);
+        assert_eq!(set_refresh_rate(61), 60);
+        assert_eq!(set_refresh_rate(u32::MAX), 60);
+        assert_eq!(refresh_rate(), 60);
+    }
+
+    #[test]
+    fn xor_twice_restores_the_buffer() {
+        let mut buffer = LcdBuffer::new();
+        buffer.fill_rect(
+            &Rectangle::new(Point::new(10, 10), Size::new(50, 30)),
+            BinaryColor::On,
+        );
+        let original = *buffer.as_bytes();
+
+        // overlaps the edges of the rectangle and of the display.
+        let circle = Circle::new(Point::new(-20, 0), 100)
+            .into_styled(PrimitiveStyle::with_fill(BinaryColor::On));
+
+        circle.draw(&mut buffer.xor()).unwrap();
+        assert_ne!(*buffer.as_bytes(), original);
+
+        circle.draw(&mut buffer.xor()).unwrap();
+        assert_eq!(*buffer.as_bytes(), original);
+    }
+}
@@ -1 +1,6 @@
+pub mod battery;
+pub mod ble;
+pub mod haptic;
+pub mod input;
 pub mod lcd;
+pub mod rtc;
@@ -0,0 +1,76 @@
+use crate::log_init;
+use core::cell::{Cell, RefCell};
+use critical_section::{self as cs, Mutex};
+use esp_hal::peripherals::LPWR;
+use esp_hal::rtc_cntl::Rtc;
+
+const MICROS_PER_SEC: u64 = 1_000_000;
+
+static RTC: Mutex<RefCell<Option<Rtc<'static>>>> = Mutex::new(RefCell::new(None));
+
+// The Unix time in microseconds when the RTC was at 0. The RTC keeps counting through light sleep,
+// unlike the timers behind `Instant`, so wall-clock time stays correct across it.
+static UNIX_OFFSET: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+// The RTC's count when it was taken out with `take`, which is used as the time until it's put back.
+static TAKEN_AT: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+pub fn init(lpwr: LPWR) {
+    let rtc = Rtc::new(lpwr);
+
+    cs::with(|cs| RTC.replace(cs, Some(rtc)));
+
+    log_init("RTC");
+}
+
+/// Runs `f` with the RTC, or returns `None` if it hasn't been initialized. This runs in a critical
+/// section, so `f` should be short.
+pub(crate) fn with_rtc<R>(f: impl FnOnce(&mut Rtc<'static>) -> R) -> Option<R> {
+    cs::with(|cs| RTC.borrow_ref_mut(cs).as_mut().map(f))
+}
+
+/// Takes the RTC out, for using it outside of a critical section for longer than [`with_rtc`]
+/// allows, like while sleeping. Until it's returned with [`put_back`], the time stands still.
+pub(crate) fn take() -> Option<Rtc<'static>> {
+    cs::with(|cs| {
+        let rtc = RTC.take(cs)?;
+        TAKEN_AT.borrow(cs).set(rtc.time_since_boot().to_micros());
+
+        Some(rtc)
+    })
+}
+
+/// Returns the RTC taken out with [`take`].
+pub(crate) fn put_back(rtc: Rtc<'static>) {
+    cs::with(|cs| RTC.replace(cs, Some(rtc)));
+}
+
+// Microseconds counted by the RTC. This is 0 if it hasn't been initialized, and the count when it
+// was taken out if it isn't back yet.
+fn rtc_micros() -> u64 {
+    cs::with(|cs| {
+        RTC.borrow_ref(cs).as_ref().map_or_else(
+            || TAKEN_AT.borrow(cs).get(),
+            |rtc| rtc.time_since_boot().to_micros(),
+        )
+    })
+}
+
+/// Returns the current Unix time in microseconds. Until the time is set, this counts up from the
+/// Unix epoch.
+pub fn unix_time_micros() -> u64 {
+    cs::with(|cs| UNIX_OFFSET.borrow(cs).get()).wrapping_add(rtc_micros())
+}
+
+/// Returns the current Unix time in seconds.
+pub fn unix_time() -> u64 {
+    unix_time_micros() / MICROS_PER_SEC
+}
+
+/// Sets the current Unix time in seconds.
+pub fn set_unix_time(secs: u64) {
+    let micros = secs.saturating_mul(MICROS_PER_SEC);
+    let offset = micros.wrapping_sub(rtc_micros());
+
+    cs::with(|cs| UNIX_OFFSET.borrow(cs).set(offset));
+}
@@ -53,6 +53,23 @@ pub trait FloatExt: Copy + Sized {
     fn sin_cos(self) -> (Self, Self) {
         (self.sin(), self.cos())
     }
+
+    /// Evaluates the polynomial with the given coefficients at `self` using Horner's method. The
+    /// coefficients are ordered from the highest degree term to the constant term, so `[a, b, c]`
+    /// evaluates `a * x^2 + b * x + c`. An empty slice evaluates to zero.
+    ///
+    /// Each step is done with [`FloatExt::mul_add`], which only rounds once, so this accumulates
+    /// less rounding error than evaluating the terms separately and summing them.
+    fn poly_eval(self, coefficients: &[Self]) -> Self
+    where
+        Self: Default,
+    {
+        coefficients
+            .iter()
+            .fold(Self::default(), |acc, &coefficient| {
+                acc.mul_add(self, coefficient)
+            })
+    }
 }
 
 impl FloatExt for f32 {
@@ -410,3 +427,50 @@ impl FloatExt for f64 {
         Libm::<f64>::lgamma_r(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sums each term separately, highest degree first like the coefficients.
+    fn naive_eval(x: f64, coefficients: &[f64]) -> f64 {
+        let degree = coefficients.len() as i32 - 1;
+
+        coefficients
+            .iter()
+            .enumerate()
+            .map(|(i, &coefficient)| coefficient * x.powi(degree - i as i32))
+            .sum()
+    }
+
+    #[test]
+    fn poly_eval_matches_naive_evaluation() {
+        let polynomials: &[&[f64]] = &[
+            &[],
+            &[3.5],
+            &[2.0, -1.0],
+            &[1.0, -3.0, 3.0, -1.0],
+            &[0.5, 0.0, -2.25, 1.0e-3, 7.0],
+        ];
+
+        for &coefficients in polynomials {
+            for x in [-2.5, -1.0, 0.0, 0.3, 1.0, 4.0] {
+                let expected = naive_eval(x, coefficients);
+                let actual = FloatExt::poly_eval(x, coefficients);
+
+                assert!(
+                    (actual - expected).abs() <= 1e-12 * expected.abs().max(1.0),
+                    "{coefficients:?} at {x}: {actual} != {expected}"
+                );
+
+                let coefficients_f32: Vec<f32> = coefficients.iter().map(|&c| c as f32).collect();
+                let actual_f32 = FloatExt::poly_eval(x as f32, &coefficients_f32);
+
+                assert!(
+                    (f64::from(actual_f32) - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+                    "{coefficients:?} at {x}: {actual_f32} != {expected}"
+                );
+            }
+        }
+    }
+}
@@ -1,633 +1,1335 @@
-// pub mod file;
-// pub(crate) mod node;
-// mod storage;
-
-// use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
-// use core::{
-//     fmt,
-//     hint::spin_loop,
-//     mem::size_of,
-//     ops::{Deref, DerefMut, Range},
-//     str::{self, FromStr},
-// };
-// use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
-// use embedded_io::{
-//     Error as IoError, ErrorKind as IoErrorKind, ErrorType as IoErrorType, Read, Seek, SeekFrom, Write
-// };
-// use embedded_io_async::{Read as AsyncRead, Seek as AsyncSeek, Write as AsyncWrite};
-// use esp_hal::{
-//     rng::Rng,
-//     sha::{Sha, Sha256},
-//     Blocking,
-// };
-// use esp_storage::{FlashStorage as EspFlashStorage, FlashStorageError as EspFlashStorageError};
-// use heapless::String as FixedString;
-// use heapless::Vec as FixedVec;
-// use node::{Chunk, Metadata, Node, CHUNK_DATA_SIZE, MAX_NAME_BYTES};
-// use postcard::experimental::max_size::MaxSize;
-// use sequential_storage::map;
-// use sequential_storage::Error as SeqStorageError;
-// use sequential_storage::{
-//     cache::KeyPointerCache,
-//     map::{Key as MapKey, SerializationError as MapSerError},
-// };
-// use serde::{Deserialize, Serialize};
-// use storage::Storage;
-// use thiserror::Error;
-
-// pub const FS_START: u32 = 0x00110000;
-// pub const FS_SIZE: u32 = 0x006f0000;
-// pub const FS_RANGE: Range<u32> = FS_START..FS_START + FS_SIZE;
-// pub const FS_PAGES: usize = FS_SIZE as usize / EspFlashStorage::SECTOR_SIZE as usize;
-// pub const FS_CACHE_KEYS: usize = 32;
-// pub const KEY_BYTES: usize = 32;
-// pub const CHUNK_ID_BYTES: usize = KEY_BYTES - size_of::<u16>();
-// const U16_BYTES: usize = size_of::<u16>();
-
-// type Cache = KeyPointerCache<FS_PAGES, Key, FS_CACHE_KEYS>;
-
-// fn postcard_error_to_i32(error: postcard::Error) -> i32 {
-//     use postcard::Error as PcError;
-
-//     match error {
-//         PcError::WontImplement => 0,
-//         PcError::NotYetImplemented => 1,
-//         PcError::SerializeBufferFull => 2,
-//         PcError::SerializeSeqLengthUnknown => 3,
-//         PcError::DeserializeUnexpectedEnd => 4,
-//         PcError::DeserializeBadVarint => 5,
-//         PcError::DeserializeBadBool => 6,
-//         PcError::DeserializeBadChar => 7,
-//         PcError::DeserializeBadUtf8 => 8,
-//         PcError::DeserializeBadOption => 9,
-//         PcError::DeserializeBadEnum => 10,
-//         PcError::DeserializeBadEncoding => 11,
-//         PcError::DeserializeBadCrc => 12,
-//         PcError::SerdeSerCustom => 13,
-//         PcError::SerdeDeCustom => 14,
-//         PcError::CollectStrError => 15,
-//         e => unimplemented!("unknown postcard error {e:?}"),
-//     }
-// }
-
-// fn i32_to_postcard_error(error: i32) -> postcard::Error {
-//     use postcard::Error as PcError;
-
-//     match error {
-//         0 => PcError::WontImplement,
-//         1 => PcError::NotYetImplemented,
-//         2 => PcError::SerializeBufferFull,
-//         3 => PcError::SerializeSeqLengthUnknown,
-//         4 => PcError::DeserializeUnexpectedEnd,
-//         5 => PcError::DeserializeBadVarint,
-//         6 => PcError::DeserializeBadBool,
-//         7 => PcError::DeserializeBadChar,
-//         8 => PcError::DeserializeBadUtf8,
-//         9 => PcError::DeserializeBadOption,
-//         10 => PcError::DeserializeBadEnum,
-//         11 => PcError::DeserializeBadEncoding,
-//         12 => PcError::DeserializeBadCrc,
-//         13 => PcError::SerdeSerCustom,
-//         14 => PcError::SerdeDeCustom,
-//         15 => PcError::CollectStrError,
-//         int => unimplemented!("invalid integer {int} for postcard error"),
-//     }
-// }
-
-// fn check_name(name: &str) -> Result<(), Error> {
-//     if name.len() <= MAX_NAME_BYTES {
-//         Ok(())
-//     } else {
-//         Err(Error::FilenameTooLong)
-//     }
-// }
-
-// fn sha256(bytes: impl AsRef<[u8]>) -> [u8; KEY_BYTES] {
-//     fn sha256_internal(bytes: &[u8]) -> [u8; KEY_BYTES] {
-//         #[inline(always)]
-//         fn wait(sha: &Sha256<Blocking>) {
-//             while sha.is_busy() {
-//                 spin_loop();
-//             }
-//         }
-
-//         let mut buf = [0; KEY_BYTES];
-//         let mut sha = Sha256::new();
-
-//         wait(&sha);
-//         sha.write_data(bytes).unwrap();
-
-//         wait(&sha);
-//         sha.process_buffer();
-
-//         wait(&sha);
-//         sha.finish(&mut buf).unwrap();
-
-//         buf
-//     }
-
-//     sha256_internal(bytes.as_ref())
-// }
-
-// #[derive(Clone)]
-// pub struct Filesystem(Arc<Mutex<CriticalSectionRawMutex, Inner>>);
-
-// impl Filesystem {
-//     async fn open_file(&self, name: &str) -> Result<(), Error> {
-//         check_name(name)?;
-
-//         let mut fs = self.0.lock().await;
-//         let meta_key = Key::from_name(name);
-//         let meta = fs.fetch_metadata(meta_key).await?;
-
-//         let file = File {
-//             chunks: meta.chunks,
-//             meta_key,
-//             chunk_key: meta.first_chunk,
-//             cursor: 0,
-//             name: String::from(meta.name.as_str()).into_boxed_str(),
-//             fs: self.clone(),
-//         };
-
-//         Ok(())
-//     }
-
-//     async fn fetch_chunk(&self, key: Key) -> Result<Chunk, Error> {
-//         self.0.lock().await.fetch_chunk(key).await
-//     }
-
-//     async fn write_chunk(&self, key: Key, chunk: Chunk) -> Result<(), Error> {
-//         let mut inner = self.0.lock().await;
-//         let node = Node::Chunk(chunk);
-//         inner.write_node(key, node).await
-//     }
-
-//     async fn fetch_metadata_by_name(&self, name: &str) -> Result<Metadata, Error> {
-//         check_name(name)?;
-
-//         self.0.lock().await.fetch_metadata_by_name(name).await
-//     }
-
-//     async fn fetch_metadata(&self, key: Key) -> Result<Metadata, Error> {
-//         self.0.lock().await.fetch_metadata(key).await
-//     }
-
-//     async fn write_metadata(&self, metadata: Metadata) -> Result<(), Error> {
-//         let mut inner = self.0.lock().await;
-//         let key = Key::from_name(&metadata.name);
-//         let node = Node::Metadata(metadata);
-
-//         inner.write_node(key, node).await
-//     }
-
-//     async fn chunk_exists(&self, key: Key) -> Result<bool, Error> {
-//         let mut fs = self.0.lock().await;
-//         match fs.fetch_node(key).await {
-//             Ok(Node::Chunk(_)) => Ok(true),
-//             Ok(_) => Err(Error::IsNotChunk),
-//             Err(Error::NotFound) => Ok(false),
-//             Err(e) => Err(e),
-//         }
-//     }
-
-//     async fn metadata_exists(&self, name: &str) -> Result<bool, Error> {
-//         check_name(name)?;
-
-//         let mut fs = self.0.lock().await;
-//         match fs.fetch_node(Key::from_name(name)).await {
-//             Ok(Node::Metadata(_)) => Ok(true),
-//             Ok(_) => Err(Error::IsNotChunk),
-//             Err(Error::NotFound) => Ok(false),
-//             Err(e) => Err(e),
-//         }
-//     }
-
-//     async fn create_file(&self, name: &str) -> Result<(), Error> {
-//         check_name(name)?;
-
-//         let mut fs = self.0.lock().await;
-//         let mut id = [0; CHUNK_ID_BYTES];
-//         fs.rng.read(&mut id);
-
-//         // crude protection to make sure that the RNG writes to every byte in the id.
-//         for byte in id.iter_mut() {
-//             loop {
-//                 if *byte == 0 {
-//                     let rand = fs.rng.random();
-//                     *byte = rand as u8;
-//                 } else {
-//                     break;
-//                 }
-//             }
-//         }
-
-//         let name = FixedString::from_str(name).unwrap();
-//         let first_chunk = Key::from_first_chunk(id);
-
-//         let meta = Metadata {
-//             chunks: 0,
-//             first_chunk,
-//             name,
-//         };
-
-//         Ok(())
-//     }
-// }
-
-// impl fmt::Debug for Filesystem {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         let guard = self.0.try_lock();
-
-//         let cache: &dyn fmt::Debug;
-//         let storage: &dyn fmt::Debug;
-//         let rng: &dyn fmt::Debug;
-
-//         match &guard {
-//             Ok(inner) => {
-//                 // there's no difference between Storage values.
-//                 cache = &inner.cache;
-//                 storage = &inner.storage;
-//                 rng = &"Rng";
-//             }
-//             Err(_) => {
-//                 cache = &"<locked>";
-//                 storage = &"<locked>";
-//                 rng = &"<locked>";
-//             }
-//         }
-
-//         f.debug_struct("Filesystem")
-//             .field("cache", cache)
-//             .field("storage", storage)
-//             .field("rng", rng)
-//             .finish()
-//     }
-// }
-
-// struct Inner {
-//     cache: Cache,
-//     storage: Storage,
-//     rng: Rng,
-// }
-
-// impl Inner {
-//     async fn fetch_node(&mut self, key: Key) -> Result<Node, Error> {
-//         let res = {
-//             let mut buf = AlignedArray([0; Node::POSTCARD_MAX_SIZE]);
-//             map::fetch_item(&mut self.storage, FS_RANGE, &mut self.cache, &mut buf, &key).await
-//         };
-
-//         match res {
-//             Ok(Some(node)) => Ok(node),
-//             Ok(None) => Err(Error::NotFound),
-//             Err(e) => Err(e.into()),
-//         }
-//     }
-
-//     async fn write_node(&mut self, key: Key, node: Node) -> Result<(), Error> {
-//         let mut buf = AlignedArray([0; Node::POSTCARD_MAX_SIZE]);
-
-//         map::store_item(
-//             &mut self.storage,
-//             FS_RANGE,
-//             &mut self.cache,
-//             &mut buf,
-//             &key,
-//             &node,
-//         )
-//         .await?;
-//         Ok(())
-//     }
-
-//     async fn fetch_metadata_by_name(&mut self, name: &str) -> Result<Metadata, Error> {
-//         self.fetch_node(Key::from_name(name))
-//             .await?
-//             .into_metadata()
-//             .ok_or(Error::IsNotMetadata)
-//     }
-
-//     async fn fetch_metadata(&mut self, key: Key) -> Result<Metadata, Error> {
-//         self.fetch_node(key)
-//             .await?
-//             .into_metadata()
-//             .ok_or(Error::IsNotMetadata)
-//     }
-
-//     async fn fetch_chunk(&mut self, key: Key) -> Result<Chunk, Error> {
-//         self.fetch_node(key)
-//             .await?
-//             .into_chunk()
-//             .ok_or(Error::IsNotMetadata)
-//     }
-
-//     // NOTE: This is incredibly fucking slow!!! It should be use extremely rarely.
-//     async fn delete_file(&mut self, name: &str) -> Result<(), Error> {
-//         check_name(name)?;
-
-//         let meta_key = Key::from_name(name);
-//         let meta = self.fetch_metadata(meta_key).await?;
-//         let mut buf = AlignedArray([0; Node::POSTCARD_MAX_SIZE]);
-//         let mut key = meta.first_chunk;
-
-//         while key.chunk() <= meta.chunks {
-//             map::remove_item(&mut self.storage, FS_RANGE, &mut self.cache, &mut buf, &key).await?;
-//             key.make_next_chunk();
-//         }
-
-//         map::remove_item(
-//             &mut self.storage,
-//             FS_RANGE,
-//             &mut self.cache,
-//             &mut buf,
-//             &meta_key,
-//         )
-//         .await?;
-
-//         Ok(())
-//     }
-// }
-
-// #[repr(transparent)]
-// #[derive(
-//     Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize, MaxSize,
-// )]
-// pub struct Key([u8; KEY_BYTES]);
-
-// impl Key {
-//     pub fn from_name(name: &str) -> Self {
-//         Self(sha256(name))
-//     }
-
-//     pub fn from_first_chunk(id: [u8; CHUNK_ID_BYTES]) -> Self {
-//         let mut buf = [0; KEY_BYTES];
-//         buf[size_of::<u16>()..].copy_from_slice(&id);
-
-//         Self(buf)
-//     }
-
-//     pub fn next_chunk(&self) -> Self {
-//         let mut new = *self;
-//         new.make_next_chunk();
-//         new
-//     }
-
-//     pub fn prev_chunk(&self) -> Self {
-//         let mut new = *self;
-//         new.make_prev_chunk();
-//         new
-//     }
-
-//     pub fn make_next_chunk(&mut self) {
-//         let next = self.chunk().wrapping_add(1);
-//         self.0[..U16_BYTES].copy_from_slice(&next.to_le_bytes());
-//     }
-
-//     pub fn make_prev_chunk(&mut self) {
-//         let next = self.chunk().wrapping_sub(1);
-//         self.0[..U16_BYTES].copy_from_slice(&next.to_le_bytes());
-//     }
-
-//     pub fn chunk(&self) -> u16 {
-//         let mut buf = [0; U16_BYTES];
-//         buf.copy_from_slice(&self.0[..U16_BYTES]);
-//         u16::from_le_bytes(buf)
-//     }
-// }
-
-// impl MapKey for Key {
-//     fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, MapSerError> {
-//         MapKey::serialize_into(&self.0, buffer)
-//     }
-
-//     fn deserialize_from(buffer: &[u8]) -> Result<(Self, usize), MapSerError> {
-//         let (key, len) = <[u8; KEY_BYTES] as MapKey>::deserialize_from(buffer)?;
-
-//         Ok((Self(key), len))
-//     }
-// }
-
-// #[derive(Clone, Debug)]
-// pub struct File {
-//     chunks: u16,
-//     meta_key: Key,
-//     chunk_key: Key,
-//     cursor: usize,
-//     name: Box<str>,
-//     fs: Filesystem,
-// }
-
-// impl File {
-//     pub fn name(&self) -> &str {
-//         &self.name
-//     }
-// }
-
-// impl IoErrorType for File {
-//     type Error = Error;
-// }
-
-// impl AsyncRead for File {
-//     async fn read(&mut self, mut buf: &mut [u8]) -> Result<usize, Self::Error> {
-//         let mut bytes_read = 0;
-
-//         while !buf.is_empty() && self.chunk_key.chunk() < self.chunks {
-//             let chunk = self.fs.fetch_chunk(self.chunk_key).await?;
-
-//             let chunk_len = chunk.0.len();
-//             let count = chunk_len.min(buf.len());
-//             let chunk_range = self.cursor..self.cursor + count;
-
-//             let (head, tail) = buf.split_at_mut(count);
-
-//             head.copy_from_slice(&chunk.0[chunk_range]);
-
-//             self.cursor += count;
-//             bytes_read += count;
-
-//             if self.cursor == chunk_len {
-//                 self.cursor = 0;
-//                 self.chunk_key.make_next_chunk();
-//             }
-
-//             buf = tail;
-//         }
-
-//         Ok(bytes_read)
-//     }
-// }
-
-// impl AsyncWrite for File {
-//     async fn write(&mut self, mut buf: &[u8]) -> Result<usize, Self::Error> {
-//         let mut bytes_written = 0;
-//         let mut metadata = self.fs.fetch_metadata(self.meta_key).await?;
-
-//         while !buf.is_empty() {
-//             let mut chunk = match self.fs.fetch_chunk(self.chunk_key).await {
-//                 Ok(c) => c,
-//                 Err(Error::NotFound) => {
-//                     if self.chunk_key.chunk() < u16::MAX {
-//                         metadata.chunks = metadata.chunks.wrapping_add(1);
-//                         self.chunk_key.make_next_chunk();
-//                         Chunk(FixedVec::new())
-//                     } else {
-//                         // This can't even happen on any ESP32 variant since it would require
-//                         // writing 255.75 MiB of data to a single file.
-//                         return Err(Error::DataTooLarge);
-//                     }
-//                 }
-//                 Err(e) => return Err(e),
-//             };
-
-//             let chunk_len = chunk.0.len();
-//             let count = buf.len().min(chunk.0.capacity() - chunk_len);
-
-//             let (head, tail) = buf.split_at(count);
-//             chunk.0[chunk_len..].copy_from_slice(head);
-//             buf = tail;
-
-//             self.cursor += count;
-//             bytes_written += count;
-
-//             if chunk.0.is_full() {
-//                 self.chunk_key.make_next_chunk();
-//                 self.cursor = 0;
-//             }
-//         }
-
-//         // updating metadata last ensures that even though writes may be "lost", reading or writing
-//         // to the file will not attempt to read or write those lost chunks.
-//         self.fs.write_metadata(metadata).await?;
-//         Ok(bytes_written)
-//     }
-// }
-
-// impl Seek for File {
-//     fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
-//         todo!()
-//     }
-// }
-
-// // impl AsyncSeek for File {
-    
-// // }
-
-// #[derive(Debug, Error)]
-// pub enum Error {
-//     #[error("filesystem corruption detected")]
-//     Corrupted,
-//     #[error("key was not found")]
-//     NotFound,
-//     #[error("file already exists")]
-//     AlreadyExists,
-//     #[error("name was invalid")]
-//     Invalidname,
-//     #[error("buffer was too small")]
-//     BufferTooSmall,
-//     #[error("data was too large to be stored")]
-//     DataTooLarge,
-//     #[error("operation was canceled")]
-//     Canceled,
-//     #[error("filesystem is full")]
-//     Full,
-//     #[error("invalid filesystem format")]
-//     InvalidFormat,
-//     #[error("filename is too long")]
-//     FilenameTooLong,
-//     #[error("filesystem node is not metadata")]
-//     IsNotMetadata,
-//     #[error("filesystem node is not a chunk")]
-//     IsNotChunk,
-//     #[error("attempt to access out of bounds")]
-//     OutOfBounds,
-//     #[error("attempted to write zero bytes to filesystem")]
-//     WriteZero,
-//     #[error("postcard error: {0}")]
-//     Postcard(postcard::Error),
-//     #[error("flash storage error: {0:?}")]
-//     Flash(EspFlashStorageError),
-// }
-
-// impl IoError for Error {
-//     fn kind(&self) -> IoErrorKind {
-//         use EspFlashStorageError as EfsError;
-
-//         match self {
-//             Self::Corrupted => IoErrorKind::InvalidData,
-//             Self::NotFound => IoErrorKind::NotFound,
-//             Self::AlreadyExists => IoErrorKind::AlreadyExists,
-//             Self::Invalidname => IoErrorKind::InvalidInput,
-//             Self::BufferTooSmall => IoErrorKind::InvalidInput,
-//             Self::DataTooLarge => IoErrorKind::InvalidInput,
-//             Self::Canceled => IoErrorKind::Interrupted,
-//             Self::Full => IoErrorKind::OutOfMemory,
-//             Self::InvalidFormat => IoErrorKind::InvalidData,
-//             Self::FilenameTooLong => IoErrorKind::InvalidInput,
-//             Self::IsNotMetadata => IoErrorKind::InvalidData,
-//             Self::IsNotChunk => IoErrorKind::InvalidData,
-//             Self::OutOfBounds => IoErrorKind::InvalidInput,
-//             Self::WriteZero => IoErrorKind::WriteZero,
-//             Self::Postcard(_) => IoErrorKind::Other,
-//             Self::Flash(e) => match e {
-//                 EfsError::IoError => IoErrorKind::Other,
-//                 EfsError::IoTimeout => IoErrorKind::TimedOut,
-//                 EfsError::CantUnlock => IoErrorKind::PermissionDenied,
-//                 EfsError::NotAligned => IoErrorKind::InvalidInput,
-//                 EfsError::OutOfBounds => IoErrorKind::Other,
-//                 EfsError::Other(_) => IoErrorKind::Other,
-//                 _ => unreachable!("flash storage error has a new variant"),
-//             },
-//         }
-//     }
-// }
-
-// impl From<postcard::Error> for Error {
-//     fn from(value: postcard::Error) -> Self {
-//         Self::Postcard(value)
-//     }
-// }
-
-// impl From<SeqStorageError<EspFlashStorageError>> for Error {
-//     fn from(value: SeqStorageError<EspFlashStorageError>) -> Self {
-//         match value {
-//             SeqStorageError::Storage { value } => Self::Flash(value),
-//             SeqStorageError::FullStorage => Self::Full,
-//             SeqStorageError::Corrupted {} => Self::Corrupted,
-//             SeqStorageError::BufferTooBig => Self::DataTooLarge,
-//             SeqStorageError::BufferTooSmall(_) => Self::BufferTooSmall,
-//             SeqStorageError::SerializationError(serialization_error) => {
-//                 Self::from(serialization_error)
-//             }
-//             SeqStorageError::ItemTooBig => Self::DataTooLarge,
-//             e => unimplemented!("unknown SeqStorageError {e:?}"),
-//         }
-//     }
-// }
-
-// impl From<MapSerError> for Error {
-//     fn from(value: MapSerError) -> Self {
-//         match value {
-//             MapSerError::BufferTooSmall => Self::BufferTooSmall,
-//             MapSerError::InvalidData => Self::DataTooLarge,
-//             MapSerError::InvalidFormat => Self::InvalidFormat,
-//             MapSerError::Custom(e) => Self::Postcard(i32_to_postcard_error(e)),
-//             e => unimplemented!("unknown MapSerError {e:?}"),
-//         }
-//     }
-// }
-
-// #[repr(C, align(4))]
-// struct AlignedArray<T, const N: usize>([T; N]);
-
-// impl<T, const N: usize> Deref for AlignedArray<T, N> {
-//     type Target = [T];
-
-//     fn deref(&self) -> &Self::Target {
-//         &self.0
-//     }
-// }
-
-// impl<T, const N: usize> DerefMut for AlignedArray<T, N> {
-//     fn deref_mut(&mut self) -> &mut Self::Target {
-//         &mut self.0
-//     }
-// }
+pub mod file;
+pub(crate) mod node;
+mod storage;
+
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::{
+    fmt,
+    hint::spin_loop,
+    mem::size_of,
+    ops::{Deref, DerefMut, Range},
+    str::{self, FromStr},
+};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embedded_io::{Error as IoError, ErrorKind as IoErrorKind, ErrorType as IoErrorType};
+use embedded_io_async::{Read as AsyncRead, Seek as AsyncSeek, SeekFrom, Write as AsyncWrite};
+use embedded_storage_async::nor_flash::ReadNorFlash as AsyncReadNorFlash;
+use esp_hal::{
+    rng::Rng,
+    sha::{Sha, Sha256},
+    Blocking,
+};
+use esp_storage::{FlashStorage as EspFlashStorage, FlashStorageError as EspFlashStorageError};
+use heapless::String as FixedString;
+use node::{
+    Chunk, Metadata, Node, CHUNK_DATA_SIZE, CRC_MISMATCH, FORMAT_VERSION, MAX_NAME_BYTES,
+    NODE_BUFFER_SIZE,
+};
+use postcard::experimental::max_size::MaxSize;
+use sequential_storage::map;
+use sequential_storage::Error as SeqStorageError;
+use sequential_storage::{
+    cache::KeyPointerCache,
+    map::{Key as MapKey, SerializationError as MapSerError},
+};
+use serde::{Deserialize, Serialize};
+use spin::Once;
+use storage::Storage;
+use thiserror::Error;
+
+pub const FS_START: u32 = 0x00110000;
+pub const FS_SIZE: u32 = 0x006f0000;
+pub const FS_RANGE: Range<u32> = FS_START..FS_START + FS_SIZE;
+/// The number of bytes the filesystem can hold, including the space used by its own bookkeeping.
+pub const FS_CAPACITY: usize = FS_SIZE as usize;
+pub const FS_PAGES: usize = FS_SIZE as usize / EspFlashStorage::SECTOR_SIZE as usize;
+pub const FS_CACHE_KEYS: usize = 32;
+pub const KEY_BYTES: usize = 32;
+pub const CHUNK_ID_BYTES: usize = KEY_BYTES - size_of::<u16>();
+const U16_BYTES: usize = size_of::<u16>();
+const PAGE_SIZE: usize = EspFlashStorage::SECTOR_SIZE as usize;
+const PAGE_MARKER_BYTES: usize = <Storage as AsyncReadNorFlash>::READ_SIZE;
+const ERASED_BYTE: u8 = 0xff;
+// Chunk ids never contain a zero byte and name keys are hashes, so no file can use this key.
+const FORMAT_KEY: Key = Key([0; KEY_BYTES]);
+
+type Cache = KeyPointerCache<FS_PAGES, Key, FS_CACHE_KEYS>;
+
+pub static FILESYSTEM: Once<Filesystem> = Once::new();
+
+/// Returns the filesystem, or `None` if it hasn't been initialized yet.
+pub fn filesystem() -> Option<&'static Filesystem> {
+    FILESYSTEM.get()
+}
+
+// Stands in for postcard errors which don't have a code, since `postcard::Error` is non-exhaustive.
+// It's turned back into `Error::InvalidFormat`.
+const UNKNOWN_POSTCARD_ERROR: i32 = i32::MAX;
+
+fn postcard_error_to_i32(error: postcard::Error) -> i32 {
+    use postcard::Error as PcError;
+
+    match error {
+        PcError::WontImplement => 0,
+        PcError::NotYetImplemented => 1,
+        PcError::SerializeBufferFull => 2,
+        PcError::SerializeSeqLengthUnknown => 3,
+        PcError::DeserializeUnexpectedEnd => 4,
+        PcError::DeserializeBadVarint => 5,
+        PcError::DeserializeBadBool => 6,
+        PcError::DeserializeBadChar => 7,
+        PcError::DeserializeBadUtf8 => 8,
+        PcError::DeserializeBadOption => 9,
+        PcError::DeserializeBadEnum => 10,
+        PcError::DeserializeBadEncoding => 11,
+        PcError::DeserializeBadCrc => 12,
+        PcError::SerdeSerCustom => 13,
+        PcError::SerdeDeCustom => 14,
+        PcError::CollectStrError => 15,
+        _ => UNKNOWN_POSTCARD_ERROR,
+    }
+}
+
+fn i32_to_postcard_error(error: i32) -> Option<postcard::Error> {
+    use postcard::Error as PcError;
+
+    let error = match error {
+        0 => PcError::WontImplement,
+        1 => PcError::NotYetImplemented,
+        2 => PcError::SerializeBufferFull,
+        3 => PcError::SerializeSeqLengthUnknown,
+        4 => PcError::DeserializeUnexpectedEnd,
+        5 => PcError::DeserializeBadVarint,
+        6 => PcError::DeserializeBadBool,
+        7 => PcError::DeserializeBadChar,
+        8 => PcError::DeserializeBadUtf8,
+        9 => PcError::DeserializeBadOption,
+        10 => PcError::DeserializeBadEnum,
+        11 => PcError::DeserializeBadEncoding,
+        12 => PcError::DeserializeBadCrc,
+        13 => PcError::SerdeSerCustom,
+        14 => PcError::SerdeDeCustom,
+        15 => PcError::CollectStrError,
+        _ => return None,
+    };
+
+    Some(error)
+}
+
+fn check_name(name: &str) -> Result<(), Error> {
+    if name.len() <= MAX_NAME_BYTES {
+        Ok(())
+    } else {
+        Err(Error::FilenameTooLong)
+    }
+}
+
+fn sha256(bytes: impl AsRef<[u8]>) -> [u8; KEY_BYTES] {
+    let mut hasher = Hasher::new();
+    hasher.update(bytes.as_ref());
+    hasher.finish()
+}
+
+// There's only one SHA peripheral, and starting a digest resets it, so a `Hasher` must only be
+// used while holding the filesystem lock. Everything that hashes goes through the filesystem, so
+// holding the lock means nothing else can use the peripheral until the digest is finished.
+//
+// The peripheral's operations only fail while it's busy, so they're retried until they succeed.
+struct Hasher(Sha256<Blocking>);
+
+impl Hasher {
+    fn new() -> Self {
+        Self(Sha256::new())
+    }
+
+    #[inline(always)]
+    fn wait(&self) {
+        while self.0.is_busy() {
+            spin_loop();
+        }
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            self.wait();
+
+            if let Ok(remaining) = self.0.write_data(bytes) {
+                bytes = remaining;
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; KEY_BYTES] {
+        let mut buf = [0; KEY_BYTES];
+
+        self.wait();
+        self.0.process_buffer();
+
+        loop {
+            self.wait();
+
+            if self.0.finish(&mut buf).is_ok() {
+                break buf;
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Filesystem(Arc<Mutex<CriticalSectionRawMutex, Inner>>);
+
+impl Filesystem {
+    pub fn new(storage: EspFlashStorage, rng: Rng) -> Self {
+        let inner = Inner {
+            cache: Cache::new(),
+            storage: Storage::new(storage),
+            rng,
+            node_writes: 0,
+        };
+
+        Self(Arc::new(Mutex::new(inner)))
+    }
+
+    /// Checks that the flash was formatted for this firmware's [`FORMAT_VERSION`], erasing it if
+    /// it wasn't. Files written in another format can't be read, so they're lost. This has to be
+    /// called before the filesystem is used.
+    pub async fn mount(&self) -> Result<(), Error> {
+        let mut fs = self.0.lock().await;
+
+        let erase = match fs.fetch_node(FORMAT_KEY).await {
+            Ok(Node::Format(FORMAT_VERSION)) => return Ok(()),
+            Err(Error::Flash(e)) => return Err(Error::Flash(e)),
+            // nothing has been written yet, so there's nothing to erase.
+            Err(Error::NotFound) => fs.space_used().await? > 0,
+            _ => true,
+        };
+
+        if erase {
+            log::warn!("filesystem isn't in format version {FORMAT_VERSION}, erasing it");
+            fs.erase().await?;
+        }
+
+        fs.write_node(FORMAT_KEY, Node::Format(FORMAT_VERSION))
+            .await
+    }
+
+    pub async fn open_file(&self, name: &str) -> Result<File, Error> {
+        check_name(name)?;
+
+        let mut fs = self.0.lock().await;
+        let meta_key = Key::from_name(name);
+        let meta = fs.fetch_metadata(meta_key).await?;
+
+        Ok(File::new(meta_key, meta, self.clone()))
+    }
+
+    /// Hashes the contents of a file with SHA-256, chunk by chunk, so a file can be checked
+    /// against its source after being written. The filesystem is locked until the whole file has
+    /// been hashed.
+    pub async fn hash_file(&self, name: &str) -> Result<[u8; KEY_BYTES], Error> {
+        check_name(name)?;
+
+        let mut fs = self.0.lock().await;
+        let meta = fs.fetch_metadata_by_name(name).await?;
+        let mut chunk_key = meta.first_chunk;
+        let mut hasher = Hasher::new();
+
+        while chunk_key.chunk() < meta.chunks {
+            let chunk = fs.fetch_chunk(chunk_key).await?;
+            hasher.update(&chunk.0);
+
+            chunk_key.make_next_chunk();
+        }
+
+        Ok(hasher.finish())
+    }
+
+    async fn fetch_chunk(&self, key: Key) -> Result<Chunk, Error> {
+        self.0.lock().await.fetch_chunk(key).await
+    }
+
+    async fn write_chunk(&self, key: Key, chunk: Chunk) -> Result<(), Error> {
+        let mut inner = self.0.lock().await;
+        let node = Node::Chunk(chunk);
+        inner.write_node(key, node).await
+    }
+
+    async fn remove_chunk(&self, key: Key) -> Result<(), Error> {
+        self.0.lock().await.remove_node(key).await
+    }
+
+    async fn fetch_metadata_by_name(&self, name: &str) -> Result<Metadata, Error> {
+        check_name(name)?;
+
+        self.0.lock().await.fetch_metadata_by_name(name).await
+    }
+
+    async fn fetch_metadata(&self, key: Key) -> Result<Metadata, Error> {
+        self.0.lock().await.fetch_metadata(key).await
+    }
+
+    async fn write_metadata(&self, metadata: Metadata) -> Result<(), Error> {
+        let mut inner = self.0.lock().await;
+        let key = Key::from_name(&metadata.name);
+        let node = Node::Metadata(metadata);
+
+        inner.write_node(key, node).await
+    }
+
+    async fn chunk_exists(&self, key: Key) -> Result<bool, Error> {
+        let mut fs = self.0.lock().await;
+        match fs.fetch_node(key).await {
+            Ok(Node::Chunk(_)) => Ok(true),
+            Ok(_) => Err(Error::IsNotChunk),
+            Err(Error::NotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn metadata_exists(&self, name: &str) -> Result<bool, Error> {
+        check_name(name)?;
+
+        let mut fs = self.0.lock().await;
+        match fs.fetch_node(Key::from_name(name)).await {
+            Ok(Node::Metadata(_)) => Ok(true),
+            Ok(_) => Err(Error::IsNotChunk),
+            Err(Error::NotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates an empty file, returning [`Error::AlreadyExists`] if there's already a file with
+    /// the same name.
+    pub async fn create_file(&self, name: &str) -> Result<File, Error> {
+        check_name(name)?;
+
+        let mut fs = self.0.lock().await;
+        let meta_key = Key::from_name(name);
+
+        match fs.fetch_node(meta_key).await {
+            Ok(_) => return Err(Error::AlreadyExists),
+            Err(Error::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut id = [0; CHUNK_ID_BYTES];
+        fs.rng.read(&mut id);
+
+        // crude protection to make sure that the RNG writes to every byte in the id.
+        for byte in id.iter_mut() {
+            loop {
+                if *byte == 0 {
+                    let rand = fs.rng.random();
+                    *byte = rand as u8;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        let name = FixedString::from_str(name).unwrap();
+        let first_chunk = Key::from_first_chunk(id);
+
+        let meta = Metadata {
+            chunks: 0,
+            first_chunk,
+            name,
+        };
+
+        fs.write_node(meta_key, Node::Metadata(meta.clone()))
+            .await?;
+
+        Ok(File::new(meta_key, meta, self.clone()))
+    }
+
+    /// Returns the size of a file in bytes.
+    pub async fn file_size(&self, name: &str) -> Result<u64, Error> {
+        let meta = self.fetch_metadata_by_name(name).await?;
+
+        self.size_of(&meta).await
+    }
+
+    async fn size_of(&self, meta: &Metadata) -> Result<u64, Error> {
+        let Some(last) = meta.chunks.checked_sub(1) else {
+            return Ok(0);
+        };
+
+        // every chunk before the last one is full.
+        let last_chunk = self.fetch_chunk(meta.first_chunk.with_chunk(last)).await?;
+
+        Ok(last as u64 * CHUNK_DATA_SIZE as u64 + last_chunk.0.len() as u64)
+    }
+
+    /// Renames a file, returning [`Error::AlreadyExists`] if there's already a file called `new`.
+    /// Only the metadata moves, since the keys of the chunks come from the file's random id rather
+    /// than its name.
+    pub async fn rename(&self, old: &str, new: &str) -> Result<(), Error> {
+        check_name(old)?;
+        check_name(new)?;
+
+        let mut fs = self.0.lock().await;
+        let old_key = Key::from_name(old);
+        let new_key = Key::from_name(new);
+        let mut meta = fs.fetch_metadata(old_key).await?;
+
+        if old_key == new_key {
+            return Ok(());
+        }
+
+        match fs.fetch_node(new_key).await {
+            Ok(_) => return Err(Error::AlreadyExists),
+            Err(Error::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        meta.name = FixedString::from_str(new).unwrap();
+
+        // writing the new metadata first means an interrupted rename leaves the file under both
+        // names instead of neither.
+        fs.write_node(new_key, Node::Metadata(meta)).await?;
+        fs.remove_node(old_key).await
+    }
+
+    pub fn capacity(&self) -> usize {
+        FS_CAPACITY
+    }
+
+    /// Returns the number of bytes in flash pages which are in use. This includes space taken by
+    /// old versions of nodes which haven't been garbage collected yet, so it's an upper bound on
+    /// the size of the files, but it's what decides when [`Error::Full`] is returned.
+    pub async fn space_used(&self) -> Result<usize, Error> {
+        self.0.lock().await.space_used().await
+    }
+
+    /// Returns the number of bytes in flash pages which haven't been used yet.
+    pub async fn free_space(&self) -> Result<usize, Error> {
+        Ok(FS_CAPACITY - self.space_used().await?)
+    }
+
+    /// Returns how many nodes have been written since boot. Every node write uses up flash, so
+    /// this is a measure of how much wear the filesystem is causing.
+    pub async fn node_writes(&self) -> usize {
+        self.0.lock().await.node_writes
+    }
+
+    pub async fn delete_file(&self, name: &str) -> Result<(), Error> {
+        self.0.lock().await.delete_file(name).await
+    }
+}
+
+impl fmt::Debug for Filesystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guard = self.0.try_lock();
+
+        let cache: &dyn fmt::Debug;
+        let storage: &dyn fmt::Debug;
+        let rng: &dyn fmt::Debug;
+
+        match &guard {
+            Ok(inner) => {
+                // there's no difference between Storage values.
+                cache = &inner.cache;
+                storage = &inner.storage;
+                rng = &"Rng";
+            }
+            Err(_) => {
+                cache = &"<locked>";
+                storage = &"<locked>";
+                rng = &"<locked>";
+            }
+        }
+
+        f.debug_struct("Filesystem")
+            .field("cache", cache)
+            .field("storage", storage)
+            .field("rng", rng)
+            .finish()
+    }
+}
+
+struct Inner {
+    cache: Cache,
+    storage: Storage,
+    rng: Rng,
+    node_writes: usize,
+}
+
+impl Inner {
+    async fn fetch_node(&mut self, key: Key) -> Result<Node, Error> {
+        let res = {
+            let mut buf = AlignedArray([0; NODE_BUFFER_SIZE]);
+            map::fetch_item(&mut self.storage, FS_RANGE, &mut self.cache, &mut buf, &key).await
+        };
+
+        match res {
+            Ok(Some(node)) => Ok(node),
+            Ok(None) => Err(Error::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write_node(&mut self, key: Key, node: Node) -> Result<(), Error> {
+        let mut buf = AlignedArray([0; NODE_BUFFER_SIZE]);
+
+        map::store_item(
+            &mut self.storage,
+            FS_RANGE,
+            &mut self.cache,
+            &mut buf,
+            &key,
+            &node,
+        )
+        .await?;
+
+        self.node_writes += 1;
+        Ok(())
+    }
+
+    // sequential_storage marks the start of a page as soon as it writes anything to it, so a page
+    // whose first word is still erased is empty.
+    async fn space_used(&mut self) -> Result<usize, Error> {
+        let mut used = 0;
+        let mut marker = AlignedArray([0; PAGE_MARKER_BYTES]);
+
+        for page in 0..FS_PAGES {
+            let offset = FS_START + (page * PAGE_SIZE) as u32;
+
+            AsyncReadNorFlash::read(&mut self.storage, offset, &mut marker)
+                .await
+                .map_err(Error::Flash)?;
+
+            if marker.iter().any(|&byte| byte != ERASED_BYTE) {
+                used += PAGE_SIZE;
+            }
+        }
+
+        Ok(used)
+    }
+
+    async fn erase(&mut self) -> Result<(), Error> {
+        sequential_storage::erase_all(&mut self.storage, FS_RANGE).await?;
+
+        // the cache points at items which were just erased.
+        self.cache = Cache::new();
+        Ok(())
+    }
+
+    async fn remove_node(&mut self, key: Key) -> Result<(), Error> {
+        let mut buf = AlignedArray([0; NODE_BUFFER_SIZE]);
+
+        map::remove_item(&mut self.storage, FS_RANGE, &mut self.cache, &mut buf, &key).await?;
+        Ok(())
+    }
+
+    async fn fetch_metadata_by_name(&mut self, name: &str) -> Result<Metadata, Error> {
+        self.fetch_node(Key::from_name(name))
+            .await?
+            .into_metadata()
+            .ok_or(Error::IsNotMetadata)
+    }
+
+    async fn fetch_metadata(&mut self, key: Key) -> Result<Metadata, Error> {
+        self.fetch_node(key)
+            .await?
+            .into_metadata()
+            .ok_or(Error::IsNotMetadata)
+    }
+
+    async fn fetch_chunk(&mut self, key: Key) -> Result<Chunk, Error> {
+        self.fetch_node(key)
+            .await?
+            .into_chunk()
+            .ok_or(Error::IsNotMetadata)
+    }
+
+    // NOTE: This is incredibly fucking slow!!! It should be use extremely rarely.
+    async fn delete_file(&mut self, name: &str) -> Result<(), Error> {
+        check_name(name)?;
+
+        let meta_key = Key::from_name(name);
+        let meta = self.fetch_metadata(meta_key).await?;
+        let mut buf = AlignedArray([0; NODE_BUFFER_SIZE]);
+        let mut key = meta.first_chunk;
+
+        while key.chunk() < meta.chunks {
+            map::remove_item(&mut self.storage, FS_RANGE, &mut self.cache, &mut buf, &key).await?;
+            key.make_next_chunk();
+        }
+
+        map::remove_item(
+            &mut self.storage,
+            FS_RANGE,
+            &mut self.cache,
+            &mut buf,
+            &meta_key,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[repr(transparent)]
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize, MaxSize,
+)]
+pub struct Key([u8; KEY_BYTES]);
+
+impl Key {
+    pub fn from_name(name: &str) -> Self {
+        Self(sha256(name))
+    }
+
+    pub fn from_first_chunk(id: [u8; CHUNK_ID_BYTES]) -> Self {
+        let mut buf = [0; KEY_BYTES];
+        buf[size_of::<u16>()..].copy_from_slice(&id);
+
+        Self(buf)
+    }
+
+    pub fn next_chunk(&self) -> Self {
+        let mut new = *self;
+        new.make_next_chunk();
+        new
+    }
+
+    pub fn prev_chunk(&self) -> Self {
+        let mut new = *self;
+        new.make_prev_chunk();
+        new
+    }
+
+    /// Returns the key for chunk `index` of the same file.
+    pub fn with_chunk(&self, index: u16) -> Self {
+        let mut new = *self;
+        new.0[..U16_BYTES].copy_from_slice(&index.to_le_bytes());
+        new
+    }
+
+    pub fn make_next_chunk(&mut self) {
+        let next = self.chunk().wrapping_add(1);
+        self.0[..U16_BYTES].copy_from_slice(&next.to_le_bytes());
+    }
+
+    pub fn make_prev_chunk(&mut self) {
+        let next = self.chunk().wrapping_sub(1);
+        self.0[..U16_BYTES].copy_from_slice(&next.to_le_bytes());
+    }
+
+    pub fn chunk(&self) -> u16 {
+        let mut buf = [0; U16_BYTES];
+        buf.copy_from_slice(&self.0[..U16_BYTES]);
+        u16::from_le_bytes(buf)
+    }
+}
+
+impl MapKey for Key {
+    fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, MapSerError> {
+        MapKey::serialize_into(&self.0, buffer)
+    }
+
+    fn deserialize_from(buffer: &[u8]) -> Result<(Self, usize), MapSerError> {
+        let (key, len) = <[u8; KEY_BYTES] as MapKey>::deserialize_from(buffer)?;
+
+        Ok((Self(key), len))
+    }
+}
+
+/// A handle to a file.
+///
+/// Writes are buffered a chunk at a time: a chunk is only written to flash once it's full, the
+/// cursor moves to another chunk, or the file is flushed. Data which hasn't been flushed isn't
+/// visible through other handles, and is lost if the handle is dropped, so call
+/// [`flush`](AsyncWrite::flush) before closing a file or whenever the data needs to be durable.
+#[derive(Clone, Debug)]
+pub struct File {
+    chunks: u16,
+    meta_key: Key,
+    chunk_key: Key,
+    cursor: usize,
+    /// The chunk under the cursor, if it's been written to since it was last saved.
+    dirty: Option<Chunk>,
+    name: Box<str>,
+    fs: Filesystem,
+}
+
+impl File {
+    fn new(meta_key: Key, meta: Metadata, fs: Filesystem) -> Self {
+        Self {
+            chunks: meta.chunks,
+            meta_key,
+            chunk_key: meta.first_chunk,
+            cursor: 0,
+            dirty: None,
+            name: String::from(meta.name.as_str()).into_boxed_str(),
+            fs,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the position of the cursor in the file.
+    pub fn position(&self) -> u64 {
+        self.chunk_key.chunk() as u64 * CHUNK_DATA_SIZE as u64 + self.cursor as u64
+    }
+
+    /// Shrinks the file to `len` bytes, returning [`Error::OutOfBounds`] if it's shorter than
+    /// that. If the cursor was past the new end, it's moved to the end.
+    pub async fn truncate(&mut self, len: u64) -> Result<(), Error> {
+        self.flush().await?;
+
+        let mut meta = self.fs.fetch_metadata(self.meta_key).await?;
+        let size = self.fs.size_of(&meta).await?;
+
+        if len > size {
+            return Err(Error::OutOfBounds);
+        }
+
+        let first_chunk = meta.first_chunk;
+        let old_chunks = meta.chunks;
+        let chunks = len.div_ceil(CHUNK_DATA_SIZE as u64) as u16;
+        let tail = (len % CHUNK_DATA_SIZE as u64) as usize;
+
+        // the new last chunk only needs rewriting if the file now ends partway through it.
+        if tail != 0 {
+            let key = first_chunk.with_chunk(chunks - 1);
+            let mut chunk = self.fs.fetch_chunk(key).await?;
+
+            chunk.0.truncate(tail);
+            self.fs.write_chunk(key, chunk).await?;
+        }
+
+        // the metadata is updated before the chunks past the end are removed, so an interrupted
+        // truncate never leaves the file referring to chunks which don't exist.
+        meta.chunks = chunks;
+        self.fs.write_metadata(meta).await?;
+        self.chunks = chunks;
+
+        for index in chunks..old_chunks {
+            self.fs.remove_chunk(first_chunk.with_chunk(index)).await?;
+        }
+
+        if self.position() > len {
+            self.chunk_key = first_chunk.with_chunk((len / CHUNK_DATA_SIZE as u64) as u16);
+            self.cursor = tail;
+        }
+
+        Ok(())
+    }
+
+    // Loads the chunk under the cursor so it can be written to, or starts a new one if the cursor
+    // is at the end of the file.
+    async fn load_chunk(&mut self) -> Result<Chunk, Error> {
+        let index = self.chunk_key.chunk();
+        self.chunks = self.fs.fetch_metadata(self.meta_key).await?.chunks;
+
+        if index < self.chunks {
+            self.fs.fetch_chunk(self.chunk_key).await
+        } else if index == u16::MAX {
+            // This can't even happen on any ESP32 variant since it would require writing
+            // 255.75 MiB of data to a single file.
+            Err(Error::DataTooLarge)
+        } else if index == self.chunks {
+            Ok(Chunk::default())
+        } else {
+            // the file was truncated through another handle, and creating this chunk would leave a
+            // hole before it.
+            Err(Error::OutOfBounds)
+        }
+    }
+
+    // Writes the chunk under the cursor to flash, and adds it to the metadata if it's new.
+    async fn save_chunk(&mut self, chunk: Chunk) -> Result<(), Error> {
+        let index = self.chunk_key.chunk();
+        let mut metadata = self.fs.fetch_metadata(self.meta_key).await?;
+
+        if index > metadata.chunks {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.fs.write_chunk(self.chunk_key, chunk).await?;
+
+        // updating metadata last ensures that even though writes may be "lost", reading or
+        // writing to the file will not attempt to read or write those lost chunks.
+        if index == metadata.chunks {
+            metadata.chunks = index + 1;
+            self.fs.write_metadata(metadata).await?;
+        }
+
+        self.chunks = self.chunks.max(index + 1);
+        Ok(())
+    }
+
+    // Saves the dirty chunk, if there is one, keeping it dirty if that fails. The cursor only moves
+    // past a full chunk once it's been saved.
+    async fn save_dirty(&mut self) -> Result<(), Error> {
+        if let Some(chunk) = self.dirty.take() {
+            if let Err(e) = self.save_chunk(chunk.clone()).await {
+                self.dirty = Some(chunk);
+                return Err(e);
+            }
+        }
+
+        if self.cursor == CHUNK_DATA_SIZE {
+            self.chunk_key.make_next_chunk();
+            self.cursor = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the rest of the file into a `Vec`.
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        let mut buf = [0; 256];
+
+        loop {
+            match self.read(&mut buf).await? {
+                0 => break Ok(bytes),
+                len => bytes.extend_from_slice(&buf[..len]),
+            }
+        }
+    }
+}
+
+impl IoErrorType for File {
+    type Error = Error;
+}
+
+// Every chunk except the last one is full, so the position in the file is always
+// `chunk_key.chunk() * CHUNK_DATA_SIZE + cursor`. A read stops at the end of a partial chunk, since
+// that can only be the last one, or once the cursor moves past the last chunk.
+impl AsyncRead for File {
+    async fn read(&mut self, mut buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let mut bytes_read = 0;
+
+        self.flush().await?;
+
+        // the file may have grown or shrunk through another handle since the last read.
+        self.chunks = self.fs.fetch_metadata(self.meta_key).await?.chunks;
+
+        while !buf.is_empty() && self.chunk_key.chunk() < self.chunks {
+            let chunk = self.fs.fetch_chunk(self.chunk_key).await?;
+            let available = chunk.0.len().saturating_sub(self.cursor);
+
+            // the end of the last chunk is the end of the file.
+            if available == 0 {
+                break;
+            }
+
+            let count = available.min(buf.len());
+            let chunk_range = self.cursor..self.cursor + count;
+
+            let (head, tail) = buf.split_at_mut(count);
+
+            head.copy_from_slice(&chunk.0[chunk_range]);
+
+            self.cursor += count;
+            bytes_read += count;
+
+            if self.cursor == CHUNK_DATA_SIZE {
+                self.cursor = 0;
+                self.chunk_key.make_next_chunk();
+            }
+
+            buf = tail;
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl AsyncWrite for File {
+    async fn write(&mut self, mut buf: &[u8]) -> Result<usize, Self::Error> {
+        let mut bytes_written = 0;
+
+        while !buf.is_empty() {
+            // the cursor is only left at the end of a chunk if saving it failed.
+            if self.cursor == CHUNK_DATA_SIZE {
+                self.save_dirty().await?;
+            }
+
+            let mut chunk = match self.dirty.take() {
+                Some(chunk) => chunk,
+                None => self.load_chunk().await?,
+            };
+
+            // the file may have been truncated through another handle, leaving the cursor past
+            // the end of its chunk.
+            if self.cursor > chunk.0.len() {
+                return Err(Error::OutOfBounds);
+            }
+
+            let count = buf.len().min(CHUNK_DATA_SIZE - self.cursor);
+            let (head, tail) = buf.split_at(count);
+
+            // overwrite whatever is already in the chunk past the cursor, then append the rest.
+            let overlap = (chunk.0.len() - self.cursor).min(count);
+            chunk.0[self.cursor..self.cursor + overlap].copy_from_slice(&head[..overlap]);
+            chunk
+                .0
+                .extend_from_slice(&head[overlap..])
+                .map_err(|_| Error::DataTooLarge)?;
+
+            buf = tail;
+            self.cursor += count;
+            bytes_written += count;
+            self.dirty = Some(chunk);
+
+            // a full chunk won't be written to again until the cursor comes back to it, so it's
+            // saved right away instead of being held in memory. If that fails, the bytes are still
+            // in the dirty chunk, so they count as written and the error comes from the next write
+            // or flush when it's tried again.
+            if self.cursor == CHUNK_DATA_SIZE && self.save_dirty().await.is_err() {
+                break;
+            }
+        }
+
+        Ok(bytes_written)
+    }
+
+    /// Saves the chunk under the cursor if it's been written to. Once this returns, everything
+    /// written through this handle is on flash.
+    ///
+    /// If saving fails, the chunk is kept so flushing can be retried.
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.save_dirty().await
+    }
+}
+
+// Files can't have holes, so seeking is limited to the existing contents. Seeking to the end puts
+// the cursor at the start of the next chunk if the last one is full, the same as reading to the
+// end does.
+impl AsyncSeek for File {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.flush().await?;
+
+        let meta = self.fs.fetch_metadata(self.meta_key).await?;
+        let size = self.fs.size_of(&meta).await?;
+        let position = self.position();
+
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => position.checked_add_signed(offset),
+        }
+        .filter(|&target| target <= size)
+        .ok_or(Error::OutOfBounds)?;
+
+        self.chunks = meta.chunks;
+        self.chunk_key = meta
+            .first_chunk
+            .with_chunk((target / CHUNK_DATA_SIZE as u64) as u16);
+        self.cursor = (target % CHUNK_DATA_SIZE as u64) as usize;
+
+        Ok(target)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("filesystem corruption detected")]
+    Corrupted,
+    #[error("key was not found")]
+    NotFound,
+    #[error("file already exists")]
+    AlreadyExists,
+    #[error("name was invalid")]
+    Invalidname,
+    #[error("buffer was too small")]
+    BufferTooSmall,
+    #[error("data was too large to be stored")]
+    DataTooLarge,
+    #[error("value was too large to fit in a flash page")]
+    ValueTooLarge,
+    #[error("read buffer was too small, {0} bytes are required")]
+    ReadBufferTooSmall(usize),
+    #[error("operation was canceled")]
+    Canceled,
+    #[error("filesystem is full")]
+    Full,
+    #[error("invalid filesystem format")]
+    InvalidFormat,
+    #[error("filename is too long")]
+    FilenameTooLong,
+    #[error("filesystem node is not metadata")]
+    IsNotMetadata,
+    #[error("filesystem node is not a chunk")]
+    IsNotChunk,
+    #[error("attempt to access out of bounds")]
+    OutOfBounds,
+    #[error("attempted to write zero bytes to filesystem")]
+    WriteZero,
+    #[error("postcard error: {0}")]
+    Postcard(postcard::Error),
+    #[error("flash storage error: {0:?}")]
+    Flash(EspFlashStorageError),
+}
+
+impl IoError for Error {
+    fn kind(&self) -> IoErrorKind {
+        use EspFlashStorageError as EfsError;
+
+        match self {
+            Self::Corrupted => IoErrorKind::InvalidData,
+            Self::NotFound => IoErrorKind::NotFound,
+            Self::AlreadyExists => IoErrorKind::AlreadyExists,
+            Self::Invalidname => IoErrorKind::InvalidInput,
+            Self::BufferTooSmall => IoErrorKind::InvalidInput,
+            Self::DataTooLarge => IoErrorKind::InvalidInput,
+            Self::ValueTooLarge => IoErrorKind::InvalidInput,
+            Self::ReadBufferTooSmall(_) => IoErrorKind::InvalidInput,
+            Self::Canceled => IoErrorKind::Interrupted,
+            Self::Full => IoErrorKind::OutOfMemory,
+            Self::InvalidFormat => IoErrorKind::InvalidData,
+            Self::FilenameTooLong => IoErrorKind::InvalidInput,
+            Self::IsNotMetadata => IoErrorKind::InvalidData,
+            Self::IsNotChunk => IoErrorKind::InvalidData,
+            Self::OutOfBounds => IoErrorKind::InvalidInput,
+            Self::WriteZero => IoErrorKind::WriteZero,
+            Self::Postcard(_) => IoErrorKind::Other,
+            Self::Flash(e) => match e {
+                EfsError::IoError => IoErrorKind::Other,
+                EfsError::IoTimeout => IoErrorKind::TimedOut,
+                EfsError::CantUnlock => IoErrorKind::PermissionDenied,
+                EfsError::NotAligned => IoErrorKind::InvalidInput,
+                EfsError::OutOfBounds => IoErrorKind::Other,
+                EfsError::Other(_) => IoErrorKind::Other,
+                // the error is non-exhaustive, and variants added later are still flash errors.
+                _ => IoErrorKind::Other,
+            },
+        }
+    }
+}
+
+impl From<postcard::Error> for Error {
+    fn from(value: postcard::Error) -> Self {
+        Self::Postcard(value)
+    }
+}
+
+impl From<SeqStorageError<EspFlashStorageError>> for Error {
+    fn from(value: SeqStorageError<EspFlashStorageError>) -> Self {
+        match value {
+            SeqStorageError::Storage { value } => Self::Flash(value),
+            SeqStorageError::FullStorage => Self::Full,
+            SeqStorageError::Corrupted {} => Self::Corrupted,
+            // The provided buffer was larger than sequential_storage can address.
+            SeqStorageError::BufferTooBig => Self::DataTooLarge,
+            // The buffer an item was read into couldn't hold it.
+            SeqStorageError::BufferTooSmall(needed) => Self::ReadBufferTooSmall(needed),
+            SeqStorageError::SerializationError(serialization_error) => {
+                Self::from(serialization_error)
+            }
+            // The item itself can't fit into a single flash page.
+            SeqStorageError::ItemTooBig => Self::ValueTooLarge,
+            // `SeqStorageError` is non-exhaustive, and anything new is most likely to be about
+            // what's on flash.
+            _ => Self::Corrupted,
+        }
+    }
+}
+
+impl From<MapSerError> for Error {
+    fn from(value: MapSerError) -> Self {
+        match value {
+            MapSerError::BufferTooSmall => Self::BufferTooSmall,
+            MapSerError::InvalidData => Self::DataTooLarge,
+            MapSerError::InvalidFormat => Self::InvalidFormat,
+            MapSerError::Custom(CRC_MISMATCH) => Self::Corrupted,
+            MapSerError::Custom(e) => {
+                i32_to_postcard_error(e).map_or(Self::InvalidFormat, Self::Postcard)
+            }
+            _ => Self::InvalidFormat,
+        }
+    }
+}
+
+#[repr(C, align(4))]
+struct AlignedArray<T, const N: usize>([T; N]);
+
+impl<T, const N: usize> Deref for AlignedArray<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, const N: usize> DerefMut for AlignedArray<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embassy_futures::block_on;
+
+    fn filesystem() -> Filesystem {
+        Filesystem::new(EspFlashStorage::new(), Rng::new(1))
+    }
+
+    // 251 is prime, so the pattern doesn't line up with chunk boundaries.
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn small_writes_are_saved_on_flush() {
+        block_on(async {
+            let fs = filesystem();
+            let mut file = fs.create_file("small").await.unwrap();
+            let before = fs.node_writes().await;
+
+            for piece in pattern(1024).chunks(16) {
+                file.write_all(piece).await.unwrap();
+            }
+
+            assert_eq!(fs.node_writes().await, before);
+
+            // the chunk is new, so the metadata is written too.
+            file.flush().await.unwrap();
+            assert_eq!(fs.node_writes().await, before + 2);
+
+            file.flush().await.unwrap();
+            assert_eq!(fs.node_writes().await, before + 2);
+
+            for piece in pattern(1024).chunks(16) {
+                file.write_all(piece).await.unwrap();
+            }
+
+            file.flush().await.unwrap();
+            assert_eq!(fs.node_writes().await, before + 3);
+        });
+    }
+
+    #[test]
+    fn full_chunks_are_saved_as_they_fill() {
+        block_on(async {
+            let fs = filesystem();
+            let mut file = fs.create_file("full").await.unwrap();
+            let before = fs.node_writes().await;
+
+            for piece in pattern(2 * CHUNK_DATA_SIZE + 100).chunks(100) {
+                file.write_all(piece).await.unwrap();
+            }
+
+            // each full chunk is written along with the metadata which adds it.
+            assert_eq!(fs.node_writes().await, before + 4);
+
+            file.flush().await.unwrap();
+            assert_eq!(fs.node_writes().await, before + 6);
+        });
+    }
+
+    #[test]
+    fn write_then_read_round_trip() {
+        block_on(async {
+            let fs = filesystem();
+            let data = pattern(3 * CHUNK_DATA_SIZE);
+            let mut file = fs.create_file("round trip").await.unwrap();
+
+            file.write_all(&data).await.unwrap();
+            file.flush().await.unwrap();
+
+            let mut file = fs.open_file("round trip").await.unwrap();
+            assert_eq!(file.read_to_end().await.unwrap(), data);
+        });
+    }
+
+    #[test]
+    fn no_chunks_past_the_end() {
+        block_on(async {
+            let fs = filesystem();
+            let mut file = fs.create_file("exact").await.unwrap();
+
+            // the cursor ends up at the start of the next chunk, which mustn't be created.
+            file.write_all(&pattern(2 * CHUNK_DATA_SIZE)).await.unwrap();
+            file.flush().await.unwrap();
+
+            let meta = fs.fetch_metadata_by_name("exact").await.unwrap();
+            let last = meta.first_chunk.with_chunk(1);
+            let past_end = meta.first_chunk.with_chunk(2);
+
+            assert_eq!(meta.chunks, 2);
+            assert!(fs.chunk_exists(last).await.unwrap());
+            assert!(!fs.chunk_exists(past_end).await.unwrap());
+            assert_eq!(
+                fs.file_size("exact").await.unwrap(),
+                2 * CHUNK_DATA_SIZE as u64
+            );
+        });
+    }
+
+    #[test]
+    fn read_one_and_a_half_chunks() {
+        block_on(async {
+            let fs = filesystem();
+            let data = pattern(CHUNK_DATA_SIZE + CHUNK_DATA_SIZE / 2);
+            let mut file = fs.create_file("partial").await.unwrap();
+
+            file.write_all(&data).await.unwrap();
+            file.flush().await.unwrap();
+
+            let mut file = fs.open_file("partial").await.unwrap();
+            let mut buf = vec![0; 2 * CHUNK_DATA_SIZE];
+
+            let len = file.read(&mut buf).await.unwrap();
+            assert_eq!(len, data.len());
+            assert_eq!(&buf[..len], data);
+            assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+
+            // a read from partway through the first chunk carries on into the second.
+            let start = CHUNK_DATA_SIZE - 10;
+            file.seek(SeekFrom::Start(start as u64)).await.unwrap();
+
+            let len = file.read(&mut buf[..20]).await.unwrap();
+            assert_eq!(len, 20);
+            assert_eq!(buf[..20], data[start..start + 20]);
+        });
+    }
+
+    #[test]
+    fn hash_file() {
+        // the SHA-256 of `pattern(6000)`.
+        const DIGEST: [u8; KEY_BYTES] = [
+            0xf9, 0x42, 0xac, 0x77, 0x73, 0x98, 0x61, 0x52, 0x6b, 0x78, 0x45, 0xfc, 0x5c, 0xf7,
+            0x8a, 0x38, 0xb1, 0xf7, 0x30, 0x8a, 0x9b, 0xb8, 0x47, 0xf4, 0x38, 0xac, 0x9d, 0xb4,
+            0xea, 0xe7, 0x26, 0x50,
+        ];
+        // the SHA-256 of no bytes.
+        const EMPTY_DIGEST: [u8; KEY_BYTES] = [
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+            0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+            0x78, 0x52, 0xb8, 0x55,
+        ];
+
+        block_on(async {
+            let fs = filesystem();
+            let mut file = fs.create_file("hashed").await.unwrap();
+
+            assert_eq!(fs.hash_file("hashed").await.unwrap(), EMPTY_DIGEST);
+
+            file.write_all(&pattern(6000)).await.unwrap();
+            file.flush().await.unwrap();
+
+            assert_eq!(fs.hash_file("hashed").await.unwrap(), DIGEST);
+            assert!(matches!(
+                fs.hash_file("missing").await,
+                Err(Error::NotFound)
+            ));
+        });
+    }
+
+    // Clears a bit in the first copy of `needle` in flash, like a bit going bad.
+    async fn damage(fs: &Filesystem, needle: &[u8]) {
+        use embedded_storage_async::nor_flash::NorFlash as AsyncNorFlash;
+
+        let mut inner = fs.0.lock().await;
+        let mut bytes = vec![0; 4 * PAGE_SIZE];
+        AsyncReadNorFlash::read(&mut inner.storage, FS_START, &mut bytes)
+            .await
+            .unwrap();
+
+        let position = bytes
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap();
+
+        // writes can only clear bits, so writing the other bytes of the word back leaves them as
+        // they are.
+        let word = position / PAGE_MARKER_BYTES * PAGE_MARKER_BYTES;
+        let byte = &mut bytes[position];
+        *byte &= *byte - 1;
+
+        let offset = FS_START + word as u32;
+        let end = word + PAGE_MARKER_BYTES;
+        AsyncNorFlash::write(&mut inner.storage, offset, &bytes[word..end])
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn damaged_chunks_are_reported_as_corrupted() {
+        block_on(async {
+            let fs = filesystem();
+            fs.mount().await.unwrap();
+
+            let mut file = fs.create_file("damaged").await.unwrap();
+            file.write_all(b"a chunk that goes bad").await.unwrap();
+            file.flush().await.unwrap();
+
+            damage(&fs, b"a chunk that goes bad").await;
+
+            let mut file = fs.open_file("damaged").await.unwrap();
+            assert!(matches!(file.read_to_end().await, Err(Error::Corrupted)));
+        });
+    }
+
+    #[test]
+    fn mounting_erases_other_formats() {
+        block_on(async {
+            let fs = filesystem();
+
+            // blank flash is formatted without erasing it.
+            fs.mount().await.unwrap();
+            fs.create_file("kept").await.unwrap();
+            fs.mount().await.unwrap();
+            assert!(fs.metadata_exists("kept").await.unwrap());
+
+            let mut inner = fs.0.lock().await;
+            let newer = Node::Format(FORMAT_VERSION + 1);
+            inner.write_node(FORMAT_KEY, newer).await.unwrap();
+            drop(inner);
+
+            fs.mount().await.unwrap();
+            assert!(!fs.metadata_exists("kept").await.unwrap());
+
+            // firmware from before the format version didn't write one.
+            let fs = filesystem();
+            fs.create_file("unversioned").await.unwrap();
+            fs.mount().await.unwrap();
+            assert!(!fs.metadata_exists("unversioned").await.unwrap());
+            assert!(fs.create_file("unversioned").await.is_ok());
+        });
+    }
+
+    #[test]
+    fn storage_errors_map_to_filesystem_errors() {
+        let map = |error: SeqStorageError<EspFlashStorageError>| Error::from(error);
+
+        assert!(matches!(
+            map(SeqStorageError::Storage {
+                value: EspFlashStorageError::IoTimeout
+            }),
+            Error::Flash(EspFlashStorageError::IoTimeout)
+        ));
+        assert!(matches!(map(SeqStorageError::FullStorage), Error::Full));
+        assert!(matches!(
+            map(SeqStorageError::Corrupted {}),
+            Error::Corrupted
+        ));
+        assert!(matches!(
+            map(SeqStorageError::BufferTooBig),
+            Error::DataTooLarge
+        ));
+        assert!(matches!(
+            map(SeqStorageError::BufferTooSmall(300)),
+            Error::ReadBufferTooSmall(300)
+        ));
+        assert!(matches!(
+            map(SeqStorageError::SerializationError(MapSerError::Custom(
+                CRC_MISMATCH
+            ))),
+            Error::Corrupted
+        ));
+        assert!(matches!(
+            map(SeqStorageError::ItemTooBig),
+            Error::ValueTooLarge
+        ));
+    }
+}
@@ -1,19 +1,33 @@
-use super::{postcard_error_to_i32, Key};
-use core::mem;
+use super::{postcard_error_to_i32, Key, KEY_BYTES, PAGE_SIZE};
+use esp_hal::rom::crc::crc32_le;
 use heapless::{String as FixedString, Vec as FixedVec};
 use postcard::experimental::max_size::MaxSize;
 use sequential_storage::map::{SerializationError as MapSerError, Value};
 use serde::{Deserialize, Serialize};
 
 pub(crate) const MAX_NAME_BYTES: usize = 255;
-pub(crate) const CHUNK_MAX_SIZE: usize = 4096;
-pub(crate) const CHUNK_DATA_SIZE: usize = CHUNK_MAX_SIZE - size_of::<FixedVec<u8, 0>>(); // 4096
+pub(crate) const CHUNK_DATA_SIZE: usize = 4000;
+pub(crate) const CRC_BYTES: usize = size_of::<u32>();
+/// The size of the buffer needed to serialize any node, including its checksum.
+pub(crate) const NODE_BUFFER_SIZE: usize = Node::POSTCARD_MAX_SIZE + CRC_BYTES;
+/// Passed through `MapSerError::Custom` when a node's checksum doesn't match its contents.
+pub(crate) const CRC_MISMATCH: i32 = -1;
+/// The layout nodes are stored in. It has to change whenever nodes written by older firmware can't
+/// be read any more, like when [`CHUNK_DATA_SIZE`] changes. Version 1 is the first with checksums.
+pub(crate) const FORMAT_VERSION: u32 = 1;
+// sequential_storage can't split an item across pages. Each page has up to two words at either end
+// marking its state, and each item has an 8 byte header followed by its key and value.
+const PAGE_ITEM_BYTES: usize = PAGE_SIZE - 4 * size_of::<u32>() - 8;
 
-#[allow()]
+const _: () = assert!(KEY_BYTES + NODE_BUFFER_SIZE <= PAGE_ITEM_BYTES);
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize, MaxSize)]
 pub enum Node {
     Metadata(Metadata),
     Chunk(Chunk),
+    /// The [`FORMAT_VERSION`] the flash was formatted with.
+    Format(u32),
 }
 
 impl Node {
@@ -46,19 +60,42 @@ impl Node {
     }
 }
 
+// Nodes are stored with a CRC32 of their serialized bytes after them, so a chunk or metadata node
+// which was damaged in flash is reported as corrupted instead of being handed out as garbage.
 impl<'a> Value<'a> for Node {
     fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, MapSerError> {
-        let slice = postcard::to_slice(self, buffer)
-            .map_err(|e| MapSerError::Custom(postcard_error_to_i32(e)))?;
+        let len = postcard::to_slice(self, buffer)
+            .map_err(|e| MapSerError::Custom(postcard_error_to_i32(e)))?
+            .len();
+
+        let crc = crc32_le(0, &buffer[..len]);
+
+        buffer
+            .get_mut(len..len + CRC_BYTES)
+            .ok_or(MapSerError::BufferTooSmall)?
+            .copy_from_slice(&crc.to_le_bytes());
 
-        Ok(slice.len())
+        Ok(len + CRC_BYTES)
     }
 
     fn deserialize_from(buffer: &'a [u8]) -> Result<Self, MapSerError>
     where
         Self: Sized,
     {
-        postcard::from_bytes(buffer).map_err(|e| MapSerError::Custom(postcard_error_to_i32(e)))
+        let len = buffer
+            .len()
+            .checked_sub(CRC_BYTES)
+            .ok_or(MapSerError::InvalidFormat)?;
+
+        let (bytes, crc) = buffer.split_at(len);
+        let mut crc_bytes = [0; CRC_BYTES];
+        crc_bytes.copy_from_slice(crc);
+
+        if crc32_le(0, bytes) != u32::from_le_bytes(crc_bytes) {
+            return Err(MapSerError::Custom(CRC_MISMATCH));
+        }
+
+        postcard::from_bytes(bytes).map_err(|e| MapSerError::Custom(postcard_error_to_i32(e)))
     }
 }
 
@@ -74,9 +111,3 @@ pub struct Metadata {
     Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Serialize, Deserialize, MaxSize,
 )]
 pub struct Chunk(pub FixedVec<u8, CHUNK_DATA_SIZE>);
-
-impl Chunk {
-    const _SIZE_CHECK: () = const {
-        assert!(mem::size_of::<Self>() <= CHUNK_MAX_SIZE);
-    };
-}
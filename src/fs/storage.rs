@@ -13,6 +13,12 @@ use esp_storage::FlashStorage as EspFlashStorage;
 #[derive(Debug, Default)]
 pub struct Storage(EspFlashStorage);
 
+impl Storage {
+    pub fn new(storage: EspFlashStorage) -> Self {
+        Self(storage)
+    }
+}
+
 impl NorFlashErrorType for Storage {
     type Error = <EspFlashStorage as NorFlashErrorType>::Error;
 }
@@ -1,6 +1,15 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use critical_section::Mutex;
 use esp_println::println;
+use heapless::{Deque, String as FixedString, Vec as FixedVec};
 use log::LevelFilter;
 
+const MAX_TARGET_FILTERS: usize = 8;
+const MAX_TARGET_BYTES: usize = 32;
+const LOG_HISTORY_BYTES: usize = 8192;
+const MAX_HISTORY_LINE_BYTES: usize = 256;
+
 const MAX_LOG_LEVEL: log::LevelFilter = match option_env!("XENON_LOGLEVEL") {
     Some(s) => match s.as_bytes() {
         b"OFF" => log::LevelFilter::Off,
@@ -14,6 +23,122 @@ const MAX_LOG_LEVEL: log::LevelFilter = match option_env!("XENON_LOGLEVEL") {
     None => log::LevelFilter::Info,
 };
 
+// Filters for log targets, matched by prefix. The longest matching prefix wins, and targets with
+// no match only go through the global level.
+static TARGET_FILTERS: Mutex<RefCell<FixedVec<TargetFilter, MAX_TARGET_FILTERS>>> =
+    Mutex::new(RefCell::new(FixedVec::new()));
+
+struct TargetFilter {
+    prefix: FixedString<MAX_TARGET_BYTES>,
+    level: LevelFilter,
+}
+
+/// Sets the level for log targets starting with `prefix`, replacing any level already set for the
+/// same prefix. This can only lower the level below the global one, since records above the global
+/// level are discarded before they reach the logger. Returns `false` if the prefix is too long or
+/// there are too many filters already.
+pub fn set_target_level(prefix: &str, level: LevelFilter) -> bool {
+    critical_section::with(|cs| {
+        let mut filters = TARGET_FILTERS.borrow_ref_mut(cs);
+
+        if let Some(filter) = filters.iter_mut().find(|filter| filter.prefix == prefix) {
+            filter.level = level;
+            return true;
+        }
+
+        let mut fixed_prefix = FixedString::new();
+
+        if fixed_prefix.push_str(prefix).is_err() {
+            return false;
+        }
+
+        filters
+            .push(TargetFilter {
+                prefix: fixed_prefix,
+                level,
+            })
+            .is_ok()
+    })
+}
+
+/// Removes the level set for `prefix` by [`set_target_level`], if there is one.
+pub fn clear_target_level(prefix: &str) {
+    critical_section::with(|cs| {
+        TARGET_FILTERS
+            .borrow_ref_mut(cs)
+            .retain(|filter| filter.prefix != prefix)
+    })
+}
+
+fn target_level(target: &str) -> LevelFilter {
+    critical_section::with(|cs| {
+        TARGET_FILTERS
+            .borrow_ref(cs)
+            .iter()
+            .filter(|filter| target.starts_with(filter.prefix.as_str()))
+            .max_by_key(|filter| filter.prefix.len())
+            .map_or(LevelFilter::Trace, |filter| filter.level)
+    })
+}
+
+// The most recent log lines, without colors, so logs from before serial was connected can still be
+// read. The oldest lines are dropped to make room for new ones.
+static LOG_HISTORY: Mutex<RefCell<Deque<u8, LOG_HISTORY_BYTES>>> =
+    Mutex::new(RefCell::new(Deque::new()));
+
+/// Copies as much of the log history as fits into `buf`, oldest first, and returns the number of
+/// bytes copied. Lines are separated by `\n`.
+pub fn read_log_history(buf: &mut [u8]) -> usize {
+    critical_section::with(|cs| {
+        let history = LOG_HISTORY.borrow_ref(cs);
+        let mut count = 0;
+
+        for (dest, &byte) in buf.iter_mut().zip(history.iter()) {
+            *dest = byte;
+            count += 1;
+        }
+
+        count
+    })
+}
+
+fn push_history(line: &str) {
+    critical_section::with(|cs| {
+        let mut history = LOG_HISTORY.borrow_ref_mut(cs);
+
+        // drop whole lines from the front until the new one fits.
+        while LOG_HISTORY_BYTES - history.len() < line.len() + 1 {
+            while let Some(byte) = history.pop_front() {
+                if byte == b'\n' {
+                    break;
+                }
+            }
+        }
+
+        for &byte in line.as_bytes() {
+            let _ = history.push_back(byte);
+        }
+
+        let _ = history.push_back(b'\n');
+    })
+}
+
+// A line for the log history. Anything past the capacity is cut off instead of failing the whole
+// line.
+struct HistoryLine(FixedString<MAX_HISTORY_LINE_BYTES>);
+
+impl Write for HistoryLine {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.0.push(c).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub fn init_logger(level: LevelFilter) {
     log::set_max_level(level);
     log::set_logger(&Logger).expect("attempted to initialize logger twice");
@@ -28,7 +153,7 @@ struct Logger;
 
 impl log::Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= log::max_level()
+        metadata.level() <= log::max_level() && metadata.level() <= target_level(metadata.target())
     }
 
     fn log(&self, record: &log::Record) {
@@ -41,7 +166,7 @@ impl log::Log for Logger {
 
         let level = record.level();
 
-        if level <= log::max_level() {
+        if self.enabled(record.metadata()) {
             let level_str = level.as_str();
             let level_color = match level {
                 log::Level::Error => COLOR_RED,
@@ -53,10 +178,20 @@ impl log::Log for Logger {
 
             let message = record.args();
 
+            let mut line = HistoryLine(FixedString::new());
+
             match record.target() {
-                "" => println!("{level_color}[{level_str}] - {message}{COLOR_RESET}"),
-                s => println!("{level_color}[{level_str} @ {s}] - {message}{COLOR_RESET}"),
+                "" => {
+                    println!("{level_color}[{level_str}] - {message}{COLOR_RESET}");
+                    let _ = write!(line, "[{level_str}] - {message}");
+                }
+                s => {
+                    println!("{level_color}[{level_str} @ {s}] - {message}{COLOR_RESET}");
+                    let _ = write!(line, "[{level_str} @ {s}] - {message}");
+                }
             };
+
+            push_history(&line.0);
         }
     }
 
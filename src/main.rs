@@ -20,6 +20,7 @@ pub mod float;
 pub mod fs;
 pub mod logger;
 pub(crate) mod macros;
+pub mod power;
 pub mod widget;
 
 use allocator::ALLOCATOR;
@@ -28,7 +29,8 @@ use esp_hal::config::WatchdogConfig;
 use core::array;
 use core::panic::PanicInfo;
 use core::ptr::with_exposed_provenance_mut;
-use driver::lcd;
+use driver::input::{self, Button};
+use driver::{battery, ble, haptic, lcd, rtc};
 use embassy_executor::Spawner;
 use embassy_time::{Instant, Timer};
 use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
@@ -44,10 +46,11 @@ use esp_hal::timer::timg::TimerGroup;
 use esp_hal::timer::AnyTimer;
 use esp_println::println;
 use esp_storage::FlashStorage;
+use embedded_graphics::mono_font::ascii::FONT_6X10;
 use esp_wifi::{EspWifiInitFor, EspWifiInitialization};
-// use fs::FS_START;
-// use fs::{Filesystem, FILESYSTEM};
+use fs::{Filesystem, FILESYSTEM};
 use macros::make_static;
+use widget::text::font::{self, Font};
 
 pub const DRIVER_SWI: u8 = 2;
 pub const VERSION: &str = match option_env!("CARGO_PKG_VERSION") {
@@ -55,6 +58,13 @@ pub const VERSION: &str = match option_env!("CARGO_PKG_VERSION") {
     None => "0.0.0",
 };
 
+/// The wasm app started on the application core at boot.
+pub const APP_FILE_NAME: &str = "xenon-test-app.wasm";
+
+/// The zlib compressed font loaded as the system font at boot. Without it, text is drawn with a
+/// built in 6x10 pixel font.
+pub const SYSTEM_FONT_FILE_NAME: &str = "system.font";
+
 pub static EXAMPLE_TEXT: &str = "The quick brown fox jumps over the lazy dog.";
 
 #[inline(always)]
@@ -88,6 +98,31 @@ fn useit<T>(x: T) -> T {
     core::hint::black_box(x)
 }
 
+// Loads the system font from flash, falling back to the built in font if it can't be, so apps can
+// always draw text.
+async fn load_system_font() -> Font {
+    let bytes = match fs::filesystem() {
+        Some(fs) => match fs.open_file(SYSTEM_FONT_FILE_NAME).await {
+            Ok(mut file) => file.read_to_end().await,
+            Err(e) => Err(e),
+        },
+        None => Err(fs::Error::NotFound),
+    };
+
+    let loaded = match bytes {
+        Ok(bytes) => Font::from_compressed_bytes(bytes)
+            .inspect_err(|e| log::warn!("invalid system font `{SYSTEM_FONT_FILE_NAME}`: {e}"))
+            .ok(),
+        Err(fs::Error::NotFound) => None,
+        Err(e) => {
+            log::warn!("failed to read system font `{SYSTEM_FONT_FILE_NAME}`: {e}");
+            None
+        }
+    };
+
+    loaded.unwrap_or_else(|| Font::from_mono_font(&FONT_6X10))
+}
+
 #[main]
 async fn main(spawner: Spawner) {
     let mut hal_config = esp_hal::Config::default();
@@ -111,13 +146,24 @@ async fn main(spawner: Spawner) {
     let dma = Dma::new(peripherals.DMA);
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     let trng = Trng::new(peripherals.RNG, peripherals.ADC1);
+    // `Rng` is `Copy`, so this leaves `trng` whole for the application core, while the wireless
+    // stack gets its own handle to the same generator.
     let rng = trng.rng;
 
     init_embassy(timg0.timer0, timg0.timer1);
+    rtc::init(peripherals.LPWR);
+    battery::init(peripherals.ADC2, io.pins.gpio11, battery::DEFAULT_DIVIDER);
+
+    let filesystem = FILESYSTEM.call_once(|| Filesystem::new(FlashStorage::new(), rng));
+
+    if let Err(e) = filesystem.mount().await {
+        log::error!("failed to mount the filesystem: {e}");
+    }
+
+    log_init("filesystem");
 
-    // let fs = Filesystem::new(FlashStorage::new(), rng).await.unwrap();
-    // FILESYSTEM.init(fs);
-    // log_init("filesystem");
+    font::init_system_font(load_system_font().await);
+    log_init("system font");
 
     spawner.must_spawn(lcd::start(
         peripherals.SPI2,
@@ -127,10 +173,25 @@ async fn main(spawner: Spawner) {
         dma,
     ));
 
-    // init_wireless(timg1.timer1, rng, peripherals.RADIO_CLK, clocks);
-
-    // let mut app_cpu = AppCpu::new(peripherals.CPU_CTRL);
-    // app_cpu.start(trng, spawner.make_send());
+    spawner.must_spawn(haptic::start(peripherals.LEDC, io.pins.gpio5));
+
+    spawner.must_spawn(input::start(Button::Back, io.pins.gpio1.degrade()));
+    spawner.must_spawn(input::start(Button::Up, io.pins.gpio2.degrade()));
+    spawner.must_spawn(input::start(Button::Select, io.pins.gpio3.degrade()));
+    spawner.must_spawn(input::start(Button::Down, io.pins.gpio4.degrade()));
+
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let wireless = make_static!(
+        EspWifiInitialization,
+        init_wireless(timg1.timer0, rng, peripherals.RADIO_CLK)
+    );
+    spawner.must_spawn(ble::start(wireless, peripherals.BT));
+
+    // `app_cpu` has to stay alive for as long as the app core runs, since dropping it parks the
+    // core, so it's static. The power task uses it to stall the app core during light sleep.
+    let app_cpu = make_static!(AppCpu<'static>, AppCpu::new(peripherals.CPU_CTRL));
+    app_cpu.start(APP_FILE_NAME, trng, spawner.make_send());
+    spawner.must_spawn(power::start(app_cpu));
 
     loop {
         Timer::after_secs(1).await;
@@ -0,0 +1,92 @@
+use crate::app::cpu::AppCpu;
+use crate::driver::{input, lcd, rtc};
+use crate::log_init;
+use embassy_executor::task;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex as CsRawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embassy_sync::signal::Signal;
+use embassy_time::Duration;
+use esp_hal::rtc_cntl::sleep::{GpioWakeupSource, TimerWakeupSource};
+
+/// The longest the chip sleeps for in one go. Longer requests are shortened to this.
+pub const MAX_SLEEP_DURATION: Duration = Duration::from_secs(10 * 60);
+
+static SLEEP_REQUEST: Signal<CsRawMutex, Duration> = Signal::new();
+
+// Held by the app core while it runs the app, and by `light_sleep` while the chip is asleep.
+static APP_AWAKE: Mutex<CsRawMutex, ()> = Mutex::new(());
+
+/// Keeps the chip from going into light sleep until the guard is dropped, waiting for it to wake up
+/// first if it's asleep. The app core holds this while running the app, so sleeping never stops
+/// it partway through a syscall.
+pub async fn stay_awake() -> MutexGuard<'static, CsRawMutex, ()> {
+    APP_AWAKE.lock().await
+}
+
+/// Puts the chip into light sleep for `duration`, or until a button is pressed. RAM (including
+/// [`lcd::LCD_BUFFER`]) is kept.
+///
+/// Everything is stopped at a safe point first: the app core finishes the app's current tick and
+/// is stalled until the chip wakes up, the display is blanked and redrawn after waking up, and the
+/// buttons are armed as wakeup sources. Sleeping blocks the executor this is called from, so no
+/// other tasks on this core run until it wakes up.
+pub async fn light_sleep(app_cpu: &mut AppCpu<'_>, duration: Duration) {
+    lcd::display_off().await;
+    input::prepare_sleep().await;
+
+    let _app = stay_awake().await;
+
+    // The app isn't running, but the app core's executor can still be. Stalling it inside a
+    // critical section means it can't be holding the critical section's lock while it's stalled,
+    // which would deadlock this core the next time it takes one.
+    critical_section::with(|_| app_cpu.park());
+
+    let duration = duration.min(MAX_SLEEP_DURATION);
+    let timer = TimerWakeupSource::new(core::time::Duration::from_micros(duration.as_micros()));
+    let buttons = GpioWakeupSource::new();
+
+    // The RTC is taken out rather than used through `with_rtc`, which would sleep in a critical
+    // section. The time reads as when the chip fell asleep until it's back.
+    match rtc::take() {
+        Some(mut rtc) => {
+            rtc.sleep_light(&[&timer, &buttons]);
+            rtc::put_back(rtc);
+        }
+        None => log::warn!("RTC isn't initialized, not sleeping"),
+    }
+
+    app_cpu.unpark();
+    input::finish_sleep();
+    lcd::display_on();
+}
+
+/// Puts the chip into deep sleep for `duration`. Waking up from deep sleep resets the chip, so
+/// this never returns.
+pub async fn deep_sleep(duration: Duration) -> ! {
+    lcd::display_off().await;
+
+    let wakeup = TimerWakeupSource::new(core::time::Duration::from_micros(duration.as_micros()));
+
+    rtc::with_rtc(|rtc| rtc.sleep_deep(&[&wakeup])).expect("RTC should be initialized");
+
+    unreachable!()
+}
+
+/// Asks the power task to put the chip into light sleep for `duration`, up to
+/// [`MAX_SLEEP_DURATION`]. This can be called from either core, and a request made before the last
+/// one was handled replaces it.
+pub fn request_sleep(duration: Duration) {
+    SLEEP_REQUEST.signal(duration);
+}
+
+/// Handles sleep requests made with [`request_sleep`]. This has to run on the main core, since
+/// it stalls the app core while the chip is asleep.
+#[task]
+pub async fn start(app_cpu: &'static mut AppCpu<'static>) -> ! {
+    log_init("power");
+
+    loop {
+        let duration = SLEEP_REQUEST.wait().await;
+        light_sleep(app_cpu, duration).await;
+    }
+}
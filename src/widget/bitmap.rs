@@ -1,4 +1,5 @@
 use alloc::boxed::Box;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::iter::{self, FusedIterator};
 use embedded_graphics::image::ImageDrawable;
@@ -8,6 +9,7 @@ use embedded_graphics::prelude::{
 };
 use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::Pixel;
+use miniz_oxide::deflate::{self, CompressionLevel};
 use miniz_oxide::inflate::{self, TINFLStatus};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -17,6 +19,9 @@ const BITMAP_COLOR_WHITE: u8 = 0b01;
 const BITMAP_COLOR_TRANSPARENT: u8 = 0b11;
 pub(crate) const MAX_BITMAP_WIDTH: u8 = 240;
 pub(crate) const MAX_BITMAP_HEIGHT: u8 = u8::MAX;
+const RLE_MAX_RUN: u8 = 64;
+const ENCODED_TAG_DEFLATE: u8 = 0;
+const ENCODED_TAG_RLE: u8 = 1;
 
 // 2 bpp
 // a maximum of MAX_WIDTH pixels wide, divided by 8 to get bytes
@@ -36,11 +41,32 @@ pub fn expected_data_len(width: u8, height: u8) -> usize {
     width_bytes * height_lines
 }
 
-fn check(width: u8, height: u8, data: &[u8]) -> Result<(), BitmapError> {
+// `height` can't be greater than `MAX_BITMAP_HEIGHT`, since that's `u8::MAX`, so only the width
+// needs checking.
+fn check_dimensions(width: u8, height: u8) -> Result<(), BitmapError> {
     if width > MAX_BITMAP_WIDTH {
         return Err(BitmapError::InvalidDimensions { width, height });
     }
 
+    Ok(())
+}
+
+fn check_decompressed_len(width: u8, height: u8, len: usize) -> Result<(), BitmapError> {
+    let expected = expected_data_len(width, height);
+
+    if len != expected {
+        return Err(BitmapError::LengthMismatch {
+            expected,
+            actual: len,
+        });
+    }
+
+    Ok(())
+}
+
+fn check(width: u8, height: u8, data: &[u8]) -> Result<(), BitmapError> {
+    check_dimensions(width, height)?;
+
     let expected_len = expected_data_len(width, height);
 
     if expected_len != data.len() {
@@ -53,59 +79,66 @@ fn check(width: u8, height: u8, data: &[u8]) -> Result<(), BitmapError> {
     Ok(())
 }
 
-fn set_pixel_internal(width: u8, height: u8, x: u8, y: u8, color: PixelColor, data: &mut [u8]) {
-    const SET_LUT: [u8; 4] = [192, 48, 12, 3];
-    const UNSET_LUT: [u8; 4] = [!192, !48, !12, !3];
-    const WHITE_LUT: [u8; 4] = [64, 16, 4, 1];
+// Builds a bitmap by calling `f` with the coordinates of each pixel to get its color. `width` must
+// not be greater than `MAX_BITMAP_WIDTH`.
+fn bitmap_from_fn(width: u8, height: u8, mut f: impl FnMut(u8, u8) -> PixelColor) -> Bitmap {
+    let mut data = vec![0; expected_data_len(width, height)];
 
+    for y in 0..height {
+        for x in 0..width {
+            set_pixel_internal(width, height, x, y, f(x, y), &mut data);
+        }
+    }
+
+    Bitmap {
+        width,
+        height,
+        data: data.into_boxed_slice(),
+    }
+}
+
+// The format read by the `from_encoded` functions: the width and height, followed by the data.
+fn encode(width: u8, height: u8, data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + 2);
+
+    encoded.push(width);
+    encoded.push(height);
+    encoded.extend_from_slice(data);
+    encoded
+}
+
+// Each byte holds 4 pixels, with the first pixel in the 2 most significant bits.
+const PIXEL_MASK: u8 = 0b11;
+
+fn set_pixel_internal(width: u8, height: u8, x: u8, y: u8, color: PixelColor, data: &mut [u8]) {
     if x < width && y < height {
-        let (index, pos) = get_index_pos_internal(width, x, y);
+        let (index, shift) = get_index_shift_internal(width, x, y);
 
-        match color {
-            PixelColor::Black => {
-                data[index] &= UNSET_LUT[pos];
-            }
-            PixelColor::White => {
-                data[index] &= UNSET_LUT[pos];
-                data[index] |= WHITE_LUT[pos];
-            }
-            PixelColor::Transparent => {
-                data[index] |= SET_LUT[pos];
-            }
-        }
+        data[index] &= !(PIXEL_MASK << shift);
+        data[index] |= (color as u8) << shift;
     }
 }
 
 fn get_pixel_internal(width: u8, height: u8, x: u8, y: u8, data: &[u8]) -> Option<PixelColor> {
-    const WHITE_LUT: [u8; 4] = [
-        BITMAP_COLOR_WHITE << 6,
-        BITMAP_COLOR_WHITE << 4,
-        BITMAP_COLOR_WHITE << 2,
-        BITMAP_COLOR_WHITE,
-    ];
-    const TRANSPARENT_LUT: [u8; 4] = [
-        BITMAP_COLOR_TRANSPARENT << 6,
-        BITMAP_COLOR_TRANSPARENT << 4,
-        BITMAP_COLOR_TRANSPARENT << 2,
-        BITMAP_COLOR_TRANSPARENT,
-    ];
-
     if x < width && y < height {
-        let (index, mask) = get_index_mask_internal(width, x, y);
+        let (index, shift) = get_index_shift_internal(width, x, y);
 
-        match data[index] & mask {
+        // 0b10 isn't a color `set_pixel_internal` writes, but bitmaps can come from apps, so it's
+        // read as transparent like 0b11, since the high bit is what marks a pixel as transparent.
+        match (data[index] >> shift) & PIXEL_MASK {
             BITMAP_COLOR_BLACK => Some(PixelColor::Black),
-            color if WHITE_LUT.contains(&color) => Some(PixelColor::White),
-            color if TRANSPARENT_LUT.contains(&color) => Some(PixelColor::Transparent),
-            _ => unreachable!("masking with {mask:#8b} ensures no other values can be matched"),
+            BITMAP_COLOR_WHITE => Some(PixelColor::White),
+            _ => Some(PixelColor::Transparent),
         }
     } else {
         None
     }
 }
 
+// Returns the index of the byte holding the pixel, and how far its 2 bits are shifted left in
+// that byte.
 #[inline]
-const fn get_index_pos_internal(width: u8, x: u8, y: u8) -> (usize, usize) {
+const fn get_index_shift_internal(width: u8, x: u8, y: u8) -> (usize, u32) {
     let actual_width = if width % 8 == 0 {
         width as usize
     } else {
@@ -114,19 +147,9 @@ const fn get_index_pos_internal(width: u8, x: u8, y: u8) -> (usize, usize) {
     };
 
     let index = (x as usize + actual_width * y as usize) / 4;
-    let pos = (x % 4) as usize;
+    let shift = 6 - 2 * (x % 4) as u32;
 
-    (index, pos)
-}
-
-#[inline]
-const fn get_index_mask_internal(width: u8, x: u8, y: u8) -> (usize, u8) {
-    const MASK: [u8; 4] = [192, 48, 12, 3];
-
-    let (index, pos) = get_index_pos_internal(width, x, y);
-    let mask = MASK[pos];
-
-    (index, mask)
+    (index, shift)
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
@@ -143,6 +166,8 @@ impl CompressedBitmap {
         let &width = iter.next().ok_or(BitmapError::NoWidth)?;
         let &height = iter.next().ok_or(BitmapError::NoHeight)?;
 
+        check_dimensions(width, height)?;
+
         Ok(Self {
             width,
             height,
@@ -158,6 +183,12 @@ impl CompressedBitmap {
         self.height
     }
 
+    /// Encodes the bitmap in the format read by [`CompressedBitmap::new`]: the width and height,
+    /// followed by the zlib compressed data.
+    pub fn to_encoded(&self) -> Vec<u8> {
+        encode(self.width, self.height, &self.data)
+    }
+
     pub fn decompress(self) -> Result<Bitmap, BitmapError> {
         let mut bytes = [0u8; MAX_IMAGE_SIZE];
 
@@ -169,6 +200,8 @@ impl CompressedBitmap {
         )
         .map_err(BitmapError::DecompressionFailed)?;
 
+        check_decompressed_len(self.width, self.height, len)?;
+
         Bitmap::new(self.width, self.height, &bytes[..len])
     }
 
@@ -180,6 +213,8 @@ impl CompressedBitmap {
             inflate::decompress_slice_iter_to_slice(buf, iter::once(&self.data[..]), true, false)
                 .map_err(BitmapError::DecompressionFailed)?;
 
+        check_decompressed_len(self.width, self.height, len)?;
+
         BitmapRef::new(self.width, self.height, &buf[..len])
     }
 
@@ -191,6 +226,8 @@ impl CompressedBitmap {
             inflate::decompress_slice_iter_to_slice(buf, iter::once(&self.data[..]), true, false)
                 .map_err(BitmapError::DecompressionFailed)?;
 
+        check_decompressed_len(self.width, self.height, len)?;
+
         BitmapRefMut::new(self.width, self.height, &mut buf[..len])
     }
 }
@@ -238,12 +275,14 @@ pub struct CompressedBitmapRef<'data> {
 }
 
 impl<'data> CompressedBitmapRef<'data> {
-    pub fn new(width: u8, height: u8, data: &'data [u8]) -> Self {
-        Self {
+    pub fn new(width: u8, height: u8, data: &'data [u8]) -> Result<Self, BitmapError> {
+        check_dimensions(width, height)?;
+
+        Ok(Self {
             width,
             height,
             data,
-        }
+        })
     }
 
     pub fn from_encoded(bytes: &'data [u8]) -> Result<Self, BitmapError> {
@@ -252,11 +291,7 @@ impl<'data> CompressedBitmapRef<'data> {
         let &width = iter.next().ok_or(BitmapError::NoWidth)?;
         let &height = iter.next().ok_or(BitmapError::NoHeight)?;
 
-        Ok(Self {
-            width,
-            height,
-            data: iter.as_slice(),
-        })
+        Self::new(width, height, iter.as_slice())
     }
 
     pub fn width(&self) -> u8 {
@@ -274,6 +309,8 @@ impl<'data> CompressedBitmapRef<'data> {
             inflate::decompress_slice_iter_to_slice(&mut bytes, iter::once(self.data), true, false)
                 .map_err(BitmapError::DecompressionFailed)?;
 
+        check_decompressed_len(self.width, self.height, len)?;
+
         Bitmap::new(self.width, self.height, &bytes[..len])
     }
 
@@ -284,6 +321,8 @@ impl<'data> CompressedBitmapRef<'data> {
         let len = inflate::decompress_slice_iter_to_slice(buf, iter::once(self.data), true, false)
             .map_err(BitmapError::DecompressionFailed)?;
 
+        check_decompressed_len(self.width, self.height, len)?;
+
         BitmapRef::new(self.width, self.height, &buf[..len])
     }
 
@@ -294,6 +333,8 @@ impl<'data> CompressedBitmapRef<'data> {
         let len = inflate::decompress_slice_iter_to_slice(buf, iter::once(self.data), true, false)
             .map_err(BitmapError::DecompressionFailed)?;
 
+        check_decompressed_len(self.width, self.height, len)?;
+
         BitmapRefMut::new(self.width, self.height, &mut buf[..len])
     }
 }
@@ -334,6 +375,392 @@ impl ImageDrawable for CompressedBitmapRef<'_> {
     }
 }
 
+/// A bitmap compressed with run-length encoding. Each byte of the data is a run of up to 64 pixels
+/// of the same color, with the color in the top 2 bits and the length of the run minus 1 in the
+/// bottom 6 bits. Pixels are stored row by row without any padding.
+///
+/// This compresses worse than [`CompressedBitmap`] in general, but icons with large solid areas
+/// compress well, and it can be drawn directly without decompressing to a buffer first.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct RleBitmap {
+    width: u8,
+    height: u8,
+    data: Box<[u8]>,
+}
+
+impl RleBitmap {
+    pub fn new(bytes: &[u8]) -> Result<Self, BitmapError> {
+        let bitmap = RleBitmapRef::from_encoded(bytes)?;
+
+        Ok(Self {
+            width: bitmap.width,
+            height: bitmap.height,
+            data: bitmap.data.into(),
+        })
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Encodes the bitmap in the format read by [`RleBitmap::new`]: the width and height,
+    /// followed by the runs.
+    pub fn to_encoded(&self) -> Vec<u8> {
+        encode(self.width, self.height, &self.data)
+    }
+
+    pub fn as_ref(&self) -> RleBitmapRef<'_> {
+        RleBitmapRef {
+            width: self.width,
+            height: self.height,
+            data: &self.data,
+        }
+    }
+
+    pub fn decompress(self) -> Result<Bitmap, BitmapError> {
+        self.as_ref().decompress()
+    }
+
+    pub fn decompress_to_ref<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<BitmapRef<'buf>, BitmapError> {
+        self.as_ref().decompress_to_ref(buf)
+    }
+
+    pub fn decompress_to_ref_mut<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<BitmapRefMut<'buf>, BitmapError> {
+        self.as_ref().decompress_to_ref_mut(buf)
+    }
+}
+
+impl OriginDimensions for RleBitmap {
+    fn size(&self) -> Size {
+        Size::new(self.width as _, self.height as _)
+    }
+}
+
+impl ImageDrawable for RleBitmap {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.as_ref().draw(target)
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.as_ref().draw_sub_image(target, area)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct RleBitmapRef<'data> {
+    width: u8,
+    height: u8,
+    data: &'data [u8],
+}
+
+impl<'data> RleBitmapRef<'data> {
+    /// Fails if the bitmap is too wide, or if the runs in `data` don't cover it exactly. Checking
+    /// the runs here means drawing the bitmap can't fail later.
+    pub fn new(width: u8, height: u8, data: &'data [u8]) -> Result<Self, BitmapError> {
+        check_dimensions(width, height)?;
+
+        let bitmap = Self {
+            width,
+            height,
+            data,
+        };
+
+        let mut decoded = 0;
+
+        for run in bitmap.runs() {
+            let (_, _, _, count) = run?;
+            decoded += count as usize;
+        }
+
+        if decoded == width as usize * height as usize {
+            Ok(bitmap)
+        } else {
+            Err(BitmapError::InvalidRunLength)
+        }
+    }
+
+    pub fn from_encoded(bytes: &'data [u8]) -> Result<Self, BitmapError> {
+        let mut iter = bytes.iter();
+
+        let &width = iter.next().ok_or(BitmapError::NoWidth)?;
+        let &height = iter.next().ok_or(BitmapError::NoHeight)?;
+
+        Self::new(width, height, iter.as_slice())
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn decompress(self) -> Result<Bitmap, BitmapError> {
+        let mut data = vec![0; expected_data_len(self.width, self.height)];
+        self.decode_into(&mut data)?;
+
+        Bitmap::new(self.width, self.height, data)
+    }
+
+    pub fn decompress_to_ref<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<BitmapRef<'buf>, BitmapError> {
+        let len = self.decode_into(buf)?;
+
+        BitmapRef::new(self.width, self.height, &buf[..len])
+    }
+
+    pub fn decompress_to_ref_mut<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<BitmapRefMut<'buf>, BitmapError> {
+        let len = self.decode_into(buf)?;
+
+        BitmapRefMut::new(self.width, self.height, &mut buf[..len])
+    }
+
+    pub fn runs(&self) -> Runs<'data> {
+        Runs {
+            width: self.width,
+            height: self.height,
+            x: 0,
+            y: 0,
+            remaining: 0,
+            color: PixelColor::Transparent,
+            data: self.data.iter(),
+        }
+    }
+
+    // Decodes the bitmap into the start of `buf`, returning the number of bytes used. Fails if
+    // `buf` is too small or if the runs don't cover the bitmap exactly.
+    fn decode_into(&self, buf: &mut [u8]) -> Result<usize, BitmapError> {
+        let (width, height) = (self.width, self.height);
+
+        check_dimensions(width, height)?;
+
+        let len = expected_data_len(width, height);
+        let pixels = width as usize * height as usize;
+
+        let Some(buf) = buf.get_mut(..len) else {
+            return Err(BitmapError::LengthMismatch {
+                expected: len,
+                actual: buf.len(),
+            });
+        };
+
+        let mut decoded = 0;
+
+        for run in self.runs() {
+            let (x, y, color, count) = run?;
+
+            for x in x..x + count {
+                set_pixel_internal(width, height, x, y, color, buf);
+            }
+
+            decoded += count as usize;
+        }
+
+        if decoded == pixels {
+            Ok(len)
+        } else {
+            Err(BitmapError::InvalidRunLength)
+        }
+    }
+}
+
+impl OriginDimensions for RleBitmapRef<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.width as _, self.height as _)
+    }
+}
+
+impl ImageDrawable for RleBitmapRef<'_> {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        // the runs were checked when the bitmap was created, so this never stops early.
+        for (x, y, color, count) in self.runs().map_while(Result::ok) {
+            let color = match color {
+                PixelColor::Black => BinaryColor::On,
+                PixelColor::White => BinaryColor::Off,
+                PixelColor::Transparent => continue,
+            };
+
+            let area = Rectangle::new(Point::new(x as i32, y as i32), Size::new(count as u32, 1));
+            target.fill_solid(&area, color)?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.draw(&mut target.translated(-area.top_left).clipped(area))
+    }
+}
+
+/// An iterator over the runs of an [`RleBitmapRef`], split at the ends of rows. Each item is the
+/// position of the first pixel in the run, its color, and the number of pixels in it.
+pub struct Runs<'data> {
+    width: u8,
+    height: u8,
+    x: u8,
+    y: u8,
+    remaining: u8,
+    color: PixelColor,
+    data: core::slice::Iter<'data, u8>,
+}
+
+impl Iterator for Runs<'_> {
+    type Item = Result<(u8, u8, PixelColor, u8), BitmapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.width == 0 {
+            return None;
+        }
+
+        if self.remaining == 0 {
+            let &byte = self.data.next()?;
+
+            self.color = match byte >> 6 {
+                BITMAP_COLOR_BLACK => PixelColor::Black,
+                BITMAP_COLOR_WHITE => PixelColor::White,
+                BITMAP_COLOR_TRANSPARENT => PixelColor::Transparent,
+                _ => return Some(Err(BitmapError::InvalidRunLength)),
+            };
+            self.remaining = (byte & 0b0011_1111) + 1;
+        }
+
+        let (x, y) = (self.x, self.y);
+
+        // runs continuing past the last row don't match the dimensions.
+        if y >= self.height {
+            self.remaining = 0;
+            return Some(Err(BitmapError::InvalidRunLength));
+        }
+
+        let count = self.remaining.min(self.width - x);
+
+        self.remaining -= count;
+
+        if x + count == self.width {
+            self.x = 0;
+            self.y += 1;
+        } else {
+            self.x += count;
+        }
+
+        Some(Ok((x, y, self.color, count)))
+    }
+}
+
+/// A compressed bitmap in either format, for data which starts with a tag byte saying which format
+/// it's in, followed by the width, height, and compressed data.
+pub enum EncodedBitmap<'data> {
+    Deflate(CompressedBitmapRef<'data>),
+    Rle(RleBitmapRef<'data>),
+}
+
+impl<'data> EncodedBitmap<'data> {
+    pub fn from_encoded(bytes: &'data [u8]) -> Result<Self, BitmapError> {
+        let (&tag, rest) = bytes
+            .split_first()
+            .ok_or(BitmapError::UnknownFormat(None))?;
+
+        match tag {
+            ENCODED_TAG_DEFLATE => CompressedBitmapRef::from_encoded(rest).map(Self::Deflate),
+            ENCODED_TAG_RLE => RleBitmapRef::from_encoded(rest).map(Self::Rle),
+            tag => Err(BitmapError::UnknownFormat(Some(tag))),
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        match self {
+            Self::Deflate(bitmap) => bitmap.width(),
+            Self::Rle(bitmap) => bitmap.width(),
+        }
+    }
+
+    pub fn height(&self) -> u8 {
+        match self {
+            Self::Deflate(bitmap) => bitmap.height(),
+            Self::Rle(bitmap) => bitmap.height(),
+        }
+    }
+
+    pub fn decompress(self) -> Result<Bitmap, BitmapError> {
+        match self {
+            Self::Deflate(bitmap) => bitmap.decompress(),
+            Self::Rle(bitmap) => bitmap.decompress(),
+        }
+    }
+
+    pub fn decompress_to_ref<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<BitmapRef<'buf>, BitmapError> {
+        match self {
+            Self::Deflate(bitmap) => bitmap.decompress_to_ref(buf),
+            Self::Rle(bitmap) => bitmap.decompress_to_ref(buf),
+        }
+    }
+}
+
+impl OriginDimensions for EncodedBitmap<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.width() as _, self.height() as _)
+    }
+}
+
+impl ImageDrawable for EncodedBitmap<'_> {
+    type Color = BinaryColor;
+
+    fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        match self {
+            Self::Deflate(bitmap) => bitmap.draw(target),
+            Self::Rle(bitmap) => bitmap.draw(target),
+        }
+    }
+
+    fn draw_sub_image<D>(&self, target: &mut D, area: &Rectangle) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        match self {
+            Self::Deflate(bitmap) => bitmap.draw_sub_image(target, area),
+            Self::Rle(bitmap) => bitmap.draw_sub_image(target, area),
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 pub struct Bitmap {
     width: u8,
@@ -373,6 +800,12 @@ impl Bitmap {
         self.height
     }
 
+    /// Encodes the bitmap in the format read by [`Bitmap::from_encoded`]: the width and height,
+    /// followed by the pixel data.
+    pub fn to_encoded(&self) -> Vec<u8> {
+        encode(self.width, self.height, &self.data)
+    }
+
     pub fn set_pixel(&mut self, x: u8, y: u8, color: PixelColor) {
         set_pixel_internal(self.width, self.height, x, y, color, &mut self.data)
     }
@@ -404,6 +837,42 @@ impl Bitmap {
             bitmap: self.as_ref(),
         }
     }
+
+    pub fn compress(&self) -> CompressedBitmap {
+        self.as_ref().compress()
+    }
+
+    pub fn compress_rle(&self) -> RleBitmap {
+        self.as_ref().compress_rle()
+    }
+
+    pub fn scale(&self, new_width: u8, new_height: u8) -> Result<Bitmap, BitmapError> {
+        self.as_ref().scale(new_width, new_height)
+    }
+
+    pub fn flip_horizontal(&self) -> Bitmap {
+        self.as_ref().flip_horizontal()
+    }
+
+    pub fn flip_vertical(&self) -> Bitmap {
+        self.as_ref().flip_vertical()
+    }
+
+    pub fn rotate_90(&self) -> Result<Bitmap, BitmapError> {
+        self.as_ref().rotate_90()
+    }
+
+    pub fn rotate_180(&self) -> Bitmap {
+        self.as_ref().rotate_180()
+    }
+
+    pub fn rotate_270(&self) -> Result<Bitmap, BitmapError> {
+        self.as_ref().rotate_270()
+    }
+
+    pub fn transform(&self, transform: Transform) -> Result<Bitmap, BitmapError> {
+        self.as_ref().transform(transform)
+    }
 }
 
 impl OriginDimensions for Bitmap {
@@ -473,6 +942,11 @@ impl<'data> BitmapRef<'data> {
         self.height
     }
 
+    /// Encodes the bitmap in the format read by [`BitmapRef::from_encoded`].
+    pub fn to_encoded(&self) -> Vec<u8> {
+        encode(self.width, self.height, self.data)
+    }
+
     pub(crate) fn data(&self) -> &[u8] {
         self.data
     }
@@ -496,6 +970,147 @@ impl<'data> BitmapRef<'data> {
             bitmap: *self,
         }
     }
+
+    /// Compresses the bitmap data with zlib, which is the format expected by
+    /// [`CompressedBitmap`]'s decompression methods.
+    pub fn compress(&self) -> CompressedBitmap {
+        let data =
+            deflate::compress_to_vec_zlib(self.data, CompressionLevel::BestCompression as u8);
+
+        CompressedBitmap {
+            width: self.width,
+            height: self.height,
+            data: data.into_boxed_slice(),
+        }
+    }
+
+    /// Compresses the bitmap with run-length encoding. See [`RleBitmap`] for the format.
+    pub fn compress_rle(&self) -> RleBitmap {
+        let mut data = Vec::new();
+        let mut run: Option<(PixelColor, u8)> = None;
+
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get_pixel(x, y).unwrap());
+
+        for color in pixels {
+            run = match run {
+                Some((run_color, len)) if run_color == color && len < RLE_MAX_RUN => {
+                    Some((run_color, len + 1))
+                }
+                Some((run_color, len)) => {
+                    data.push(((run_color as u8) << 6) | (len - 1));
+                    Some((color, 1))
+                }
+                None => Some((color, 1)),
+            };
+        }
+
+        if let Some((run_color, len)) = run {
+            data.push(((run_color as u8) << 6) | (len - 1));
+        }
+
+        RleBitmap {
+            width: self.width,
+            height: self.height,
+            data: data.into_boxed_slice(),
+        }
+    }
+
+    /// Resizes the bitmap to `new_width` x `new_height` with nearest-neighbor sampling, so every
+    /// pixel in the result (including transparent ones) is a copy of a pixel in the original.
+    pub fn scale(&self, new_width: u8, new_height: u8) -> Result<Bitmap, BitmapError> {
+        let empty = self.width == 0 || self.height == 0 || new_width == 0 || new_height == 0;
+
+        if empty || new_width > MAX_BITMAP_WIDTH {
+            return Err(BitmapError::InvalidDimensions {
+                width: new_width,
+                height: new_height,
+            });
+        }
+
+        let scaled = bitmap_from_fn(new_width, new_height, |x, y| {
+            // these are always less than the original width and height, so they fit in a u8 and
+            // the pixel is always in bounds.
+            let src_x = (x as usize * self.width as usize / new_width as usize) as u8;
+            let src_y = (y as usize * self.height as usize / new_height as usize) as u8;
+
+            self.get_pixel(src_x, src_y).unwrap()
+        });
+
+        Ok(scaled)
+    }
+
+    /// Mirrors the bitmap left to right.
+    pub fn flip_horizontal(&self) -> Bitmap {
+        let max_x = self.width.saturating_sub(1);
+
+        bitmap_from_fn(self.width, self.height, |x, y| {
+            self.get_pixel(max_x - x, y).unwrap()
+        })
+    }
+
+    /// Mirrors the bitmap top to bottom.
+    pub fn flip_vertical(&self) -> Bitmap {
+        let max_y = self.height.saturating_sub(1);
+
+        bitmap_from_fn(self.width, self.height, |x, y| {
+            self.get_pixel(x, max_y - y).unwrap()
+        })
+    }
+
+    /// Rotates the bitmap 90 degrees clockwise. The width and height are swapped, so this fails if
+    /// the height is greater than [`MAX_BITMAP_WIDTH`].
+    pub fn rotate_90(&self) -> Result<Bitmap, BitmapError> {
+        self.check_rotated()?;
+        let max_y = self.height.saturating_sub(1);
+
+        Ok(bitmap_from_fn(self.height, self.width, |x, y| {
+            self.get_pixel(y, max_y - x).unwrap()
+        }))
+    }
+
+    /// Rotates the bitmap 180 degrees.
+    pub fn rotate_180(&self) -> Bitmap {
+        let max_x = self.width.saturating_sub(1);
+        let max_y = self.height.saturating_sub(1);
+
+        bitmap_from_fn(self.width, self.height, |x, y| {
+            self.get_pixel(max_x - x, max_y - y).unwrap()
+        })
+    }
+
+    /// Rotates the bitmap 90 degrees counterclockwise. The width and height are swapped, so this
+    /// fails if the height is greater than [`MAX_BITMAP_WIDTH`].
+    pub fn rotate_270(&self) -> Result<Bitmap, BitmapError> {
+        self.check_rotated()?;
+        let max_x = self.width.saturating_sub(1);
+
+        Ok(bitmap_from_fn(self.height, self.width, |x, y| {
+            self.get_pixel(max_x - y, x).unwrap()
+        }))
+    }
+
+    pub fn transform(&self, transform: Transform) -> Result<Bitmap, BitmapError> {
+        match transform {
+            Transform::FlipHorizontal => Ok(self.flip_horizontal()),
+            Transform::FlipVertical => Ok(self.flip_vertical()),
+            Transform::Rotate90 => self.rotate_90(),
+            Transform::Rotate180 => Ok(self.rotate_180()),
+            Transform::Rotate270 => self.rotate_270(),
+        }
+    }
+
+    fn check_rotated(&self) -> Result<(), BitmapError> {
+        if self.height > MAX_BITMAP_WIDTH {
+            Err(BitmapError::InvalidDimensions {
+                width: self.height,
+                height: self.width,
+            })
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl OriginDimensions for BitmapRef<'_> {
@@ -589,6 +1204,35 @@ impl<'data> BitmapRefMut<'data> {
         }
     }
 
+    /// Copies the opaque pixels of `src` onto this bitmap with the top left corner of `src` at
+    /// (`x`, `y`). Pixels under transparent pixels of `src` are left unchanged, and any part of
+    /// `src` outside of this bitmap is clipped.
+    pub fn blit(&mut self, src: &BitmapRef<'_>, x: i32, y: i32) {
+        // the range of `src` coordinates which land inside this bitmap on each axis. This is
+        // done in i64, since negating or subtracting from offsets near the ends of i32 overflows.
+        let src_range = |offset: i32, src_len: u8, dst_len: u8| {
+            let offset = offset as i64;
+            let start = (-offset).clamp(0, src_len as i64);
+            let end = (dst_len as i64 - offset).clamp(start, src_len as i64);
+
+            start as u8..end as u8
+        };
+
+        for src_y in src_range(y, src.height, self.height) {
+            for src_x in src_range(x, src.width, self.width) {
+                let color = src.get_pixel(src_x, src_y).unwrap();
+
+                if color != PixelColor::Transparent {
+                    // the ranges above ensure these are within this bitmap's bounds.
+                    let dst_x = (x + src_x as i32) as u8;
+                    let dst_y = (y + src_y as i32) as u8;
+
+                    self.set_pixel(dst_x, dst_y, color);
+                }
+            }
+        }
+    }
+
     pub fn pixels(&self) -> Pixels<'_> {
         Pixels {
             x: 0,
@@ -672,6 +1316,19 @@ pub enum BitmapError {
     LengthMismatch { expected: usize, actual: usize },
     #[error("decompression error: {0:?}")]
     DecompressionFailed(TINFLStatus),
+    #[error("run-length encoded data doesn't match the bitmap dimensions")]
+    InvalidRunLength,
+    #[error("unknown bitmap format tag {0:?}")]
+    UnknownFormat(Option<u8>),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Transform {
+    FlipHorizontal,
+    FlipVertical,
+    Rotate90,
+    Rotate180,
+    Rotate270,
 }
 
 #[repr(u8)]
@@ -699,3 +1356,484 @@ impl TryFrom<u32> for PixelColor {
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Error)]
 #[error("invalid pixel color")]
 pub struct InvalidPixelColorError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cycles through every color, so neighbouring pixels in a byte always differ.
+    fn color_at(x: u8, y: u8) -> PixelColor {
+        match (x as usize + y as usize) % 3 {
+            0 => PixelColor::Black,
+            1 => PixelColor::White,
+            _ => PixelColor::Transparent,
+        }
+    }
+
+    #[test]
+    fn pixels_round_trip() {
+        // widths which aren't a multiple of 4 leave padding at the end of each row.
+        for (width, height) in [
+            (1, 1),
+            (4, 2),
+            (5, 3),
+            (8, 4),
+            (13, 7),
+            (MAX_BITMAP_WIDTH, 2),
+        ] {
+            let mut data = vec![0; expected_data_len(width, height)];
+
+            for y in 0..height {
+                for x in 0..width {
+                    set_pixel_internal(width, height, x, y, color_at(x, y), &mut data);
+                }
+            }
+
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(
+                        get_pixel_internal(width, height, x, y, &data),
+                        Some(color_at(x, y)),
+                        "pixel ({x}, {y}) of a {width}x{height} bitmap",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overwritten_pixels_round_trip() {
+        let (width, height) = (6, 2);
+        let mut data = vec![0; expected_data_len(width, height)];
+
+        for color in [
+            PixelColor::Transparent,
+            PixelColor::White,
+            PixelColor::Black,
+        ] {
+            for y in 0..height {
+                for x in 0..width {
+                    set_pixel_internal(width, height, x, y, color, &mut data);
+                    assert_eq!(get_pixel_internal(width, height, x, y, &data), Some(color));
+                }
+            }
+        }
+
+        assert!(data.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn pixels_at_each_position_in_a_byte() {
+        let (width, height) = (8, 1);
+
+        for color in [PixelColor::White, PixelColor::Transparent] {
+            for x in 0..4 {
+                let mut data = vec![0; expected_data_len(width, height)];
+                set_pixel_internal(width, height, x, 0, color, &mut data);
+
+                // the first pixel is stored in the high bits.
+                let shift = 6 - 2 * x;
+                assert_eq!(data[0], (color as u8) << shift, "{color:?} at x = {x}");
+                assert_eq!(data[1], 0);
+
+                for other in 0..width {
+                    let expected = if other == x { color } else { PixelColor::Black };
+                    assert_eq!(
+                        get_pixel_internal(width, height, other, 0, &data),
+                        Some(expected)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unused_bit_pattern_reads_as_transparent() {
+        for x in 0..4 {
+            let data = [0b10 << (6 - 2 * x)];
+
+            assert_eq!(
+                get_pixel_internal(4, 1, x, 0, &data),
+                Some(PixelColor::Transparent),
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_pixels() {
+        let (width, height) = (5, 3);
+        let mut data = vec![0; expected_data_len(width, height)];
+
+        set_pixel_internal(width, height, width, 0, PixelColor::White, &mut data);
+        set_pixel_internal(width, height, 0, height, PixelColor::White, &mut data);
+
+        assert!(data.iter().all(|&byte| byte == 0));
+        assert_eq!(get_pixel_internal(width, height, width, 0, &data), None);
+        assert_eq!(get_pixel_internal(width, height, 0, height, &data), None);
+    }
+
+    fn bitmap(width: u8, height: u8) -> Bitmap {
+        bitmap_from_fn(width, height, color_at)
+    }
+
+    #[test]
+    fn compressed_bitmaps_too_wide_are_rejected() {
+        let width = MAX_BITMAP_WIDTH + 1;
+        let compressed = bitmap(4, 4).compress();
+        let mut encoded = compressed.to_encoded();
+        encoded[0] = width;
+
+        let invalid = Some(BitmapError::InvalidDimensions { width, height: 4 });
+
+        assert_eq!(CompressedBitmap::new(&encoded).err(), invalid);
+        assert_eq!(CompressedBitmapRef::from_encoded(&encoded).err(), invalid);
+    }
+
+    #[test]
+    fn compressed_bitmaps_with_the_wrong_length_are_rejected() {
+        // the data decompresses to a 4x4 bitmap, which is shorter than an 8x8 one.
+        let mut encoded = bitmap(4, 4).compress().to_encoded();
+        encoded[..2].copy_from_slice(&[8, 8]);
+
+        let mismatch = Err(BitmapError::LengthMismatch {
+            expected: expected_data_len(8, 8),
+            actual: expected_data_len(4, 4),
+        });
+
+        let compressed = CompressedBitmapRef::from_encoded(&encoded).unwrap();
+        let owned = CompressedBitmap::new(&encoded).unwrap();
+        let mut buf = bitmap_buffer();
+
+        assert_eq!(compressed.decompress_to_ref(&mut buf).map(|_| ()), mismatch);
+        assert_eq!(compressed.decompress().map(|_| ()), mismatch);
+        assert_eq!(owned.decompress().map(|_| ()), mismatch);
+    }
+
+    #[test]
+    fn corrupt_compressed_bitmaps_are_rejected() {
+        let encoded = [4, 4, 0xde, 0xad, 0xbe, 0xef];
+        let compressed = CompressedBitmapRef::from_encoded(&encoded).unwrap();
+
+        assert!(matches!(
+            compressed.decompress(),
+            Err(BitmapError::DecompressionFailed(_)),
+        ));
+    }
+
+    fn solid(width: u8, height: u8, color: PixelColor) -> Bitmap {
+        bitmap_from_fn(width, height, |_, _| color)
+    }
+
+    #[test]
+    fn upscaling_repeats_pixels() {
+        let original = bitmap(3, 2);
+        let scaled = original.scale(6, 6).unwrap();
+
+        assert_eq!((scaled.width(), scaled.height()), (6, 6));
+
+        for y in 0..6 {
+            for x in 0..6 {
+                assert_eq!(
+                    scaled.get_pixel(x, y),
+                    original.get_pixel(x / 2, y / 3),
+                    "pixel ({x}, {y})",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn downscaling_samples_pixels() {
+        let original = bitmap(9, 6);
+        let scaled = original.scale(3, 2).unwrap();
+
+        assert_eq!((scaled.width(), scaled.height()), (3, 2));
+
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(
+                    scaled.get_pixel(x, y),
+                    original.get_pixel(x * 3, y * 3),
+                    "pixel ({x}, {y})",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scaling_keeps_transparent_pixels() {
+        let mut original = solid(2, 2, PixelColor::Black);
+        original.set_pixel(1, 1, PixelColor::Transparent);
+
+        let scaled = original.scale(4, 4).unwrap();
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x >= 2 && y >= 2 {
+                    PixelColor::Transparent
+                } else {
+                    PixelColor::Black
+                };
+
+                assert_eq!(scaled.get_pixel(x, y), Some(expected), "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn scaling_to_invalid_dimensions_fails() {
+        let original = bitmap(4, 4);
+        let invalid = |width, height| Some(BitmapError::InvalidDimensions { width, height });
+
+        assert_eq!(original.scale(0, 4).err(), invalid(0, 4));
+        assert_eq!(original.scale(4, 0).err(), invalid(4, 0));
+        assert_eq!(
+            original.scale(MAX_BITMAP_WIDTH + 1, 4).err(),
+            invalid(MAX_BITMAP_WIDTH + 1, 4),
+        );
+        assert_eq!(bitmap(0, 4).scale(4, 4).err(), invalid(4, 4));
+        assert!(original.scale(MAX_BITMAP_WIDTH, u8::MAX).is_ok());
+    }
+
+    #[test]
+    fn rotating_90_then_270_is_the_identity() {
+        for (width, height) in [(4, 4), (5, 3), (3, 7), (13, 2)] {
+            let original = bitmap(width, height);
+
+            let rotated = original.rotate_90().unwrap();
+            assert_eq!(rotated.rotate_270().unwrap(), original);
+
+            let rotated = original.rotate_270().unwrap();
+            assert_eq!(rotated.rotate_90().unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn rotating_moves_pixels_clockwise() {
+        let original = bitmap(5, 3);
+        let rotated = original.rotate_90().unwrap();
+
+        // the left column becomes the top row, read from the bottom up.
+        for y in 0..5 {
+            for x in 0..3 {
+                assert_eq!(rotated.get_pixel(x, y), original.get_pixel(y, 2 - x));
+            }
+        }
+    }
+
+    #[test]
+    fn rotating_recomputes_the_stride() {
+        // a 5 pixel wide row takes 2 bytes, and a 3 pixel wide one takes 1.
+        let original = bitmap(5, 3);
+        let rotated = original.rotate_90().unwrap();
+
+        assert_eq!((rotated.width(), rotated.height()), (3, 5));
+        assert_eq!(original.as_ref().data().len(), 2 * 3);
+        assert_eq!(rotated.as_ref().data().len(), 5);
+        assert_eq!(
+            rotated.as_ref().data().len(),
+            expected_data_len(rotated.width(), rotated.height()),
+        );
+    }
+
+    #[test]
+    fn rotating_too_tall_bitmaps_fails() {
+        let original = bitmap(4, MAX_BITMAP_WIDTH + 1);
+        let invalid = Some(BitmapError::InvalidDimensions {
+            width: MAX_BITMAP_WIDTH + 1,
+            height: 4,
+        });
+
+        assert_eq!(original.rotate_90().err(), invalid);
+        assert_eq!(original.rotate_270().err(), invalid);
+        assert_eq!(original.transform(Transform::Rotate90).err(), invalid);
+    }
+
+    #[test]
+    fn flips_and_transforms() {
+        let original = bitmap(5, 3);
+
+        assert_eq!(original.flip_horizontal().flip_horizontal(), original);
+        assert_eq!(original.flip_vertical().flip_vertical(), original);
+        assert_eq!(
+            original.flip_horizontal().flip_vertical(),
+            original.rotate_180()
+        );
+        assert_eq!(
+            original.flip_horizontal().get_pixel(0, 1),
+            original.get_pixel(4, 1)
+        );
+        assert_eq!(
+            original.flip_vertical().get_pixel(1, 0),
+            original.get_pixel(1, 2)
+        );
+
+        for (transform, expected) in [
+            (Transform::FlipHorizontal, original.flip_horizontal()),
+            (Transform::FlipVertical, original.flip_vertical()),
+            (Transform::Rotate90, original.rotate_90().unwrap()),
+            (Transform::Rotate180, original.rotate_180()),
+            (Transform::Rotate270, original.rotate_270().unwrap()),
+        ] {
+            assert_eq!(original.transform(transform), Ok(expected), "{transform:?}");
+        }
+    }
+
+    // Blits a 2x2 black square onto a 4x4 white bitmap and returns the positions of the black
+    // pixels.
+    fn blit_square(x: i32, y: i32) -> Vec<(u8, u8)> {
+        let mut dst = solid(4, 4, PixelColor::White);
+        dst.as_mut()
+            .blit(&solid(2, 2, PixelColor::Black).as_ref(), x, y);
+
+        (0..4)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .filter(|&(x, y)| dst.get_pixel(x, y) == Some(PixelColor::Black))
+            .collect()
+    }
+
+    #[test]
+    fn blit_inside() {
+        assert_eq!(blit_square(1, 2), [(1, 2), (2, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn blit_clips_negative_offsets() {
+        assert_eq!(blit_square(-1, -1), [(0, 0)]);
+        assert_eq!(blit_square(-1, 1), [(0, 1), (0, 2)]);
+        assert_eq!(blit_square(-2, 0), []);
+        assert_eq!(blit_square(i32::MIN, i32::MIN), []);
+    }
+
+    #[test]
+    fn blit_clips_overflowing_offsets() {
+        assert_eq!(blit_square(3, 3), [(3, 3)]);
+        assert_eq!(blit_square(1, 3), [(1, 3), (2, 3)]);
+        assert_eq!(blit_square(4, 0), []);
+        assert_eq!(blit_square(i32::MAX, i32::MAX), []);
+    }
+
+    #[test]
+    fn blit_skips_transparent_pixels() {
+        let mut dst = bitmap(4, 4);
+        let mut src = solid(4, 4, PixelColor::Transparent);
+        src.set_pixel(1, 2, PixelColor::White);
+
+        dst.as_mut().blit(&src.as_ref(), 0, 0);
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if (x, y) == (1, 2) {
+                    PixelColor::White
+                } else {
+                    color_at(x, y)
+                };
+
+                assert_eq!(dst.get_pixel(x, y), Some(expected), "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        // runs longer than `RLE_MAX_RUN` are split, and so are runs crossing the end of a row.
+        let long_runs = bitmap_from_fn(100, 3, |x, _| {
+            if x < 70 {
+                PixelColor::Black
+            } else {
+                PixelColor::Transparent
+            }
+        });
+
+        for original in [
+            bitmap(5, 3),
+            bitmap(13, 7),
+            long_runs,
+            solid(4, 0, PixelColor::White),
+        ] {
+            let rle = original.compress_rle();
+            assert_eq!(rle.clone().decompress().unwrap(), original);
+
+            let encoded = rle.to_encoded();
+            assert_eq!(RleBitmap::new(&encoded).unwrap(), rle);
+
+            let tagged = [&[ENCODED_TAG_RLE], &encoded[..]].concat();
+            let decoded = EncodedBitmap::from_encoded(&tagged).unwrap();
+            assert!(matches!(decoded, EncodedBitmap::Rle(_)));
+            assert_eq!(decoded.decompress().unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn invalid_runs_are_rejected() {
+        let invalid = Some(BitmapError::InvalidRunLength);
+
+        // 0b10 isn't a color.
+        assert_eq!(RleBitmapRef::new(4, 1, &[0b1000_0011]).err(), invalid);
+        // a run of 4 pixels for a bitmap of 8.
+        assert_eq!(RleBitmapRef::new(4, 2, &[3]).err(), invalid);
+        // a second run past the end.
+        assert_eq!(RleBitmapRef::new(4, 1, &[3, 0]).err(), invalid);
+        assert_eq!(RleBitmap::new(&[4, 1, 3, 0]).err(), invalid);
+        assert_eq!(
+            RleBitmapRef::new(MAX_BITMAP_WIDTH + 1, 1, &[]).err(),
+            Some(BitmapError::InvalidDimensions {
+                width: MAX_BITMAP_WIDTH + 1,
+                height: 1,
+            }),
+        );
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        for original in [
+            bitmap(5, 3),
+            bitmap(MAX_BITMAP_WIDTH, 40),
+            solid(16, 16, PixelColor::Transparent),
+        ] {
+            let compressed = original.compress();
+            assert_eq!(
+                (compressed.width(), compressed.height()),
+                (original.width(), original.height())
+            );
+            assert_eq!(compressed.clone().decompress().unwrap(), original);
+
+            let mut buf = bitmap_buffer();
+            assert_eq!(
+                compressed.decompress_to_ref(&mut buf).unwrap(),
+                original.as_ref()
+            );
+
+            let tagged = [&[ENCODED_TAG_DEFLATE], &compressed.to_encoded()[..]].concat();
+            let decoded = EncodedBitmap::from_encoded(&tagged).unwrap();
+            assert!(matches!(decoded, EncodedBitmap::Deflate(_)));
+            assert_eq!(decoded.decompress().unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn encoded_round_trip() {
+        for original in [bitmap(1, 1), bitmap(5, 3), bitmap(MAX_BITMAP_WIDTH, 2)] {
+            let encoded = original.to_encoded();
+            assert_eq!(&encoded[..2], [original.width(), original.height()]);
+
+            assert_eq!(Bitmap::from_encoded(&encoded).unwrap(), original);
+            assert_eq!(
+                BitmapRef::from_encoded(&encoded).unwrap(),
+                original.as_ref()
+            );
+            assert_eq!(original.as_ref().to_encoded(), encoded);
+
+            let compressed = original.compress();
+            let encoded = compressed.to_encoded();
+            assert_eq!(CompressedBitmap::new(&encoded).unwrap(), compressed);
+            assert_eq!(
+                CompressedBitmapRef::from_encoded(&encoded)
+                    .unwrap()
+                    .decompress()
+                    .unwrap(),
+                original,
+            );
+        }
+    }
+}
@@ -1,7 +1,10 @@
+use super::envelope;
+use crate::float::FloatExt;
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::{Dimensions, DrawTarget, Point, Size};
 use embedded_graphics::primitives::{
-    Circle, Line, PrimitiveStyleBuilder, Rectangle, StrokeAlignment, Styled,
+    Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle,
+    StrokeAlignment, Styled,
 };
 use embedded_graphics::Drawable;
 
@@ -32,11 +35,28 @@ impl RadioButton {
     pub fn deselect(&mut self) {
         self.selected = false;
     }
+
+    fn styled(&self) -> Styled<Circle, PrimitiveStyle<BinaryColor>> {
+        let style = PrimitiveStyleBuilder::new()
+            .stroke_color(self.color)
+            .stroke_width(self.border_width as u32)
+            .stroke_alignment(StrokeAlignment::Inside)
+            .fill_color(if self.selected {
+                self.color
+            } else {
+                self.color.invert()
+            })
+            .build();
+
+        Styled::new(Circle::new(self.top_left, self.diameter as u32), style)
+    }
 }
 
 impl Dimensions for RadioButton {
     fn bounding_box(&self) -> Rectangle {
-        Circle::new(self.top_left, self.diameter as u32).bounding_box()
+        // Using the styled bounding box means the stroke is always accounted for, even if the
+        // stroke alignment is changed later.
+        self.styled().bounding_box()
     }
 }
 
@@ -49,20 +69,7 @@ impl Drawable for RadioButton {
     where
         D: DrawTarget<Color = Self::Color>,
     {
-        let style = PrimitiveStyleBuilder::new()
-            .stroke_color(self.color)
-            .stroke_width(self.border_width as u32)
-            .stroke_alignment(StrokeAlignment::Inside)
-            .fill_color(if self.selected {
-                self.color
-            } else {
-                self.color.invert()
-            })
-            .build();
-
-        let circle = Styled::new(Circle::new(self.top_left, self.diameter as u32), style);
-
-        circle.draw(target)?;
+        self.styled().draw(target)?;
 
         Ok(())
     }
@@ -103,11 +110,56 @@ impl Checkbox {
     pub fn deselect(&mut self) {
         self.selected = false;
     }
+
+    fn square(&self) -> Styled<Rectangle, PrimitiveStyle<BinaryColor>> {
+        let style = PrimitiveStyleBuilder::new()
+            .stroke_color(self.color)
+            .stroke_width(self.border_width as u32)
+            .stroke_alignment(StrokeAlignment::Inside)
+            .fill_color(self.color.invert())
+            .build();
+
+        Styled::new(
+            Rectangle::new(self.top_left, Size::new_equal(self.size as u32)),
+            style,
+        )
+    }
+
+    // The lines making up the "X" which marks the checkbox as selected, or `None` if the checkbox
+    // has a size of zero.
+    fn cross(&self) -> Option<[Styled<Line, PrimitiveStyle<BinaryColor>>; 2]> {
+        let square = Rectangle::new(self.top_left, Size::new_equal(self.size as u32));
+        let bottom_right = square.bottom_right()?;
+
+        let top_left = square.top_left;
+        let top_right = Point::new(bottom_right.x, top_left.y);
+        let bottom_left = Point::new(top_left.x, bottom_right.y);
+
+        let style = PrimitiveStyleBuilder::new()
+            .stroke_color(self.color)
+            .stroke_width(self.line_width as u32)
+            .build();
+
+        let tl_to_br = Styled::new(Line::new(top_left, bottom_right), style);
+        let bl_to_tr = Styled::new(Line::new(bottom_left, top_right), style);
+
+        Some([tl_to_br, bl_to_tr])
+    }
 }
 
 impl Dimensions for Checkbox {
     fn bounding_box(&self) -> Rectangle {
-        Rectangle::new(self.top_left, Size::new_equal(self.size as u32)).bounding_box()
+        // The lines of the "X" are centered on the corners of the square, so with a line width
+        // greater than 1 they poke out past the border. They're included even when the checkbox
+        // isn't selected so the bounds don't change when it's toggled.
+        let square = self.square().bounding_box();
+
+        self.cross()
+            .into_iter()
+            .flatten()
+            .fold(square, |bounds, line| {
+                envelope(&bounds, &line.bounding_box())
+            })
     }
 }
 
@@ -120,40 +172,208 @@ impl Drawable for Checkbox {
     where
         D: DrawTarget<Color = Self::Color>,
     {
+        self.square().draw(target)?;
+
+        // Draw the "X" to mark it as selected
+        if let Some([tl_to_br, bl_to_tr]) = self.cross() {
+            if self.selected {
+                tl_to_br.draw(target)?;
+                bl_to_tr.draw(target)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A switch with a knob which sits at the left of its track when off, and the right when on.
+///
+/// The knob can be animated between the two by calling [`ToggleSwitch::step`] before each draw,
+/// for example from a [`Dynamic`](super::misc::Dynamic) update function.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct ToggleSwitch {
+    top_left: Point,
+    size: Size,
+    border_width: u8,
+    on: bool,
+    // where the knob is drawn, from 0 (off) to 1 (on).
+    knob: f32,
+    color: BinaryColor,
+}
+
+impl ToggleSwitch {
+    pub fn new(top_left: Point, size: Size, border_width: u8, color: BinaryColor) -> Self {
+        Self {
+            top_left,
+            size,
+            border_width,
+            on: false,
+            knob: 0.0,
+            color,
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    /// Turns the switch on, moving the knob to the right immediately.
+    pub fn on(&mut self) {
+        self.set(true);
+        self.knob = 1.0;
+    }
+
+    /// Turns the switch off, moving the knob to the left immediately.
+    pub fn off(&mut self) {
+        self.set(false);
+        self.knob = 0.0;
+    }
+
+    /// Flips the switch without moving the knob, so it can be animated with
+    /// [`ToggleSwitch::step`].
+    pub fn toggle(&mut self) {
+        self.set(!self.on);
+    }
+
+    pub fn set(&mut self, on: bool) {
+        self.on = on;
+    }
+
+    /// Moves the knob up to `amount` of the way across the track towards the side matching the
+    /// switch's state, and returns whether it's still moving.
+    pub fn step(&mut self, amount: f32) -> bool {
+        let target = if self.on { 1.0 } else { 0.0 };
+        let amount = if amount.is_nan() { 0.0 } else { amount.abs() };
+
+        self.knob = if self.knob < target {
+            (self.knob + amount).min(target)
+        } else {
+            (self.knob - amount).max(target)
+        };
+
+        self.knob != target
+    }
+
+    fn track(&self) -> Styled<RoundedRectangle, PrimitiveStyle<BinaryColor>> {
+        // the track fills in once the knob is most of the way to the on side.
+        let fill = if self.knob >= 0.5 {
+            self.color
+        } else {
+            self.color.invert()
+        };
+
         let style = PrimitiveStyleBuilder::new()
             .stroke_color(self.color)
             .stroke_width(self.border_width as u32)
             .stroke_alignment(StrokeAlignment::Inside)
-            .fill_color(self.color.invert())
+            .fill_color(fill)
             .build();
 
-        let square = Styled::new(
-            Rectangle::new(self.top_left, Size::new_equal(self.size as u32)),
+        let rectangle = Rectangle::new(self.top_left, self.size);
+        let radius = Size::new_equal(self.size.height / 2);
+
+        Styled::new(
+            RoundedRectangle::with_equal_corners(rectangle, radius),
             style,
-        );
+        )
+    }
 
-        square.draw(target)?;
+    fn knob(&self) -> Styled<Circle, PrimitiveStyle<BinaryColor>> {
+        // leave a one pixel gap between the knob and the border.
+        let inset = self.border_width as u32 + 1;
+        let diameter = self.size.height.saturating_sub(inset * 2);
+        let travel = self.size.width.saturating_sub(inset * 2 + diameter);
 
-        // Draw the "X" to mark it as selected
-        if let Some(bottom_right) = square.primitive.bottom_right() {
-            if self.selected {
-                let top_left = square.primitive.top_left;
-                let top_right = Point::new(bottom_right.x, top_left.y);
-                let bottom_left = Point::new(top_left.x, bottom_right.y);
+        let x = (travel as f32 * self.knob).round() as i32;
+        let top_left = self.top_left + Point::new(inset as i32 + x, inset as i32);
 
-                let style = PrimitiveStyleBuilder::new()
-                    .stroke_color(self.color)
-                    .stroke_width(self.line_width as u32)
-                    .build();
+        let color = if self.knob >= 0.5 {
+            self.color.invert()
+        } else {
+            self.color
+        };
 
-                let tl_to_br = Styled::new(Line::new(top_left, bottom_right), style);
-                let bl_to_tr = Styled::new(Line::new(bottom_left, top_right), style);
+        Styled::new(
+            Circle::new(top_left, diameter),
+            PrimitiveStyle::with_fill(color),
+        )
+    }
+}
 
-                tl_to_br.draw(target)?;
-                bl_to_tr.draw(target)?;
-            }
-        }
+impl Dimensions for ToggleSwitch {
+    fn bounding_box(&self) -> Rectangle {
+        // The knob is always inside the track.
+        self.track().bounding_box()
+    }
+}
+
+impl Drawable for ToggleSwitch {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.track().draw(target)?;
+        self.knob().draw(target)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+
+    // Draws `widget` and checks that every pixel it touched is inside its bounding box.
+    fn assert_bounds_enclose_drawing<W>(widget: &W)
+    where
+        W: Drawable<Color = BinaryColor> + Dimensions,
+    {
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        widget.draw(&mut display).unwrap();
+
+        let drawn = display.affected_area();
+        let bounds = widget.bounding_box();
+
+        assert_eq!(
+            envelope(&bounds, &drawn),
+            bounds,
+            "{drawn:?} isn't inside {bounds:?}"
+        );
+    }
+
+    #[test]
+    fn radio_button_bounds_enclose_the_stroke() {
+        for border_width in [1, 3, 8] {
+            let mut button =
+                RadioButton::new(Point::new(20, 20), 17, border_width, BinaryColor::On);
+            assert_bounds_enclose_drawing(&button);
+
+            button.select();
+            assert_bounds_enclose_drawing(&button);
+        }
+    }
+
+    #[test]
+    fn checkbox_bounds_enclose_the_stroke_and_cross() {
+        for (border_width, line_width) in [(1, 1), (2, 5), (4, 9)] {
+            let mut checkbox = Checkbox::new(
+                Point::new(20, 20),
+                16,
+                border_width,
+                line_width,
+                BinaryColor::On,
+            );
+            assert_bounds_enclose_drawing(&checkbox);
+
+            // the cross is centered on the corners, so wide lines reach past the square.
+            checkbox.select();
+            assert_bounds_enclose_drawing(&checkbox);
+        }
+    }
+}
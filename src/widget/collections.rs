@@ -1,7 +1,13 @@
-use super::Widget;
+use super::{envelope, Widget};
 use crate::driver::lcd::LcdBuffer;
 use alloc::boxed::Box;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt;
+use core::slice;
+use embedded_graphics::prelude::{Dimensions, Point, Transform};
+use embedded_graphics::primitives::Rectangle;
 use heapless::Vec as ConstVec;
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
@@ -29,6 +35,16 @@ impl<W: Widget, const N: usize> Widget for ConstTypedContainer<W, N> {
             widget.render(buffer);
         }
     }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        for widget in self.0.iter() {
+            widget.render_clipped(buffer, offset, area);
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        combined_bounds(self.0.iter())
+    }
 }
 
 impl<W, const N: usize> FromIterator<W> for ConstTypedContainer<W, N> {
@@ -49,6 +65,28 @@ impl<W> TypedContainer<W> {
         self.0.push(widget);
         self
     }
+
+    pub fn push(&mut self, widget: W) {
+        self.0.push(widget);
+    }
+
+    /// Inserts `widget` at `index`, shifting the widgets after it back.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than the number of widgets.
+    pub fn insert(&mut self, index: usize, widget: W) {
+        self.0.insert(index, widget);
+    }
+
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> W {
+        self.0.remove(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
 }
 
 impl<W: Widget> Widget for TypedContainer<W> {
@@ -57,6 +95,16 @@ impl<W: Widget> Widget for TypedContainer<W> {
             widget.render(buffer);
         }
     }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        for widget in self.0.iter() {
+            widget.render_clipped(buffer, offset, area);
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        combined_bounds(self.0.iter())
+    }
 }
 
 impl<W> FromIterator<W> for TypedContainer<W> {
@@ -93,6 +141,16 @@ impl<const N: usize> Widget for ConstContainer<N> {
             widget.render(buffer);
         }
     }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        for widget in self.0.iter() {
+            widget.render_clipped(buffer, offset, area);
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        combined_bounds(self.0.iter().map(|widget| &**widget))
+    }
 }
 
 impl<const N: usize> FromIterator<Box<dyn Widget>> for ConstContainer<N> {
@@ -101,36 +159,425 @@ impl<const N: usize> FromIterator<Box<dyn Widget>> for ConstContainer<N> {
     }
 }
 
+/// A container of widgets with a z-order. Widgets with a higher z are drawn later, so they're
+/// drawn over widgets with a lower z. Widgets with the same z are drawn in the order they were
+/// added.
 #[derive(Default)]
-pub struct Container(pub Vec<Box<dyn Widget>>);
+pub struct Container {
+    // Both are kept sorted by z, so rendering can just go in order.
+    widgets: Vec<Box<dyn Widget>>,
+    z: Vec<i32>,
+}
 
 impl Container {
     pub const fn new() -> Self {
-        Self(Vec::new())
+        Self {
+            widgets: Vec::new(),
+            z: Vec::new(),
+        }
     }
 
-    pub fn with_widget<W: Widget + 'static>(mut self, widget: W) -> Self {
-        self.0.push(Box::new(widget));
+    pub fn with_widget<W: Widget + 'static>(self, widget: W) -> Self {
+        self.with_widget_z(widget, 0)
+    }
+
+    pub fn with_widget_z<W: Widget + 'static>(mut self, widget: W, z: i32) -> Self {
+        self.push_with_z(widget, z);
         self
     }
+
+    pub fn push<W: Widget + 'static>(&mut self, widget: W) {
+        self.push_with_z(widget, 0);
+    }
+
+    pub fn push_with_z<W: Widget + 'static>(&mut self, widget: W, z: i32) {
+        self.insert_boxed(Box::new(widget), z);
+    }
+
+    /// Returns the z of the widget at `index` in drawing order.
+    pub fn z(&self, index: usize) -> Option<i32> {
+        self.z.get(index).copied()
+    }
+
+    /// Changes the z of the widget at `index` in drawing order. It's drawn after any other widgets
+    /// with the same z.
+    pub fn set_z(&mut self, index: usize, z: i32) {
+        if index < self.widgets.len() {
+            let widget = self.widgets.remove(index);
+            self.z.remove(index);
+            self.insert_boxed(widget, z);
+        }
+    }
+
+    /// Moves the widget at `index` in drawing order above every other widget.
+    pub fn bring_to_front(&mut self, index: usize) {
+        if let Some(&top) = self.z.last() {
+            self.set_z(index, top);
+        }
+    }
+
+    /// Inserts `widget` at `index` in drawing order. It takes the z of the widget it's inserted
+    /// before, or the z of the last widget if it's inserted at the end, so the order stays sorted.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than the number of widgets.
+    pub fn insert<W: Widget + 'static>(&mut self, index: usize, widget: W) {
+        let z = self.z.get(index).or(self.z.last()).copied().unwrap_or(0);
+
+        self.widgets.insert(index, Box::new(widget));
+        self.z.insert(index, z);
+    }
+
+    /// Removes the widget at `index` in drawing order.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Box<dyn Widget> {
+        self.z.remove(index);
+        self.widgets.remove(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.widgets.clear();
+        self.z.clear();
+    }
+
+    fn insert_boxed(&mut self, widget: Box<dyn Widget>, z: i32) {
+        // Inserting after every widget with a z less than or equal to this one keeps the order
+        // stable.
+        let index = self.z.partition_point(|&other| other <= z);
+
+        self.widgets.insert(index, widget);
+        self.z.insert(index, z);
+    }
 }
 
 impl Widget for Container {
     fn render(&self, buffer: &mut LcdBuffer) {
-        for widget in self.0.iter() {
+        for widget in self.widgets.iter() {
             widget.render(buffer);
         }
     }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        for widget in self.widgets.iter() {
+            widget.render_clipped(buffer, offset, area);
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        combined_bounds(self.widgets.iter().map(|widget| &**widget))
+    }
 }
 
 impl FromIterator<Box<dyn Widget>> for Container {
     fn from_iter<T: IntoIterator<Item = Box<dyn Widget>>>(iter: T) -> Self {
-        Self(Vec::from_iter(iter))
+        let widgets = Vec::from_iter(iter);
+        let z = vec![0; widgets.len()];
+
+        Self { widgets, z }
     }
 }
 
+// Collection methods shared by the containers. Indices are in drawing order.
+macro_rules! collection {
+    (impl[$($generics:tt)*] $name:ty, $field:tt: $storage:ty => $item:ty) => {
+        impl<$($generics)*> $name {
+            pub fn len(&self) -> usize {
+                self.$field.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.$field.is_empty()
+            }
+
+            pub fn get(&self, index: usize) -> Option<&$item> {
+                self.$field.get(index)
+            }
+
+            pub fn get_mut(&mut self, index: usize) -> Option<&mut $item> {
+                self.$field.get_mut(index)
+            }
+
+            pub fn iter(&self) -> slice::Iter<'_, $item> {
+                self.$field.iter()
+            }
+
+            pub fn iter_mut(&mut self) -> slice::IterMut<'_, $item> {
+                self.$field.iter_mut()
+            }
+        }
+
+        impl<$($generics)*> IntoIterator for $name {
+            type Item = $item;
+
+            type IntoIter = <$storage as IntoIterator>::IntoIter;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.$field.into_iter()
+            }
+        }
+
+        impl<'c, $($generics)*> IntoIterator for &'c $name {
+            type Item = &'c $item;
+
+            type IntoIter = slice::Iter<'c, $item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter()
+            }
+        }
+
+        impl<'c, $($generics)*> IntoIterator for &'c mut $name {
+            type Item = &'c mut $item;
+
+            type IntoIter = slice::IterMut<'c, $item>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter_mut()
+            }
+        }
+    };
+}
+
+collection!(impl[W, const N: usize] ConstTypedContainer<W, N>, 0: ConstVec<W, N> => W);
+collection!(impl[W] TypedContainer<W>, 0: Vec<W> => W);
+collection!(
+    impl[const N: usize] ConstContainer<N>,
+    0: ConstVec<Box<dyn Widget>, N> => Box<dyn Widget>
+);
+collection!(impl[] Container, widgets: Vec<Box<dyn Widget>> => Box<dyn Widget>);
+
+/// A vertically scrolling list of widgets. Widgets keep the positions they were created with, and
+/// the list moves them up by `scroll_offset` and only draws what's inside `viewport`.
+pub struct ScrollList {
+    widgets: Vec<Box<dyn Widget>>,
+    scroll_offset: i32,
+    viewport: Rectangle,
+}
+
+impl ScrollList {
+    pub const fn new(viewport: Rectangle) -> Self {
+        Self {
+            widgets: Vec::new(),
+            scroll_offset: 0,
+            viewport,
+        }
+    }
+
+    pub fn with_widget<W: Widget + 'static>(mut self, widget: W) -> Self {
+        self.push(widget);
+        self
+    }
+
+    pub fn push<W: Widget + 'static>(&mut self, widget: W) {
+        self.widgets.push(Box::new(widget));
+    }
+
+    pub fn viewport(&self) -> Rectangle {
+        self.viewport
+    }
+
+    pub fn scroll_offset(&self) -> i32 {
+        self.scroll_offset
+    }
+
+    /// Scrolls the list by `delta` pixels, where positive values move the contents up. The offset
+    /// isn't limited to [`ScrollList::max_scroll`], so overscrolling can be animated.
+    pub fn scroll_by(&mut self, delta: i32) {
+        self.scroll_offset = self.scroll_offset.saturating_add(delta);
+    }
+
+    pub fn scroll_to(&mut self, offset: i32) {
+        self.scroll_offset = offset;
+    }
+
+    /// The distance from the top of the viewport to the bottom of the lowest widget, or 0 if
+    /// there are no widgets below the top of the viewport.
+    pub fn content_height(&self) -> u32 {
+        let top = self.viewport.top_left.y;
+
+        self.widgets
+            .iter()
+            .filter_map(|widget| widget.bounding_box().bottom_right())
+            .map(|bottom_right| bottom_right.y + 1 - top)
+            .max()
+            .unwrap_or(0)
+            .max(0) as u32
+    }
+
+    /// The largest scroll offset which still has content at the bottom of the viewport.
+    pub fn max_scroll(&self) -> i32 {
+        self.content_height()
+            .saturating_sub(self.viewport.size.height) as i32
+    }
+}
+
+impl Widget for ScrollList {
+    fn render(&self, buffer: &mut LcdBuffer) {
+        let area = buffer.bounding_box();
+
+        self.render_clipped(buffer, Point::zero(), &area);
+    }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        // The viewport moves with the list, but the contents also move by the scroll offset.
+        let area = area.intersection(&self.viewport.translate(offset));
+        let offset = offset - Point::new(0, self.scroll_offset);
+
+        for widget in self.widgets.iter() {
+            let bounds = widget.bounding_box().translate(offset);
+
+            // Skip anything which is scrolled out of view entirely.
+            if !bounds.intersection(&area).is_zero_sized() {
+                widget.render_clipped(buffer, offset, &area);
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        self.viewport
+    }
+}
+
+/// A container which places its widgets in a column, starting at `origin` with `spacing` pixels
+/// between each one. Widgets are moved based on their bounding boxes, so they can be created at
+/// any position.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct VStack<W = Box<dyn Widget>> {
+    widgets: Vec<W>,
+    origin: Point,
+    spacing: u32,
+}
+
+/// A container which places its widgets in a row, starting at `origin` with `spacing` pixels
+/// between each one. See [`VStack`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct HStack<W = Box<dyn Widget>> {
+    widgets: Vec<W>,
+    origin: Point,
+    spacing: u32,
+}
+
+macro_rules! stack {
+    ($name:ident, $axis:expr) => {
+        impl<W> $name<W> {
+            pub const fn new(origin: Point, spacing: u32) -> Self {
+                Self {
+                    widgets: Vec::new(),
+                    origin,
+                    spacing,
+                }
+            }
+
+            pub fn with_widget(mut self, widget: W) -> Self {
+                self.widgets.push(widget);
+                self
+            }
+
+            pub fn push(&mut self, widget: W) {
+                self.widgets.push(widget);
+            }
+        }
+
+        impl<W: Widget> $name<W> {
+            // Each widget along with how far it needs to be moved to be in its place.
+            fn placements(&self) -> impl Iterator<Item = (&W, Point)> {
+                placements(&self.widgets, self.origin, self.spacing, $axis)
+            }
+        }
+
+        impl<W: Widget> Widget for $name<W> {
+            fn render(&self, buffer: &mut LcdBuffer) {
+                let area = buffer.bounding_box();
+
+                self.render_clipped(buffer, Point::zero(), &area);
+            }
+
+            fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+                for (widget, placement) in self.placements() {
+                    widget.render_clipped(buffer, offset + placement, area);
+                }
+            }
+
+            fn bounding_box(&self) -> Rectangle {
+                self.placements()
+                    .fold(Rectangle::zero(), |bounds, (widget, placement)| {
+                        envelope(&bounds, &widget.bounding_box().translate(placement))
+                    })
+            }
+        }
+    };
+}
+
+stack!(VStack, Axis::Vertical);
+stack!(HStack, Axis::Horizontal);
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+fn placements<W: Widget>(
+    widgets: &[W],
+    origin: Point,
+    spacing: u32,
+    axis: Axis,
+) -> impl Iterator<Item = (&W, Point)> {
+    let mut cursor = origin;
+
+    widgets.iter().map(move |widget| {
+        let bounds = widget.bounding_box();
+        let placement = cursor - bounds.top_left;
+
+        match axis {
+            Axis::Vertical => cursor.y += (bounds.size.height + spacing) as i32,
+            Axis::Horizontal => cursor.x += (bounds.size.width + spacing) as i32,
+        }
+
+        (widget, placement)
+    })
+}
+
+// The smallest rectangle enclosing all of `widgets`.
+fn combined_bounds<'w, W>(widgets: impl Iterator<Item = &'w W>) -> Rectangle
+where
+    W: Widget + ?Sized + 'w,
+{
+    widgets.fold(Rectangle::zero(), |bounds, widget| {
+        envelope(&bounds, &widget.bounding_box())
+    })
+}
+
+/// Returned when adding a widget to a container which is already holding `N` widgets. Both the
+/// container and the widget are handed back, so neither is lost.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
 pub struct CapacityError<C, W, const N: usize> {
     container: C,
     widget: W,
 }
+
+impl<C, W, const N: usize> CapacityError<C, W, N> {
+    /// The widget which couldn't be added.
+    pub fn widget(&self) -> &W {
+        &self.widget
+    }
+
+    pub fn into_widget(self) -> W {
+        self.widget
+    }
+
+    /// Returns the unchanged container and the widget which couldn't be added.
+    pub fn into_parts(self) -> (C, W) {
+        (self.container, self.widget)
+    }
+}
+
+impl<C, W, const N: usize> fmt::Display for CapacityError<C, W, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "container is full, it can only hold {N} widgets")
+    }
+}
+
+impl<C: fmt::Debug, W: fmt::Debug, const N: usize> Error for CapacityError<C, W, N> {}
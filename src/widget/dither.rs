@@ -0,0 +1,76 @@
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Point, Size};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::{Drawable, Pixel};
+
+// A 4x4 Bayer matrix. Each entry is scaled to a threshold in the middle of its 16-value band, so
+// a flat gray of `n` turns roughly `n / 256` of the pixels white.
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// An 8-bit grayscale image, where 0 is black and 255 is white, drawn to the 1-bit display with
+/// ordered dithering. Pixels are stored row by row.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct DitheredImage<'a> {
+    top_left: Point,
+    size: Size,
+    data: &'a [u8],
+}
+
+impl<'a> DitheredImage<'a> {
+    /// Returns `None` if `data` doesn't hold exactly `width * height` pixels.
+    pub fn new(top_left: Point, width: u32, height: u32, data: &'a [u8]) -> Option<Self> {
+        if data.len() as u64 != width as u64 * height as u64 {
+            return None;
+        }
+
+        Some(Self {
+            top_left,
+            size: Size::new(width, height),
+            data,
+        })
+    }
+
+    // The pattern is based on the position within the image, so it doesn't shimmer when the image
+    // moves.
+    fn dither(x: usize, y: usize, gray: u8) -> BinaryColor {
+        let threshold = BAYER_4X4[y % 4][x % 4] * 16 + 8;
+
+        if gray < threshold {
+            BinaryColor::On
+        } else {
+            BinaryColor::Off
+        }
+    }
+}
+
+impl Dimensions for DitheredImage<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(self.top_left, self.size)
+    }
+}
+
+impl Drawable for DitheredImage<'_> {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let width = self.size.width as usize;
+
+        if width == 0 {
+            return Ok(());
+        }
+
+        let pixels = self.data.iter().enumerate().map(|(i, &gray)| {
+            let (x, y) = (i % width, i / width);
+            let point = self.top_left + Point::new(x as i32, y as i32);
+
+            Pixel(point, Self::dither(x, y, gray))
+        });
+
+        target.draw_iter(pixels)
+    }
+}
@@ -0,0 +1,62 @@
+use super::envelope;
+use alloc::vec::Vec;
+use embedded_graphics::primitives::Rectangle;
+
+/// Collects the regions of the screen that widgets have changed during a frame, so only the
+/// widgets overlapping those regions need to be redrawn.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Invalidator {
+    regions: Vec<Rectangle>,
+}
+
+impl Invalidator {
+    pub const fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+        }
+    }
+
+    /// Marks `region` as changed. Zero sized regions are ignored, and regions already covered by a
+    /// previously invalidated region aren't stored again.
+    pub fn invalidate(&mut self, region: Rectangle) {
+        if region.is_zero_sized() || self.regions.iter().any(|r| contains(r, &region)) {
+            return;
+        }
+
+        self.regions.retain(|r| !contains(&region, r));
+        self.regions.push(region);
+    }
+
+    /// Returns whether any part of `area` overlaps an invalidated region.
+    pub fn is_invalidated(&self, area: &Rectangle) -> bool {
+        self.regions
+            .iter()
+            .any(|r| !r.intersection(area).is_zero_sized())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn regions(&self) -> &[Rectangle] {
+        &self.regions
+    }
+
+    /// Returns the smallest rectangle enclosing every invalidated region, or `None` if nothing has
+    /// been invalidated.
+    pub fn bounds(&self) -> Option<Rectangle> {
+        self.regions.iter().copied().reduce(|a, b| envelope(&a, &b))
+    }
+
+    /// Clears the invalidated regions, usually once the frame they were collected for is drawn.
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+}
+
+fn contains(outer: &Rectangle, inner: &Rectangle) -> bool {
+    match inner.bottom_right() {
+        Some(bottom_right) => outer.contains(inner.top_left) && outer.contains(bottom_right),
+        None => true,
+    }
+}
@@ -1,7 +1,40 @@
-use core::cell::{Ref, RefCell, RefMut};
+use super::envelope;
+use super::invalidate::Invalidator;
+use core::cell::{Cell, Ref, RefCell, RefMut};
 use core::marker::PhantomData;
-use embedded_graphics::prelude::{DrawTarget, PixelColor, PixelIteratorExt};
-use embedded_graphics::Drawable;
+use embassy_time::{Duration, Instant};
+use embedded_graphics::prelude::{Dimensions, DrawTarget, PixelColor, PixelIteratorExt, Size};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::{Drawable, Pixel};
+
+/// The update function of a [`Dynamic`] widget. It's implemented for closures taking the widget
+/// and its state, and for [`Timed`] closures which also take the time since the last update.
+pub trait Update<D, S> {
+    fn update(&mut self, widget: &mut D, state: &mut S, elapsed: Duration);
+}
+
+impl<D, S, F> Update<D, S> for F
+where
+    F: FnMut(&mut D, &mut S),
+{
+    fn update(&mut self, widget: &mut D, state: &mut S, _: Duration) {
+        self(widget, state)
+    }
+}
+
+/// An update function which is passed the time since the widget was last updated, so animations
+/// can run at the same speed no matter how often the display refreshes.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Timed<F>(pub F);
+
+impl<D, S, F> Update<D, S> for Timed<F>
+where
+    F: FnMut(&mut D, &mut S, Duration),
+{
+    fn update(&mut self, widget: &mut D, state: &mut S, elapsed: Duration) {
+        (self.0)(widget, state, elapsed)
+    }
+}
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub struct Dynamic<D, F, S> {
@@ -9,6 +42,8 @@ pub struct Dynamic<D, F, S> {
     widget: RefCell<D>,
     state: RefCell<S>,
     f: RefCell<F>,
+    changed: Cell<bool>,
+    last_update: Cell<Option<Instant>>,
 }
 
 impl<D, F, S> Dynamic<D, F, S>
@@ -16,11 +51,31 @@ where
     F: FnMut(&mut D, &mut S),
 {
     pub fn new(mode: UpdateMode, widget: D, state: S, f: F) -> Self {
+        Self::with_update(mode, widget, state, f)
+    }
+}
+
+impl<D, G, S> Dynamic<D, Timed<G>, S>
+where
+    G: FnMut(&mut D, &mut S, Duration),
+{
+    /// Creates a widget whose update function is also passed the time since the last update. The
+    /// first update is passed a duration of 0.
+    pub fn timed(mode: UpdateMode, widget: D, state: S, f: G) -> Self {
+        Self::with_update(mode, widget, state, Timed(f))
+    }
+}
+
+impl<D, F, S> Dynamic<D, F, S> {
+    fn with_update(mode: UpdateMode, widget: D, state: S, f: F) -> Self {
         Self {
             mode,
             widget: RefCell::new(widget),
             state: RefCell::new(state),
             f: RefCell::new(f),
+            // the widget hasn't been drawn yet, so it needs to be drawn at least once.
+            changed: Cell::new(true),
+            last_update: Cell::new(None),
         }
     }
 
@@ -29,20 +84,49 @@ where
     }
 
     pub fn state_mut(&self) -> RefMut<'_, S> {
+        self.changed.set(true);
         self.state.borrow_mut()
     }
 
     pub fn with_state(&self, f: impl FnOnce(&mut S)) {
+        self.changed.set(true);
         let mut state = self.state.borrow_mut();
         f(&mut state)
     }
+
+    /// Returns whether the state has been accessed mutably since the widget was last drawn.
+    pub fn changed(&self) -> bool {
+        self.changed.get()
+    }
+
+    // Returns the time since the last update, and records that an update is happening now.
+    fn elapsed(&self) -> Duration {
+        let now = Instant::now();
+
+        self.last_update
+            .replace(Some(now))
+            .map_or(Duration::from_ticks(0), |last| now - last)
+    }
+}
+
+impl<D, F, S> Dynamic<D, F, S>
+where
+    D: Dimensions,
+{
+    /// Reports the widget's bounding box to `invalidator` if its state has changed since it was
+    /// last drawn.
+    pub fn invalidate(&self, invalidator: &mut Invalidator) {
+        if self.changed() {
+            invalidator.invalidate(self.widget.borrow().bounding_box());
+        }
+    }
 }
 
 impl<T, F, S, C, O> Drawable for Dynamic<T, F, S>
 where
     T: Drawable<Color = C, Output = O>,
     C: PixelColor,
-    F: FnMut(&mut T, &mut S),
+    F: Update<T, S>,
 {
     type Color = <T as Drawable>::Color;
 
@@ -56,20 +140,31 @@ where
         let mut f = self.f.borrow_mut();
         let mut state = self.state.borrow_mut();
 
+        self.changed.set(false);
+
         match self.mode {
             UpdateMode::Before => {
-                f(&mut drawable, &mut state);
+                f.update(&mut drawable, &mut state, self.elapsed());
                 drawable.draw(target)
             }
             UpdateMode::After => {
                 let ret = drawable.draw(target);
-                f(&mut drawable, &mut state);
+                f.update(&mut drawable, &mut state, self.elapsed());
                 ret
             }
         }
     }
 }
 
+impl<D, F, S> Dimensions for Dynamic<D, F, S>
+where
+    D: Dimensions,
+{
+    fn bounding_box(&self) -> Rectangle {
+        self.widget.borrow().bounding_box()
+    }
+}
+
 pub struct DrawableIter<I, C>(pub I, PhantomData<C>);
 
 impl<I, C> DrawableIter<I, C> {
@@ -95,6 +190,21 @@ where
     }
 }
 
+impl<I, C> Dimensions for DrawableIter<I, C>
+where
+    I: IntoIterator<Item = Pixel<C>> + Clone,
+    C: PixelColor,
+{
+    fn bounding_box(&self) -> Rectangle {
+        // There's no way to know where the pixels are without going through all of them.
+        self.0
+            .clone()
+            .into_iter()
+            .map(|Pixel(point, _)| Rectangle::new(point, Size::new(1, 1)))
+            .fold(Rectangle::zero(), |bounds, pixel| envelope(&bounds, &pixel))
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum UpdateMode {
     Before,
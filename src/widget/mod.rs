@@ -1,22 +1,176 @@
-use crate::driver::lcd::LcdBuffer;
+use crate::driver::lcd::{LcdBuffer, LCD_X, LCD_Y};
+use alloc::boxed::Box;
+use core::cmp::Ordering;
+use core::convert::Infallible;
 use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, DrawTargetExt, Point, Size};
+use embedded_graphics::primitives::{Rectangle, Triangle};
 use embedded_graphics::Drawable;
 
 pub mod bitmap;
 pub mod button;
 pub mod collections;
+pub mod dither;
+pub mod invalidate;
 pub mod misc;
+pub mod progress;
+pub mod slider;
+pub mod sprite;
 pub mod text;
 
 pub trait Widget {
     fn render(&self, buffer: &mut LcdBuffer);
+
+    /// Renders the widget moved by `offset`, only drawing the parts which end up inside `area`.
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle);
+
+    /// The area the widget draws to. By default this is the whole screen, since a widget could
+    /// draw anywhere.
+    fn bounding_box(&self) -> Rectangle {
+        Rectangle::new(Point::zero(), Size::new(LCD_X as u32, LCD_Y as u32))
+    }
+}
+
+// Drawing to an `LcdBuffer` can't fail. Matching on the error instead of ignoring it means this
+// stops compiling if that ever changes, rather than silently dropping errors.
+fn infallible<T>(result: Result<T, Infallible>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(never) => match never {},
+    }
 }
 
 impl<T, O> Widget for T
 where
-    T: Drawable<Color = BinaryColor, Output = O>,
+    T: Drawable<Color = BinaryColor, Output = O> + Dimensions,
 {
     fn render(&self, buffer: &mut LcdBuffer) {
-        let _ = self.draw(buffer);
+        infallible(self.draw(buffer));
+    }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        infallible(self.draw(&mut buffer.clipped(area).translated(offset)));
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        Dimensions::bounding_box(self)
+    }
+}
+
+impl Widget for Box<dyn Widget> {
+    fn render(&self, buffer: &mut LcdBuffer) {
+        (**self).render(buffer)
+    }
+
+    fn render_clipped(&self, buffer: &mut LcdBuffer, offset: Point, area: &Rectangle) {
+        (**self).render_clipped(buffer, offset, area)
+    }
+
+    fn bounding_box(&self) -> Rectangle {
+        (**self).bounding_box()
+    }
+}
+
+/// Returns the smallest rectangle enclosing both `a` and `b`. Zero sized rectangles don't contain
+/// any points, so they're ignored.
+pub(crate) fn envelope(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    match (a.bottom_right(), b.bottom_right()) {
+        (Some(a_br), Some(b_br)) => Rectangle::with_corners(
+            a.top_left.component_min(b.top_left),
+            a_br.component_max(b_br),
+        ),
+        (Some(_), None) => *a,
+        (None, _) => *b,
+    }
+}
+
+// embedded-graphics rasterizes triangles differently depending on the order of their vertices, so
+// the same triangle passed with a different winding or starting vertex can fill slightly different
+// pixels. Putting the vertices in a canonical order (topmost-leftmost first, then clockwise on
+// screen) makes the result independent of the order the app provided them in.
+pub(crate) fn normalize_winding(triangle: Triangle) -> Triangle {
+    let key = |p: Point| (p.y, p.x);
+
+    let mut vertices = triangle.vertices;
+    let first = (0..vertices.len())
+        .min_by_key(|&i| key(vertices[i]))
+        .unwrap_or(0);
+
+    // Rotating the vertices doesn't change the winding.
+    vertices.rotate_left(first);
+
+    let [a, b, c] = vertices.map(|p| (p.x as i64, p.y as i64));
+    let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+
+    let [a, b, c] = vertices;
+
+    match cross.cmp(&0) {
+        // Counter-clockwise on screen, since the y axis points down.
+        Ordering::Less => Triangle::new(a, c, b),
+        Ordering::Greater => Triangle::new(a, b, c),
+        // The vertices are colinear, so there's no winding. Order them along the line instead.
+        Ordering::Equal if key(c) < key(b) => Triangle::new(a, c, b),
+        Ordering::Equal => Triangle::new(a, b, c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mock_display::MockDisplay;
+    use embedded_graphics::primitives::{Primitive, PrimitiveStyleBuilder};
+
+    fn orders(a: Point, b: Point, c: Point) -> [Triangle; 6] {
+        [
+            Triangle::new(a, b, c),
+            Triangle::new(b, c, a),
+            Triangle::new(c, a, b),
+            Triangle::new(a, c, b),
+            Triangle::new(c, b, a),
+            Triangle::new(b, a, c),
+        ]
+    }
+
+    fn draw(triangle: Triangle) -> MockDisplay<BinaryColor> {
+        let style = PrimitiveStyleBuilder::new()
+            .fill_color(BinaryColor::On)
+            .stroke_color(BinaryColor::Off)
+            .stroke_width(1)
+            .build();
+
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        infallible(
+            normalize_winding(triangle)
+                .into_styled(style)
+                .draw(&mut display),
+        );
+        display
+    }
+
+    fn assert_order_independent(a: Point, b: Point, c: Point) {
+        let [first, rest @ ..] = orders(a, b, c);
+        let expected = normalize_winding(first);
+        let pixels = draw(first);
+
+        for triangle in rest {
+            assert_eq!(
+                normalize_winding(triangle).vertices,
+                expected.vertices,
+                "{:?}",
+                triangle.vertices
+            );
+            assert_eq!(draw(triangle), pixels, "{:?}", triangle.vertices);
+        }
+    }
+
+    #[test]
+    fn both_windings_fill_the_same_pixels() {
+        assert_order_independent(Point::new(3, 1), Point::new(40, 17), Point::new(11, 50));
+    }
+
+    #[test]
+    fn colinear_vertices_fill_the_same_pixels() {
+        assert_order_independent(Point::new(2, 2), Point::new(10, 6), Point::new(20, 11));
     }
 }
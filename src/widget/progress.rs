@@ -0,0 +1,102 @@
+use crate::float::FloatExt;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Point, Size};
+use embedded_graphics::primitives::{
+    PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, StrokeAlignment, Styled,
+};
+use embedded_graphics::Drawable;
+
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct ProgressBar {
+    top_left: Point,
+    size: Size,
+    border_width: u8,
+    progress: f32,
+    color: BinaryColor,
+}
+
+impl ProgressBar {
+    pub fn new(top_left: Point, size: Size, border_width: u8, color: BinaryColor) -> Self {
+        Self {
+            top_left,
+            size,
+            border_width,
+            progress: 0.0,
+            color,
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// Sets the progress, from 0 (empty) to 1 (full). Values outside that range are clamped, and
+    /// NaN is treated as 0.
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = if progress.is_nan() {
+            0.0
+        } else {
+            progress.clamp(0.0, 1.0)
+        };
+    }
+
+    fn outline(&self) -> Styled<Rectangle, PrimitiveStyle<BinaryColor>> {
+        let style = PrimitiveStyleBuilder::new()
+            .stroke_color(self.color)
+            .stroke_width(self.border_width as u32)
+            .stroke_alignment(StrokeAlignment::Inside)
+            .fill_color(self.color.invert())
+            .build();
+
+        Styled::new(Rectangle::new(self.top_left, self.size), style)
+    }
+
+    // The filled part of the bar inside the border, or `None` if there's nothing to fill.
+    fn fill(&self) -> Option<Styled<Rectangle, PrimitiveStyle<BinaryColor>>> {
+        let border = self.border_width as u32;
+        let inner = Size::new(
+            self.size.width.saturating_sub(border * 2),
+            self.size.height.saturating_sub(border * 2),
+        );
+
+        let width = (inner.width as f32 * self.progress).round() as u32;
+
+        if width == 0 || inner.height == 0 {
+            return None;
+        }
+
+        let top_left = self.top_left + Point::new(border as i32, border as i32);
+        let style = PrimitiveStyle::with_fill(self.color);
+
+        Some(Styled::new(
+            Rectangle::new(top_left, Size::new(width, inner.height)),
+            style,
+        ))
+    }
+}
+
+impl Dimensions for ProgressBar {
+    fn bounding_box(&self) -> Rectangle {
+        // The fill is always inside the border, so the outline covers everything.
+        self.outline().bounding_box()
+    }
+}
+
+impl Drawable for ProgressBar {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.outline().draw(target)?;
+
+        if let Some(fill) = self.fill() {
+            fill.draw(target)?;
+        }
+
+        Ok(())
+    }
+}
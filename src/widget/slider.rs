@@ -0,0 +1,130 @@
+use super::envelope;
+use crate::float::FloatExt;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Point};
+use embedded_graphics::primitives::{
+    Circle, Line, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, StrokeAlignment, Styled,
+};
+use embedded_graphics::Drawable;
+
+/// A horizontal slider, with a circular thumb which moves along a track. The thumb stays entirely
+/// within `width`, so its center moves from `thumb_diameter / 2` to `width - thumb_diameter / 2`.
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct Slider {
+    top_left: Point,
+    width: u32,
+    track_width: u8,
+    thumb_diameter: u8,
+    border_width: u8,
+    value: f32,
+    color: BinaryColor,
+}
+
+impl Slider {
+    pub fn new(
+        top_left: Point,
+        width: u32,
+        track_width: u8,
+        thumb_diameter: u8,
+        border_width: u8,
+        color: BinaryColor,
+    ) -> Self {
+        Self {
+            top_left,
+            width,
+            track_width,
+            thumb_diameter,
+            border_width,
+            value: 0.0,
+            color,
+        }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Sets the value, from 0 (thumb at the left) to 1 (thumb at the right). Values outside that
+    /// range are clamped, and NaN is treated as 0.
+    pub fn set_value(&mut self, value: f32) {
+        self.value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+    }
+
+    /// Returns the value which would put the center of the thumb closest to `x`, so touch or
+    /// cursor positions can be mapped to a value.
+    pub fn value_from_x(&self, x: i32) -> f32 {
+        let travel = self.travel();
+
+        if travel == 0 {
+            return 0.0;
+        }
+
+        let start = self.top_left.x + self.thumb_diameter as i32 / 2;
+
+        ((x - start) as f32 / travel as f32).clamp(0.0, 1.0)
+    }
+
+    // How far the thumb can move, in pixels.
+    fn travel(&self) -> u32 {
+        self.width.saturating_sub(self.thumb_diameter as u32)
+    }
+
+    fn track(&self) -> Styled<Line, PrimitiveStyle<BinaryColor>> {
+        let radius = self.thumb_diameter as i32 / 2;
+        let y = self.top_left.y + radius;
+
+        let start = Point::new(self.top_left.x + radius, y);
+        let end = Point::new(start.x + self.travel() as i32, y);
+
+        Styled::new(
+            Line::new(start, end),
+            PrimitiveStyle::with_stroke(self.color, self.track_width as u32),
+        )
+    }
+
+    fn thumb(&self) -> Styled<Circle, PrimitiveStyle<BinaryColor>> {
+        let offset = (self.travel() as f32 * self.value).round() as i32;
+
+        let style = PrimitiveStyleBuilder::new()
+            .stroke_color(self.color)
+            .stroke_width(self.border_width as u32)
+            .stroke_alignment(StrokeAlignment::Inside)
+            .fill_color(self.color.invert())
+            .build();
+
+        Styled::new(
+            Circle::new(
+                self.top_left + Point::new(offset, 0),
+                self.thumb_diameter as u32,
+            ),
+            style,
+        )
+    }
+}
+
+impl Dimensions for Slider {
+    fn bounding_box(&self) -> Rectangle {
+        // A track wider than the thumb pokes out above and below it.
+        envelope(&self.track().bounding_box(), &self.thumb().bounding_box())
+    }
+}
+
+impl Drawable for Slider {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.track().draw(target)?;
+        self.thumb().draw(target)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use embedded_graphics::image::{Image, ImageDrawable, ImageDrawableExt};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Point};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Drawable;
+
+/// Draws one region of an image, such as a frame of a sprite sheet.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Sprite<I> {
+    image: I,
+    source: Rectangle,
+    position: Point,
+}
+
+impl<I> Sprite<I>
+where
+    I: ImageDrawable<Color = BinaryColor>,
+{
+    /// Creates a sprite which draws the part of `image` inside `source`, with its top left corner
+    /// at `position`. Parts of `source` outside the image aren't drawn.
+    pub fn new(image: I, source: Rectangle, position: Point) -> Self {
+        Self {
+            image,
+            source,
+            position,
+        }
+    }
+
+    pub fn source(&self) -> Rectangle {
+        self.source
+    }
+
+    pub fn set_source(&mut self, source: Rectangle) {
+        self.source = source;
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Point) {
+        self.position = position;
+    }
+
+    /// Selects frame `index` of a sheet made of a grid of `columns` frames the size of the source
+    /// rectangle, counting left to right, then top to bottom.
+    pub fn set_frame(&mut self, index: u32, columns: u32) {
+        let columns = columns.max(1);
+        let size = self.source.size;
+
+        let x = (index % columns * size.width) as i32;
+        let y = (index / columns * size.height) as i32;
+
+        self.source.top_left = Point::new(x, y);
+    }
+
+    // The part of the source rectangle which is inside the image.
+    fn visible_source(&self) -> Rectangle {
+        self.source.intersection(&self.image.bounding_box())
+    }
+}
+
+impl<I> Dimensions for Sprite<I>
+where
+    I: ImageDrawable<Color = BinaryColor>,
+{
+    fn bounding_box(&self) -> Rectangle {
+        let visible = self.visible_source();
+        let offset = visible.top_left - self.source.top_left;
+
+        Rectangle::new(self.position + offset, visible.size)
+    }
+}
+
+impl<I> Drawable for Sprite<I>
+where
+    I: ImageDrawable<Color = BinaryColor>,
+{
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let visible = self.visible_source();
+        let sub_image = self.image.sub_image(&visible);
+
+        Image::new(&sub_image, self.bounding_box().top_left).draw(target)
+    }
+}
@@ -1,17 +1,48 @@
-use super::util::CharHasher;
-use crate::widget::bitmap::{Bitmap, BitmapRef};
+use super::util::{AsciiIndex, CharHasher};
+use crate::widget::bitmap::{self, Bitmap, BitmapRef, PixelColor};
+use alloc::vec;
 use alloc::vec::Vec;
 use core::mem;
+use core::num::Wrapping;
+use embedded_graphics::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics::image::GetPixel;
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::pixelcolor::BinaryColor;
 use hashbrown::HashMap;
 use miniz_oxide::inflate::{decompress_to_vec_zlib, DecompressError};
+use rustc_hash::FxBuildHasher;
 use serde::{Deserialize, Serialize};
+use spin::Once;
 use thiserror::Error;
 
+/// The character whose glyph is drawn in place of characters missing from every font, usually a
+/// box. U+FFFD REPLACEMENT CHARACTER is used since it can't be confused with real text.
+pub const NOTDEF: char = '\u{FFFD}';
+
+static SYSTEM_FONT: Once<Font> = Once::new();
+
+/// Sets the font used to render text for apps. The system font can only be set once, so if it was
+/// already set, `font` is dropped and the existing font is returned.
+pub fn init_system_font(font: Font) -> &'static Font {
+    SYSTEM_FONT.call_once(|| font)
+}
+
+/// Returns the font used to render text for apps, or `None` if it hasn't been set with
+/// [`init_system_font`] yet.
+pub fn system_font() -> Option<&'static Font> {
+    SYSTEM_FONT.get()
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "FontRepr")]
 pub struct Font {
     metrics: FontMetrics,
     map: HashMap<char, usize, CharHasher>,
     glyphs: Vec<GlyphData>,
+    kerning: HashMap<(char, char), i32, FxBuildHasher>,
+    // Derived from `map` when deserializing, so it isn't part of the serialized format.
+    #[serde(skip)]
+    ascii: AsciiIndex,
 }
 
 impl Font {
@@ -20,6 +51,8 @@ impl Font {
             metrics,
             map: HashMap::with_hasher(Default::default()),
             glyphs: Vec::new(),
+            kerning: HashMap::with_hasher(Default::default()),
+            ascii: AsciiIndex::new(),
         }
     }
 
@@ -35,8 +68,75 @@ impl Font {
         Self::from_bytes(decompressed)
     }
 
+    /// Converts the printable ASCII characters of one of embedded-graphics' monospaced fonts,
+    /// which are built into the firmware. Every glyph fills the whole line, and [`NOTDEF`] is
+    /// drawn with the font's replacement character.
+    pub fn from_mono_font(mono: &MonoFont<'_>) -> Self {
+        let Size { width, height } = mono.character_size;
+        let baseline = mono.baseline as i32;
+
+        let mut font = Self::new(FontMetrics {
+            ascent: baseline + 1,
+            descent: baseline + 1 - height as i32,
+            line_gap: 0,
+        });
+
+        let columns = mono.image.size().width / width;
+
+        for c in (' '..='~').chain([NOTDEF]) {
+            let index = mono.glyph_mapping.index(c) as u32;
+            let origin = Point::new(
+                ((index % columns) * width) as i32,
+                ((index / columns) * height) as i32,
+            );
+
+            let glyph_bitmap = |ink| {
+                let (width, height) = (width as u8, height as u8);
+                let data = vec![0; bitmap::expected_data_len(width, height)];
+                let mut glyph =
+                    Bitmap::new(width, height, data).expect("glyph should fit a bitmap");
+
+                for y in 0..height {
+                    for x in 0..width {
+                        let point = origin + Point::new(x as i32, y as i32);
+                        let color = match mono.image.pixel(point) {
+                            Some(BinaryColor::On) => ink,
+                            _ => PixelColor::Transparent,
+                        };
+
+                        glyph.set_pixel(x, y, color);
+                    }
+                }
+
+                glyph
+            };
+
+            font.set_glyph(
+                c,
+                GlyphData::new(
+                    height as i32 - (baseline + 1),
+                    (width + mono.character_spacing) as i32,
+                    height as i32,
+                    glyph_bitmap(PixelColor::Black),
+                    glyph_bitmap(PixelColor::White),
+                ),
+            );
+        }
+
+        font
+    }
+
     pub fn id(&self, c: char) -> Option<GlyphId> {
-        self.map.get(&c).copied().map(GlyphId)
+        match self.ascii.get(c) {
+            Some(index) => index.map(GlyphId),
+            None => self.map.get(&c).copied().map(GlyphId),
+        }
+    }
+
+    /// Returns the glyph used for characters that no font has a glyph for, which is the glyph for
+    /// [`NOTDEF`] if the font has one.
+    pub fn notdef(&self) -> Option<GlyphId> {
+        self.id(NOTDEF)
     }
 
     pub fn set_glyph(&mut self, c: char, glyph: GlyphData) -> Option<GlyphData> {
@@ -49,15 +149,48 @@ impl Font {
                 let index = self.glyphs.len();
                 self.glyphs.push(glyph);
                 assert!(self.map.insert(c, index).is_none());
+                self.ascii.set(c, index);
                 None
             }
         }
     }
 
+    /// Returns how much closer together `right` should be drawn when it follows `left`. Pairs
+    /// without kerning data have an adjustment of 0.
+    pub fn kerning(&self, left: char, right: char) -> i32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0)
+    }
+
+    pub fn set_kerning(&mut self, left: char, right: char, adjustment: i32) -> Option<i32> {
+        self.kerning.insert((left, right), adjustment)
+    }
+
     pub fn font_metrics(&self) -> FontMetrics {
         self.metrics
     }
 
+    /// Returns the distance between the baselines of consecutive lines.
+    pub fn line_height(&self) -> i32 {
+        self.metrics.ascent - self.metrics.descent + self.metrics.line_gap
+    }
+
+    /// Returns how far `s` advances the pen when drawn on a single line, including kerning.
+    /// Characters without a glyph are measured as [`.notdef`](Font::notdef), or skipped if the font
+    /// doesn't have one. Fallback fonts and line breaks aren't taken into account, use a
+    /// [`Layout`](super::layout::Layout) for those.
+    pub fn text_width(&self, s: &str) -> i32 {
+        let mut prev = None;
+
+        s.chars()
+            .filter_map(|c| self.id(c).or_else(|| self.notdef()).map(|id| (c, id)))
+            .map(|(c, id)| {
+                let kerning = prev.replace(c).map_or(0, |prev| self.kerning(prev, c));
+                Wrapping(self.glyphs[id.0].h_advance) - Wrapping(kerning)
+            })
+            .sum::<Wrapping<i32>>()
+            .0
+    }
+
     pub fn glyph_metrics(&self, GlyphId(id): GlyphId) -> GlyphMetrics {
         let glyph = &self.glyphs[id];
 
@@ -111,6 +244,40 @@ impl Font {
     }
 }
 
+// The serialized representation of a `Font`. Field order must match `Font`.
+#[derive(Deserialize)]
+struct FontRepr {
+    metrics: FontMetrics,
+    map: HashMap<char, usize, CharHasher>,
+    glyphs: Vec<GlyphData>,
+    kerning: HashMap<(char, char), i32, FxBuildHasher>,
+}
+
+impl From<FontRepr> for Font {
+    fn from(repr: FontRepr) -> Self {
+        let FontRepr {
+            metrics,
+            map,
+            glyphs,
+            kerning,
+        } = repr;
+
+        let mut ascii = AsciiIndex::new();
+
+        for (&c, &index) in map.iter() {
+            ascii.set(c, index);
+        }
+
+        Self {
+            metrics,
+            map,
+            glyphs,
+            kerning,
+            ascii,
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 pub struct GlyphData {
     pub y_offset: i32,
@@ -174,3 +341,63 @@ pub enum FontError {
     #[error("font deserialization error: {0}")]
     Deserialization(postcard::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::mono_font::ascii::FONT_6X10;
+
+    #[test]
+    fn mono_font_metrics() {
+        let font = Font::from_mono_font(&FONT_6X10);
+
+        assert_eq!(font.line_height(), 10);
+        assert_eq!(font.text_width("abc"), 18);
+
+        let id = font.id('A').unwrap();
+        let metrics = font.glyph_metrics(id);
+
+        assert_eq!((metrics.width, metrics.height), (6, 10));
+        // the top of the glyph is drawn at the top of the line.
+        assert_eq!(
+            font.font_metrics().ascent - metrics.height as i32 + metrics.y_offset,
+            0
+        );
+    }
+
+    #[test]
+    fn mono_font_glyphs_match_the_image() {
+        let font = Font::from_mono_font(&FONT_6X10);
+        let id = font.id('|').unwrap();
+
+        let black = font.glyph_black_bitmap(id);
+        let white = font.glyph_white_bitmap(id);
+        let mut inked = 0;
+
+        for y in 0..10 {
+            for x in 0..6 {
+                match black.get_pixel(x, y) {
+                    Some(PixelColor::Black) => {
+                        inked += 1;
+                        assert_eq!(white.get_pixel(x, y), Some(PixelColor::White));
+                    }
+                    color => {
+                        assert_eq!(color, Some(PixelColor::Transparent));
+                        assert_eq!(white.get_pixel(x, y), Some(PixelColor::Transparent));
+                    }
+                }
+            }
+        }
+
+        assert!(inked > 0);
+    }
+
+    #[test]
+    fn mono_font_notdef() {
+        let font = Font::from_mono_font(&FONT_6X10);
+
+        assert!(font.id('é').is_none());
+        assert_eq!(font.notdef(), font.id(NOTDEF));
+        assert!(font.notdef().is_some());
+    }
+}
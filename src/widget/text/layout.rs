@@ -1,11 +1,17 @@
 use super::font::{Font, FontMetrics, GlyphId, GlyphMetrics};
+use super::util::CharHasher;
 use crate::widget::bitmap::BitmapRef;
+use crate::widget::envelope;
 use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::iter;
 use core::num::Wrapping;
 use embedded_graphics::image::Image;
 use embedded_graphics::pixelcolor::BinaryColor;
-use embedded_graphics::prelude::{DrawTarget, Point};
+use embedded_graphics::prelude::{Dimensions, DrawTarget, OriginDimensions, Pixel, Point, Size};
+use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::Drawable;
+use hashbrown::HashMap;
 
 fn whitespace_wrap(
     layout: &mut Layout<'_>,
@@ -44,59 +50,171 @@ pub enum WrapMode {
     /// reached and no whitespace character is available.
     #[default]
     Both,
+
+    /// Never wrap. If a line doesn't fit within the max width, it's cut off and ends with an
+    /// ellipsis instead.
+    Truncate,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum Alignment {
+    /// Align each line with the starting position.
+    #[default]
+    Left,
+
+    /// Center each line between the starting position and the max width.
+    Center,
+
+    /// Align the end of each line with the max width.
+    Right,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
 pub struct Config {
     pub max_width: Option<i32>,
+    /// The height below the starting position that lines have to fit within. Lines that would
+    /// extend past it, and all text after them, are left out of the layout.
     pub max_height: Option<i32>,
+    /// Whether to end the last line that fits within `max_height` with an ellipsis when text is
+    /// left out.
+    pub clip_ellipsis: bool,
     pub wrap_mode: WrapMode,
+    /// How lines are aligned horizontally. Alignment is relative to `max_width`, so lines are
+    /// always left aligned if it isn't set.
+    pub alignment: Alignment,
+    /// Emboldens text by drawing each glyph a second time, one pixel to the right. Glyphs advance
+    /// one pixel further to make room for it.
+    pub bold: bool,
+    /// Slants text by shifting each row of a glyph right, by one pixel for every
+    /// [`ITALIC_SLOPE`] rows above its bottom row.
+    pub italic: bool,
 }
 
+/// How many rows it takes for synthetic italics to shift a glyph one pixel to the right.
+pub const ITALIC_SLOPE: u32 = 4;
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct PositionedGlyph<'font> {
     pub position: Point,
     pub bitmap: BitmapRef<'font>,
 }
 
+// Maps characters to the index of the font which has them and their glyph, or `None` if no font
+// does.
+type FallbackCache = RefCell<HashMap<char, Option<(usize, GlyphId)>, CharHasher>>;
+
 pub struct Layout<'font> {
     start: Point,
     current: Point,
-    font: &'font Font,
+    // the first font is the primary font. The rest are only used for characters it doesn't have.
+    fonts: Vec<&'font Font>,
     config: Config,
     glyphs: Vec<PositionedGlyph<'font>>,
+    // index of the first glyph on the current line, used to align the line once it's complete.
+    line_start: usize,
+    // whether the current line was cut off by `WrapMode::Truncate`.
+    truncated: bool,
+    // whether the layout has reached `Config::max_height`. Once set, no more text is added.
+    clipped: bool,
+    // the last character pushed on the current line, used for kerning.
+    prev_char: Option<char>,
+    bounds: Cell<Option<Rectangle>>,
+    // characters the primary font doesn't have, mapped to the index of the font that does, if
+    // any. Wrapping measures the same words more than once, and each miss searches every font.
+    fallbacks: FallbackCache,
 }
 
 impl<'font> Layout<'font> {
     pub fn new(position: Point, font: &'font Font, config: Config) -> Self {
+        Self::with_fallbacks(position, &[font], config)
+    }
+
+    /// Creates a layout which renders each character with the first font in `fonts` that has a
+    /// glyph for it. Characters which none of the fonts have are rendered with the first font's
+    /// [`.notdef`](Font::notdef) glyph. Line spacing is always taken from the first font.
+    ///
+    /// # Panics
+    /// Panics if `fonts` is empty.
+    pub fn with_fallbacks(position: Point, fonts: &[&'font Font], config: Config) -> Self {
+        assert!(!fonts.is_empty(), "a layout needs at least one font");
+
         Self {
             start: position,
             current: position,
-            font,
+            fonts: fonts.to_vec(),
             config,
             glyphs: Vec::new(),
+            line_start: 0,
+            truncated: false,
+            clipped: false,
+            prev_char: None,
+            bounds: Cell::new(None),
+            fallbacks: RefCell::new(HashMap::with_hasher(Default::default())),
         }
     }
 
     pub fn with_text<S: AsRef<str>>(&mut self, s: S, color: BinaryColor) -> &mut Self {
         let s = s.as_ref();
+        self.bounds.set(None);
 
         match self.config.wrap_mode {
             WrapMode::Whitespace => self.with_text_whitespace_wrap(s, color),
             WrapMode::Character => self.with_text_char_wrap(s, color),
             WrapMode::Both => self.with_text_both_wrap(s, color),
+            WrapMode::Truncate => self.with_text_truncate(s, color),
         }
     }
 
     pub fn clear(&mut self) {
         self.current = self.start;
         self.glyphs.clear();
+        self.line_start = 0;
+        self.truncated = false;
+        self.clipped = false;
+        self.prev_char = None;
+        self.bounds.set(None);
     }
 
     pub fn glyphs(&self) -> &[PositionedGlyph<'font>] {
         &self.glyphs
     }
 
+    /// Returns the smallest rectangle enclosing every glyph bitmap in the layout. If the layout is
+    /// empty, this is a zero sized rectangle at the starting position.
+    ///
+    /// The result is cached until the layout is changed with [`Layout::with_text`] or
+    /// [`Layout::clear`].
+    pub fn bounds(&self) -> Rectangle {
+        if let Some(bounds) = self.bounds.get() {
+            return bounds;
+        }
+
+        let bounds = self
+            .glyphs
+            .iter()
+            .map(|glyph| self.glyph_area(glyph))
+            .fold(Rectangle::new(self.start, Size::zero()), |bounds, glyph| {
+                envelope(&bounds, &glyph)
+            });
+
+        self.bounds.set(Some(bounds));
+
+        bounds
+    }
+
+    /// Returns the total height of the lines in the layout, which is the number of lines times the
+    /// font's line spacing.
+    pub fn lines_height(&self) -> u32 {
+        let height = self.current.y.abs_diff(self.start.y);
+
+        // a clipped layout never moves past its last line.
+        if self.clipped {
+            height + self.line_spacing().unsigned_abs()
+        } else {
+            height
+        }
+    }
+
     fn with_text_char_wrap(&mut self, s: &str, color: BinaryColor) -> &mut Self {
         self.with_text_internal(s, color, |data| {
             let WrapData {
@@ -104,6 +222,7 @@ impl<'font> Layout<'font> {
                 line: _,
                 index: _,
                 c: _,
+                color: _,
                 glyph_metrics,
                 line_spacing,
             } = data;
@@ -125,6 +244,7 @@ impl<'font> Layout<'font> {
                 line,
                 index,
                 c,
+                color: _,
                 glyph_metrics: _,
                 line_spacing,
             } = data;
@@ -148,6 +268,7 @@ impl<'font> Layout<'font> {
                 line,
                 index,
                 c,
+                color: _,
                 glyph_metrics,
                 line_spacing,
             } = data;
@@ -166,37 +287,120 @@ impl<'font> Layout<'font> {
         })
     }
 
+    fn with_text_truncate(&mut self, s: &str, color: BinaryColor) -> &mut Self {
+        self.with_text_internal(s, color, |data| {
+            let WrapData {
+                layout,
+                line,
+                index,
+                c: _,
+                color,
+                glyph_metrics,
+                line_spacing: _,
+            } = data;
+
+            if layout.truncated {
+                return true;
+            }
+
+            let Some(max_width) = layout.config.max_width else {
+                return false;
+            };
+
+            // the rest of the line fits, so there's nothing to truncate.
+            if layout.current.x + layout.str_h_advance(&line[index..]) < max_width {
+                return false;
+            }
+
+            let ellipsis = layout.ellipsis();
+
+            if layout.current.x + glyph_metrics.h_advance + layout.str_h_advance(ellipsis)
+                >= max_width
+            {
+                layout.push_str(ellipsis, color);
+                layout.truncated = true;
+                return true;
+            }
+
+            false
+        })
+    }
+
+    // The string appended to truncated lines. Falls back to periods if none of the fonts have an
+    // ellipsis glyph.
+    fn ellipsis(&self) -> &'static str {
+        if self.fonts.iter().any(|font| font.id('…').is_some()) {
+            "…"
+        } else {
+            "..."
+        }
+    }
+
+    // Pushes the glyphs for `s` without any wrapping.
+    fn push_str(&mut self, s: &str, color: BinaryColor) {
+        let font_metrics = self.primary_font().font_metrics();
+
+        for c in s.chars() {
+            let Some((font, id)) = self.resolve(c) else {
+                continue;
+            };
+
+            let (metrics, bitmap) = match color {
+                BinaryColor::On => font.black_glyph(id),
+                BinaryColor::Off => font.white_glyph(id),
+            };
+
+            self.push_positioned_glyph(font_metrics, metrics, bitmap);
+        }
+    }
+
     fn with_text_internal(
         &mut self,
         s: &str,
         color: BinaryColor,
         wrap: impl Fn(WrapData<'_, '_, '_>) -> bool,
     ) -> &mut Self {
-        let font_metrics = self.font.font_metrics();
-        let line_spacing = font_metrics.ascent - font_metrics.descent + font_metrics.line_gap;
+        let font_metrics = self.primary_font().font_metrics();
+        let line_spacing = self.line_spacing();
 
         let get_glyph: fn(&'_ Font, GlyphId) -> (GlyphMetrics, BitmapRef<'_>) = match color {
             BinaryColor::On => |font: &Font, id| font.black_glyph(id),
             BinaryColor::Off => |font: &Font, id| font.white_glyph(id),
         };
 
-        for mut line in s.lines() {
+        if !self.clipped && !self.line_fits(self.current.y, line_spacing) {
+            self.clipped = true;
+        }
+
+        let mut lines = s.lines().peekable();
+
+        'lines: while let Some(mut line) = lines.next() {
+            if self.clipped {
+                break;
+            }
+
             if let Some(stripped) = line.strip_suffix(|c: char| c.is_whitespace()) {
                 line = stripped;
             }
 
             for (index, c) in line.char_indices() {
-                let Some(glyph) = self.font.id(c) else {
+                if self.clipped {
+                    self.finish_clipped(color, true);
+                    break 'lines;
+                }
+
+                let Some((font, glyph)) = self.resolve(c) else {
                     continue;
                 };
 
-                let (metrics, bitmap) = get_glyph(self.font, glyph);
+                let (metrics, bitmap) = get_glyph(font, glyph);
 
                 let wrap_data = WrapData {
                     layout: self,
                     line,
                     index,
                     c,
+                    color,
                     glyph_metrics: metrics,
                     line_spacing,
                 };
@@ -207,15 +411,74 @@ impl<'font> Layout<'font> {
                     continue;
                 }
 
+                if self.clipped {
+                    self.finish_clipped(color, true);
+                    break 'lines;
+                }
+
+                if let Some(prev) = self.prev_char.replace(c) {
+                    self.current.x -= font.kerning(prev, c);
+                }
+
                 self.push_positioned_glyph(font_metrics, metrics, bitmap);
             }
 
             self.new_line(line_spacing);
+
+            if self.clipped {
+                let text_left = lines.peek().is_some();
+                self.finish_clipped(color, text_left);
+            }
         }
 
         self
     }
 
+    fn line_spacing(&self) -> i32 {
+        self.primary_font().line_height()
+    }
+
+    fn primary_font(&self) -> &'font Font {
+        self.fonts[0]
+    }
+
+    // Finds the font to render `c` with, falling back to the primary font's `.notdef` glyph.
+    fn resolve(&self, c: char) -> Option<(&'font Font, GlyphId)> {
+        let primary = self.primary_font();
+
+        if let Some(id) = primary.id(c) {
+            return Some((primary, id));
+        }
+
+        let resolved = *self.fallbacks.borrow_mut().entry(c).or_insert_with(|| {
+            self.fonts
+                .iter()
+                .enumerate()
+                .skip(1)
+                .find_map(|(index, font)| font.id(c).map(|id| (index, id)))
+                .or_else(|| primary.notdef().map(|id| (0, id)))
+        });
+
+        resolved.map(|(index, id)| (self.fonts[index], id))
+    }
+
+    fn line_fits(&self, y: i32, line_spacing: i32) -> bool {
+        match self.config.max_height {
+            Some(max_height) => y + line_spacing <= self.start.y + max_height,
+            None => true,
+        }
+    }
+
+    // Called once the line the layout stopped on is known to be the last one, since the next line
+    // wouldn't fit within the max height.
+    fn finish_clipped(&mut self, color: BinaryColor, text_left: bool) {
+        if text_left && self.config.clip_ellipsis {
+            self.push_str(self.ellipsis(), color);
+        }
+
+        self.align_line();
+    }
+
     fn push_positioned_glyph(
         &mut self,
         font_metrics: FontMetrics,
@@ -228,23 +491,88 @@ impl<'font> Layout<'font> {
 
         let positioned = PositionedGlyph { position, bitmap };
 
-        self.current.x += metrics.h_advance;
+        self.current.x += metrics.h_advance + self.bold_advance();
 
         self.glyphs.push(positioned);
     }
 
     fn str_h_advance(&self, s: &str) -> i32 {
+        let mut prev = None;
+
         s.chars()
-            .filter_map(|c| self.font.id(c))
-            .map(|id| Wrapping(self.font.glyph_metrics(id).h_advance))
+            .filter_map(|c| self.resolve(c).map(|(font, id)| (c, font, id)))
+            .map(|(c, font, id)| {
+                let kerning = prev.replace(c).map_or(0, |prev| font.kerning(prev, c));
+                Wrapping(font.glyph_metrics(id).h_advance + self.bold_advance()) - Wrapping(kerning)
+            })
             .sum::<Wrapping<i32>>()
             .0
     }
 
+    // The extra space each glyph takes up when synthetic bold is enabled.
+    fn bold_advance(&self) -> i32 {
+        self.config.bold as i32
+    }
+
+    // The area a glyph covers once synthetic bold and italics are applied.
+    fn glyph_area(&self, glyph: &PositionedGlyph<'_>) -> Rectangle {
+        let mut size = glyph.bitmap.size();
+
+        if self.config.bold {
+            size.width += 1;
+        }
+
+        if self.config.italic {
+            size.width += size.height.saturating_sub(1) / ITALIC_SLOPE;
+        }
+
+        Rectangle::new(glyph.position, size)
+    }
+
     fn new_line(&mut self, line_spacing: i32) {
+        // The current line stays open when the next one doesn't fit, so an ellipsis can still be
+        // added to it.
+        if !self.line_fits(self.current.y + line_spacing, line_spacing) {
+            self.clipped = true;
+            return;
+        }
+
+        self.align_line();
+        self.truncated = false;
+        self.prev_char = None;
+
         self.current.x = self.start.x;
         self.current.y += line_spacing;
     }
+
+    // Shifts the glyphs on the current line to match the configured alignment. The width of a line
+    // isn't known until it ends, so this is done when moving to the next line.
+    fn align_line(&mut self) {
+        let line_start = self.line_start;
+        self.line_start = self.glyphs.len();
+
+        let Some(max_width) = self.config.max_width else {
+            return;
+        };
+
+        let free_width = (max_width - self.current.x).max(0);
+
+        let offset = match self.config.alignment {
+            Alignment::Left => return,
+            Alignment::Center => free_width / 2,
+            Alignment::Right => free_width,
+        };
+
+        for glyph in &mut self.glyphs[line_start..] {
+            glyph.position.x += offset;
+        }
+    }
+}
+
+impl Dimensions for Layout<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        self.bounds()
+    }
 }
 
 impl Drawable for Layout<'_> {
@@ -256,9 +584,36 @@ impl Drawable for Layout<'_> {
     where
         D: DrawTarget<Color = Self::Color>,
     {
+        if !self.config.bold && !self.config.italic {
+            for positioned in self.glyphs() {
+                let image = Image::new(&positioned.bitmap, positioned.position);
+                image.draw(target)?;
+            }
+
+            return Ok(());
+        }
+
+        let bold = self.config.bold;
+        let italic = self.config.italic;
+
         for positioned in self.glyphs() {
-            let image = Image::new(&positioned.bitmap, positioned.position);
-            image.draw(target)?;
+            let height = positioned.bitmap.height() as i32;
+
+            let pixels = positioned
+                .bitmap
+                .pixels()
+                .flat_map(|Pixel(mut point, color)| {
+                    if italic {
+                        point.x += (height - 1 - point.y) / ITALIC_SLOPE as i32;
+                    }
+
+                    point += positioned.position;
+
+                    let emboldened = bold.then(|| Pixel(point + Point::new(1, 0), color));
+                    iter::once(Pixel(point, color)).chain(emboldened)
+                });
+
+            target.draw_iter(pixels)?;
         }
 
         Ok(())
@@ -278,11 +633,18 @@ impl<'f> Drawable for &Layout<'f> {
     }
 }
 
+impl Dimensions for &Layout<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        (**self).bounding_box()
+    }
+}
+
 struct WrapData<'l, 's, 'font> {
     layout: &'l mut Layout<'font>,
     line: &'s str,
     index: usize,
     c: char,
+    color: BinaryColor,
     glyph_metrics: GlyphMetrics,
     line_spacing: i32,
 }
@@ -0,0 +1,103 @@
+use super::font::Font;
+use super::layout::{Config, Layout};
+use core::cell::Cell;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::{Dimensions, DrawTarget, DrawTargetExt, Point};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Drawable;
+
+/// The default number of pixels a [`Marquee`] scrolls each frame.
+pub const DEFAULT_SPEED: i32 = 1;
+
+/// The default gap between the end of a [`Marquee`]'s text and the start of the next copy.
+pub const DEFAULT_GAP: i32 = 16;
+
+/// A single line of text which scrolls to the left when it's too wide for its area. Every draw
+/// moves the text by the marquee's speed, and once the end of the text has scrolled past, it
+/// comes around again after a gap. Text which fits is drawn without scrolling.
+pub struct Marquee<'font> {
+    area: Rectangle,
+    layout: Layout<'font>,
+    text_width: i32,
+    speed: i32,
+    gap: i32,
+    offset: Cell<i32>,
+}
+
+impl<'font> Marquee<'font> {
+    /// Creates a marquee showing `text` inside `area`. Line breaks in `text` aren't supported.
+    pub fn new(area: Rectangle, text: &str, font: &'font Font, color: BinaryColor) -> Self {
+        let mut layout = Layout::new(Point::zero(), font, Config::default());
+        layout.with_text(text, color);
+
+        Self {
+            area,
+            layout,
+            text_width: font.text_width(text),
+            speed: DEFAULT_SPEED,
+            gap: DEFAULT_GAP,
+            offset: Cell::new(0),
+        }
+    }
+
+    /// Sets how many pixels the text moves each time it's drawn. Negative speeds are treated as 0.
+    pub fn set_speed(&mut self, speed: i32) {
+        self.speed = speed.max(0);
+    }
+
+    /// Sets the space between the end of the text and the start of the next copy. Negative gaps
+    /// are treated as 0.
+    pub fn set_gap(&mut self, gap: i32) {
+        self.gap = gap.max(0);
+    }
+
+    /// Whether the text is too wide for the marquee's area, and scrolls.
+    pub fn scrolls(&self) -> bool {
+        self.text_width > self.area.size.width as i32
+    }
+
+    /// Moves the text back to its starting position.
+    pub fn reset(&self) {
+        self.offset.set(0);
+    }
+}
+
+impl Dimensions for Marquee<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        self.area
+    }
+}
+
+impl Drawable for Marquee<'_> {
+    type Color = BinaryColor;
+
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let mut clipped = target.clipped(&self.area);
+
+        if !self.scrolls() {
+            return self
+                .layout
+                .draw(&mut clipped.translated(self.area.top_left));
+        }
+
+        // the text repeats every `period` pixels, so drawing it twice always covers the area.
+        let period = self.text_width + self.gap;
+        let offset = self.offset.get();
+
+        for copy in 0..2 {
+            let x = self.area.top_left.x - offset + copy * period;
+            let position = Point::new(x, self.area.top_left.y);
+
+            self.layout.draw(&mut clipped.translated(position))?;
+        }
+
+        self.offset.set((offset + self.speed) % period);
+
+        Ok(())
+    }
+}
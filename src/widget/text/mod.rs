@@ -1,3 +1,4 @@
 pub mod font;
 pub mod layout;
+pub mod marquee;
 mod util;
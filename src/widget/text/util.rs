@@ -2,6 +2,8 @@ use core::hash::{BuildHasherDefault, Hasher};
 
 pub(crate) type CharHasher = BuildHasherDefault<IdentityHasherU32>;
 
+const ASCII_CHARS: usize = 128;
+
 const UNIMPLEMENTED: &str = "32 bit identity hasher only supports hashing 32 bit values";
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
@@ -64,3 +66,35 @@ impl Hasher for IdentityHasherU32 {
         unimplemented!("{}", UNIMPLEMENTED);
     }
 }
+
+/// A dense lookup table for glyph indices of ASCII characters, which make up the vast majority of
+/// text that gets rendered. Indexing an array is cheaper than hashing, even with an identity hasher.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub(crate) struct AsciiIndex([Option<usize>; ASCII_CHARS]);
+
+impl AsciiIndex {
+    pub(crate) const fn new() -> Self {
+        Self([None; ASCII_CHARS])
+    }
+
+    /// Returns `Some(index)` if `c` is an ASCII character, where `index` is the glyph index of `c`
+    /// if it exists, and `None` otherwise.
+    #[inline]
+    pub(crate) fn get(&self, c: char) -> Option<Option<usize>> {
+        self.0.get(c as usize).copied()
+    }
+
+    /// Sets the glyph index for `c`. Does nothing if `c` is not an ASCII character.
+    #[inline]
+    pub(crate) fn set(&mut self, c: char, index: usize) {
+        if let Some(slot) = self.0.get_mut(c as usize) {
+            *slot = Some(index);
+        }
+    }
+}
+
+impl Default for AsciiIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}